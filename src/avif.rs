@@ -0,0 +1,360 @@
+//! AVIF still image encoding.
+//!
+//! [`encode`] encodes a single [`Frame`] with still-picture coding (`avif` configuration flag)
+//! and writes it into AVIF (HEIF) container. Optional alpha plane is encoded as a separate
+//! auxiliary image, with neutral chroma as SVT-AV1 only accepts 4:2:0 input.
+use crate::{
+    bmff::{av1c_box, colr_box, hdr_boxes, write_box, write_full_box},
+    ffi::{
+        ColorPrimaries, ColorRange, MatrixCoefficients, SvtAv1EncConfiguration,
+        TransferCharacteristics,
+    },
+    obu::{av1_codec_config, ObuError, TemporalUnitAssembler},
+    Frame, SvtAv1EncoderConfig,
+};
+use std::io;
+
+const ALPHA_URN: &[u8] = b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha\0";
+
+/// AV1 still image produced by [`encode_frame`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedImage {
+    /// Stream header with sequence header OBU
+    pub stream_header: Vec<u8>,
+    /// Temporal unit of the image, without temporal delimiter
+    pub data: Vec<u8>,
+}
+
+/// Alpha plane of an image. Samples have the same size as in the color [`Frame`],
+/// 1 byte for 8-bit and 2 bytes (little-endian) for 10-bit input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlphaPlane<'a> {
+    pub data: &'a [u8],
+    /// Stride in samples
+    pub stride: u32,
+}
+
+/// Encode single frame as AV1 still image. Sets `avif` flag in configuration
+pub fn encode_frame(mut config: SvtAv1EncoderConfig, frame: Frame<'_>) -> io::Result<EncodedImage> {
+    config.config.avif = true;
+    let encoder = config.into_encoder().map_err(io::Error::other)?;
+    let stream_header = encoder.stream_header().map_err(io::Error::other)?;
+    encoder
+        .send_picture(frame, Some(0), true)
+        .map_err(io::Error::other)?;
+    encoder.send_eos().map_err(io::Error::other)?;
+
+    let mut assembler = TemporalUnitAssembler::new();
+    let mut image = None;
+    for packet in encoder.drain() {
+        let packet = packet.map_err(io::Error::other)?;
+        if let Some(unit) = assembler.push_packet(&packet) {
+            if !unit.data.is_empty() {
+                image.get_or_insert(unit);
+            }
+        }
+    }
+    let image = image.ok_or_else(|| io::Error::other("encoder didn't output any picture"))?;
+
+    Ok(EncodedImage {
+        stream_header,
+        data: image.data,
+    })
+}
+
+/// Encode frame and optional alpha plane and write them as AVIF file.
+///
+/// Alpha is encoded with the same configuration, except color description,
+/// which is unspecified with full range.
+pub fn encode(
+    config: SvtAv1EncoderConfig,
+    frame: Frame<'_>,
+    alpha: Option<AlphaPlane<'_>>,
+) -> io::Result<Vec<u8>> {
+    let cfg = config.config;
+    let alpha = match alpha {
+        Some(alpha) => {
+            let mut alpha_config =
+                SvtAv1EncoderConfig::try_new(cfg.source_width, cfg.source_height, None)
+                    .map_err(io::Error::other)?;
+            alpha_config.config = cfg;
+            alpha_config.config.color_primaries = ColorPrimaries::CicpCpUnspecified;
+            alpha_config.config.transfer_characteristics =
+                TransferCharacteristics::CicpTcUnspecified;
+            alpha_config.config.matrix_coefficients = MatrixCoefficients::CicpMcUnspecified;
+            alpha_config.config.color_range = ColorRange::CrFullRange;
+
+            let chroma_stride = cfg.source_width.div_ceil(2);
+            let chroma_samples = (chroma_stride * cfg.source_height.div_ceil(2)) as usize;
+            let chroma = if cfg.encoder_bit_depth > 8 {
+                let neutral = 1u16 << (cfg.encoder_bit_depth - 1);
+                neutral.to_le_bytes().repeat(chroma_samples)
+            } else {
+                vec![128; chroma_samples]
+            };
+            let frame = Frame::new(
+                alpha.data,
+                &chroma,
+                &chroma,
+                alpha.stride,
+                chroma_stride,
+                chroma_stride,
+                (alpha.data.len() + 2 * chroma.len()) as u32,
+            );
+            Some(encode_frame(alpha_config, frame)?)
+        }
+        None => None,
+    };
+    let color = encode_frame(config, frame)?;
+    Ok(write_avif(&cfg, &color, alpha.as_ref())?)
+}
+
+/// Write AVIF file with already encoded color image and optional alpha image
+pub fn write_avif(
+    config: &SvtAv1EncConfiguration,
+    color: &EncodedImage,
+    alpha: Option<&EncodedImage>,
+) -> Result<Vec<u8>, ObuError> {
+    let color_av1c = av1_codec_config(&color.stream_header)?;
+    let alpha_av1c = alpha
+        .map(|alpha| av1_codec_config(&alpha.stream_header))
+        .transpose()?;
+    let bit_depth = config.encoder_bit_depth as u8;
+
+    // Item properties, referenced from ipma by 1-based index
+    let mut ipco = Vec::new();
+    let mut count = 0;
+    let mut add_property = |write: &dyn Fn(&mut Vec<u8>)| {
+        write(&mut ipco);
+        count += 1;
+        count
+    };
+    let ispe = add_property(&|out| {
+        write_full_box(out, b"ispe", 0, 0, |out| {
+            out.extend_from_slice(&config.source_width.to_be_bytes());
+            out.extend_from_slice(&config.source_height.to_be_bytes());
+        });
+    });
+    let mut color_properties = vec![
+        (ispe, false),
+        (add_property(&|out| pixi_box(out, 3, bit_depth)), false),
+        (add_property(&|out| av1c_box(out, &color_av1c)), true),
+        (add_property(&|out| colr_box(out, config)), false),
+    ];
+    let mut hdr = Vec::new();
+    hdr_boxes(&mut hdr, config);
+    let mut pos = 0;
+    while pos < hdr.len() {
+        // Every HDR box is a separate property
+        let size = u32::from_be_bytes(hdr[pos..pos + 4].try_into().unwrap()) as usize;
+        let property = &hdr[pos..pos + size];
+        color_properties.push((add_property(&|out| out.extend_from_slice(property)), false));
+        pos += size;
+    }
+    let alpha_properties = alpha_av1c.as_ref().map(|alpha_av1c| {
+        vec![
+            (ispe, false),
+            (add_property(&|out| pixi_box(out, 1, bit_depth)), false),
+            (add_property(&|out| av1c_box(out, alpha_av1c)), true),
+            (
+                add_property(&|out| {
+                    write_full_box(out, b"auxC", 0, 0, |out| out.extend_from_slice(ALPHA_URN))
+                }),
+                true,
+            ),
+        ]
+    });
+
+    let mut items = vec![(1u16, &color.data, color_properties)];
+    if let (Some(alpha), Some(properties)) = (alpha, alpha_properties) {
+        items.push((2, &alpha.data, properties));
+    }
+
+    let mut ftyp = Vec::new();
+    write_box(&mut ftyp, b"ftyp", |out| {
+        out.extend_from_slice(b"avif");
+        out.extend_from_slice(&0u32.to_be_bytes());
+        for brand in [b"avif", b"mif1", b"miaf"] {
+            out.extend_from_slice(brand);
+        }
+    });
+
+    let mut meta = Vec::new();
+    let mut iloc_offsets = Vec::new();
+    write_full_box(&mut meta, b"meta", 0, 0, |out| {
+        write_full_box(out, b"hdlr", 0, 0, |out| {
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(b"pict");
+            out.extend_from_slice(&[0; 12]);
+            out.push(0);
+        });
+        write_full_box(out, b"pitm", 0, 0, |out| {
+            out.extend_from_slice(&1u16.to_be_bytes())
+        });
+        write_full_box(out, b"iloc", 0, 0, |out| {
+            // offset_size = 4, length_size = 4, base_offset_size = 0
+            out.extend_from_slice(&[0x44, 0x00]);
+            out.extend_from_slice(&(items.len() as u16).to_be_bytes());
+            for (id, data, _) in &items {
+                out.extend_from_slice(&id.to_be_bytes());
+                // data_reference_index and extent_count
+                out.extend_from_slice(&0u16.to_be_bytes());
+                out.extend_from_slice(&1u16.to_be_bytes());
+                iloc_offsets.push(out.len());
+                out.extend_from_slice(&0u32.to_be_bytes());
+                out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            }
+        });
+        write_full_box(out, b"iinf", 0, 0, |out| {
+            out.extend_from_slice(&(items.len() as u16).to_be_bytes());
+            for (id, _, _) in &items {
+                write_full_box(out, b"infe", 2, 0, |out| {
+                    out.extend_from_slice(&id.to_be_bytes());
+                    out.extend_from_slice(&0u16.to_be_bytes());
+                    out.extend_from_slice(b"av01");
+                    out.push(0);
+                });
+            }
+        });
+        if items.len() > 1 {
+            write_full_box(out, b"iref", 0, 0, |out| {
+                // alpha is auxiliary image of color item
+                write_box(out, b"auxl", |out| {
+                    out.extend_from_slice(&2u16.to_be_bytes());
+                    out.extend_from_slice(&1u16.to_be_bytes());
+                    out.extend_from_slice(&1u16.to_be_bytes());
+                });
+            });
+        }
+        write_box(out, b"iprp", |out| {
+            write_box(out, b"ipco", |out| out.extend_from_slice(&ipco));
+            write_full_box(out, b"ipma", 0, 0, |out| {
+                out.extend_from_slice(&(items.len() as u32).to_be_bytes());
+                for (id, _, properties) in &items {
+                    out.extend_from_slice(&id.to_be_bytes());
+                    out.push(properties.len() as u8);
+                    for (index, essential) in properties {
+                        out.push(((*essential as u8) << 7) | index);
+                    }
+                }
+            });
+        });
+    });
+
+    // iloc offsets are absolute, item data goes right after mdat header
+    let mut offset = (ftyp.len() + meta.len() + 8) as u32;
+    for ((_, data, _), pos) in items.iter().zip(iloc_offsets) {
+        meta[pos..pos + 4].copy_from_slice(&offset.to_be_bytes());
+        offset += data.len() as u32;
+    }
+
+    let mut out = ftyp;
+    out.append(&mut meta);
+    write_box(&mut out, b"mdat", |out| {
+        for (_, data, _) in &items {
+            out.extend_from_slice(data);
+        }
+    });
+    Ok(out)
+}
+
+fn pixi_box(out: &mut Vec<u8>, channels: u8, bit_depth: u8) {
+    write_full_box(out, b"pixi", 0, 0, |out| {
+        out.push(channels);
+        (0..channels).for_each(|_| out.push(bit_depth));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bmff::{read_box, read_boxes},
+        obu::tests::stream_header,
+        tests::test_config,
+    };
+
+    fn image(data: &[u8]) -> EncodedImage {
+        EncodedImage {
+            stream_header: stream_header(),
+            data: data.to_vec(),
+        }
+    }
+
+    fn find(
+        boxes: &[([u8; 4], std::ops::Range<usize>)],
+        box_type: &[u8; 4],
+    ) -> std::ops::Range<usize> {
+        boxes.iter().find(|b| &b.0 == box_type).unwrap().1.clone()
+    }
+
+    #[test]
+    fn color_only() {
+        let config = test_config(640, 480);
+        let out = write_avif(&config, &image(&[1, 2, 3, 4]), None).unwrap();
+        let top = read_boxes(&out, 0..out.len());
+        let types: Vec<_> = top.iter().map(|b| b.0).collect();
+        assert_eq!(types, [*b"ftyp", *b"meta", *b"mdat"]);
+        assert_eq!(&out[top[0].1.clone()][..4], b"avif");
+
+        // meta is a full box
+        let meta_range = top[1].1.start + 4..top[1].1.end;
+        let meta = read_boxes(&out, meta_range);
+        let types: Vec<_> = meta.iter().map(|b| b.0).collect();
+        assert_eq!(types, [*b"hdlr", *b"pitm", *b"iloc", *b"iinf", *b"iprp"]);
+
+        // iloc extent points to image data in mdat
+        let iloc = &out[find(&meta, b"iloc")];
+        assert_eq!(u16::from_be_bytes([iloc[6], iloc[7]]), 1);
+        let offset = u32::from_be_bytes(iloc[14..18].try_into().unwrap()) as usize;
+        let length = u32::from_be_bytes(iloc[18..22].try_into().unwrap()) as usize;
+        assert_eq!(out[offset..offset + length], [1, 2, 3, 4]);
+        assert_eq!(offset, top[2].1.start);
+
+        let iprp = find(&meta, b"iprp");
+        let (ipco_type, ipco) = read_box(&out, iprp.start);
+        assert_eq!(ipco_type, *b"ipco");
+        let properties: Vec<_> = read_boxes(&out, ipco.clone()).iter().map(|b| b.0).collect();
+        assert_eq!(properties, [*b"ispe", *b"pixi", *b"av1C", *b"colr"]);
+        let (_, ipma) = read_box(&out, ipco.end);
+        // version, flags, entry count, item id, association count and property indices
+        assert_eq!(out[ipma][4..], [0, 0, 0, 1, 0, 1, 4, 1, 2, 0x83, 4]);
+    }
+
+    #[test]
+    fn alpha() {
+        let mut config = test_config(640, 480);
+        config.content_light_level.max_cll = 1000;
+        let out = write_avif(&config, &image(&[1, 2, 3]), Some(&image(&[5, 6]))).unwrap();
+        let top = read_boxes(&out, 0..out.len());
+        let meta = read_boxes(&out, top[1].1.start + 4..top[1].1.end);
+
+        let iloc = &out[find(&meta, b"iloc")];
+        assert_eq!(u16::from_be_bytes([iloc[6], iloc[7]]), 2);
+        let offset = u32::from_be_bytes(iloc[28..32].try_into().unwrap()) as usize;
+        assert_eq!(out[offset..offset + 2], [5, 6]);
+
+        let iref = find(&meta, b"iref");
+        let (auxl_type, auxl) = read_box(&out, iref.start + 4);
+        assert_eq!(auxl_type, *b"auxl");
+        assert_eq!(out[auxl], [0, 2, 0, 1, 0, 1]);
+
+        let iprp = find(&meta, b"iprp");
+        let (_, ipco) = read_box(&out, iprp.start);
+        let properties = read_boxes(&out, ipco.clone());
+        let types: Vec<_> = properties.iter().map(|b| b.0).collect();
+        assert_eq!(
+            types,
+            [*b"ispe", *b"pixi", *b"av1C", *b"colr", *b"clli", *b"pixi", *b"av1C", *b"auxC"]
+        );
+        assert_eq!(
+            &out[properties[7].1.start + 4..properties[7].1.end],
+            ALPHA_URN
+        );
+        let (_, ipma) = read_box(&out, ipco.end);
+        assert_eq!(
+            out[ipma][4..],
+            [0, 0, 0, 2, 0, 1, 5, 1, 2, 0x83, 4, 5, 0, 2, 4, 1, 6, 0x87, 0x88]
+        );
+    }
+}
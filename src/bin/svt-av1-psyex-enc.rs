@@ -0,0 +1,501 @@
+//! Command line encoder, enabled by `cli` feature.
+//!
+//! Reads Y4M or raw YUV from file or stdin and writes IVF, OBU or WebM. Encoder parameters
+//! are passed as `--name value` with names accepted by `set_parameter_from_str`.
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, IsTerminal, Read, Seek, SeekFrom, Write},
+    path::Path,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+use svt_av1_psyex_enc::{
+    ffi::SvtFlags,
+    ivf::IvfWriter,
+    matroska::{DocType, MatroskaWriter},
+    obu::{ObuFormat, ObuWriter},
+    y4m::Y4mReader,
+    yuv::{PixelFormat, YuvReader},
+    Frame, Packet, SvtAv1Encoder,
+};
+
+const USAGE: &str = "\
+Usage: svt-av1-psyex-enc -i <input> -o <output> [options] [--<parameter> <value>]...
+
+Input is Y4M, or raw YUV when --width and --height are given. Use `-` for stdin.
+Output format is chosen by extension: .ivf, .obu, .webm or .mkv. Use `-` for stdout (IVF).
+
+Options:
+  -i, --input <file>      Input file
+  -o, --output <file>     Output file
+      --format <format>   Output format: ivf, obu, webm or mkv
+      --width <width>     Width of raw input
+      --height <height>   Height of raw input
+      --pix-fmt <format>  Pixel format of raw input: i420 (default), i422, i444, p010, yuv420p10le
+      --fps <rate>        Frame rate of raw input as number or fraction, 30 by default
+      --frames <count>    Encode only first frames
+  -q, --quiet             Don't print progress
+  -h, --help              Print this help
+
+Other options are encoder parameters, for example `--preset 6 --crf 30 --tune 2`.
+";
+
+/// Interval of progress updates
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Invalid command line, reported with usage hint
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+fn usage_error(message: impl Into<String>) -> Box<dyn Error> {
+    Box::new(UsageError(message.into()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Ivf,
+    Obu,
+    WebM,
+    Matroska,
+}
+
+impl OutputFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ivf" => Some(Self::Ivf),
+            "obu" => Some(Self::Obu),
+            "webm" => Some(Self::WebM),
+            "mkv" => Some(Self::Matroska),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    input: Option<String>,
+    output: Option<String>,
+    format: Option<OutputFormat>,
+    width: Option<u32>,
+    height: Option<u32>,
+    pixel_format: Option<PixelFormat>,
+    /// Frame rate numerator and denominator
+    frame_rate: Option<(u32, u32)>,
+    frames: Option<u64>,
+    quiet: bool,
+    help: bool,
+    /// Encoder parameters in command line order
+    parameters: Vec<(String, String)>,
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, Box<dyn Error>> {
+    value
+        .parse()
+        .map_err(|_| usage_error(format!("invalid value {value:?} for {option}")))
+}
+
+fn parse_pixel_format(value: &str) -> Option<PixelFormat> {
+    match value.to_ascii_lowercase().as_str() {
+        "i420" | "yuv420p" => Some(PixelFormat::I420),
+        "i422" | "yuv422p" => Some(PixelFormat::I422),
+        "i444" | "yuv444p" => Some(PixelFormat::I444),
+        "p010" => Some(PixelFormat::P010),
+        "yuv420p10le" => Some(PixelFormat::Yuv420p10le),
+        _ => None,
+    }
+}
+
+fn parse_frame_rate(value: &str) -> Option<(u32, u32)> {
+    let (numerator, denominator) = value.split_once('/').unwrap_or((value, "1"));
+    let rate = (numerator.parse().ok()?, denominator.parse().ok()?);
+    (rate.0 > 0 && rate.1 > 0).then_some(rate)
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            let (option, inline_value) = match arg.split_once('=') {
+                Some((option, value)) if arg.starts_with("--") => (option.to_owned(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            match option.as_str() {
+                "-h" | "--help" => {
+                    parsed.help = true;
+                    continue;
+                }
+                "-q" | "--quiet" => {
+                    parsed.quiet = true;
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(name) = option.strip_prefix("--").or(option.strip_prefix('-')) else {
+                return Err(usage_error(format!("unexpected argument {arg:?}")));
+            };
+            let value = match inline_value {
+                Some(value) => value.to_owned(),
+                None => args
+                    .next()
+                    .ok_or_else(|| usage_error(format!("missing value for {option}")))?,
+            };
+            match name {
+                "i" | "input" => parsed.input = Some(value),
+                "o" | "output" => parsed.output = Some(value),
+                "format" => {
+                    parsed.format =
+                        Some(OutputFormat::parse(&value).ok_or_else(|| {
+                            usage_error(format!("unknown output format {value:?}"))
+                        })?);
+                }
+                "width" => parsed.width = Some(parse_number(&option, &value)?),
+                "height" => parsed.height = Some(parse_number(&option, &value)?),
+                "pix-fmt" => {
+                    parsed.pixel_format =
+                        Some(parse_pixel_format(&value).ok_or_else(|| {
+                            usage_error(format!("unknown pixel format {value:?}"))
+                        })?);
+                }
+                "fps" => {
+                    parsed.frame_rate = Some(
+                        parse_frame_rate(&value)
+                            .ok_or_else(|| usage_error(format!("invalid frame rate {value:?}")))?,
+                    );
+                }
+                "frames" => parsed.frames = Some(parse_number(&option, &value)?),
+                _ if option.starts_with("--") => parsed.parameters.push((name.to_owned(), value)),
+                _ => return Err(usage_error(format!("unknown option {option}"))),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn output_format(&self, output: &str) -> Result<OutputFormat, Box<dyn Error>> {
+        if let Some(format) = self.format {
+            return Ok(format);
+        }
+        if output == "-" {
+            return Ok(OutputFormat::Ivf);
+        }
+        Path::new(output)
+            .extension()
+            .and_then(|extension| OutputFormat::parse(&extension.to_string_lossy()))
+            .ok_or_else(|| {
+                usage_error(format!(
+                    "can't choose output format of {output:?}, use .ivf, .obu, .webm or .mkv extension or --format"
+                ))
+            })
+    }
+}
+
+type InputReader = BufReader<Box<dyn Read>>;
+
+enum Input {
+    Y4m(Y4mReader<InputReader>),
+    Raw(YuvReader<InputReader>),
+}
+
+impl Input {
+    fn open(args: &Args, path: &str) -> Result<Self, Box<dyn Error>> {
+        let reader: Box<dyn Read> = if path == "-" {
+            Box::new(io::stdin())
+        } else {
+            let file = File::open(path).map_err(|err| format!("failed to open {path:?}: {err}"))?;
+            Box::new(file)
+        };
+        let reader = BufReader::with_capacity(1 << 20, reader);
+        match (args.width, args.height) {
+            (Some(width), Some(height)) => {
+                let format = args.pixel_format.unwrap_or(PixelFormat::I420);
+                let (numerator, denominator) = args.frame_rate.unwrap_or((30, 1));
+                Ok(Self::Raw(YuvReader::new(
+                    reader,
+                    width,
+                    height,
+                    format,
+                    numerator,
+                    denominator,
+                )))
+            }
+            (None, None) => {
+                let reader = Y4mReader::new(reader)
+                    .map_err(|err| format!("failed to read {path:?}: {err}"))?;
+                Ok(Self::Y4m(reader))
+            }
+            _ => Err(usage_error("raw input needs both --width and --height")),
+        }
+    }
+
+    fn read_frame(&mut self) -> Result<Option<Frame<'_>>, Box<dyn Error>> {
+        match self {
+            Self::Y4m(reader) => Ok(reader.read_frame()?),
+            Self::Raw(reader) => Ok(reader.read_frame()?),
+        }
+    }
+}
+
+/// Output file or stdout
+enum OutputFile {
+    File(BufWriter<File>),
+    Stdout(io::Stdout),
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.write(buf),
+            Self::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(file) => file.flush(),
+            Self::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
+
+impl Seek for OutputFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::File(file) => file.seek(pos),
+            Self::Stdout(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "stdout isn't seekable",
+            )),
+        }
+    }
+}
+
+enum Output {
+    /// IVF writer and whether frame count can be stored in header
+    Ivf(IvfWriter<OutputFile>, bool),
+    Obu(ObuWriter<OutputFile>),
+    Matroska(MatroskaWriter<OutputFile>),
+}
+
+impl Output {
+    fn create(
+        path: &str,
+        format: OutputFormat,
+        encoder: &SvtAv1Encoder,
+    ) -> Result<Self, Box<dyn Error>> {
+        let seekable = path != "-";
+        let file = if path == "-" {
+            if matches!(format, OutputFormat::WebM | OutputFormat::Matroska) {
+                return Err(usage_error(
+                    "WebM and Matroska output can't be written to stdout",
+                ));
+            }
+            OutputFile::Stdout(io::stdout())
+        } else {
+            let file =
+                File::create(path).map_err(|err| format!("failed to create {path:?}: {err}"))?;
+            OutputFile::File(BufWriter::with_capacity(1 << 20, file))
+        };
+        Ok(match format {
+            OutputFormat::Ivf => Self::Ivf(IvfWriter::new(file, encoder.config())?, seekable),
+            OutputFormat::Obu => Self::Obu(ObuWriter::new(file, ObuFormat::Section5, true)),
+            OutputFormat::WebM => {
+                Self::Matroska(MatroskaWriter::new(file, encoder, DocType::WebM)?)
+            }
+            OutputFormat::Matroska => {
+                Self::Matroska(MatroskaWriter::new(file, encoder, DocType::Matroska)?)
+            }
+        })
+    }
+
+    fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        match self {
+            Self::Ivf(writer, _) => writer.write_packet(packet),
+            Self::Obu(writer) => writer.write_packet(packet),
+            Self::Matroska(writer) => writer.write_packet(packet),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        let _ = match self {
+            Self::Ivf(writer, true) => writer.finish_with_frame_count()?,
+            Self::Ivf(writer, false) => writer.finish()?,
+            Self::Obu(writer) => writer.finish()?,
+            Self::Matroska(writer) => writer.finish()?,
+        };
+        Ok(())
+    }
+}
+
+/// Progress line on stderr, updated in place when stderr is terminal
+struct Progress {
+    quiet: bool,
+    live: bool,
+    /// Progress line was printed without newline
+    line_open: bool,
+    start: Instant,
+    last_update: Instant,
+    frame_rate: f64,
+    frames: u64,
+    bytes: u64,
+}
+
+impl Progress {
+    fn new(quiet: bool, frame_rate: f64) -> Self {
+        let now = Instant::now();
+        Self {
+            quiet,
+            live: !quiet && io::stderr().is_terminal(),
+            line_open: false,
+            start: now,
+            last_update: now,
+            frame_rate,
+            frames: 0,
+            bytes: 0,
+        }
+    }
+
+    fn packet(&mut self, packet: &Packet) {
+        self.bytes += packet.len() as u64;
+        if !packet.is_empty() && !packet.flags.contains(SvtFlags::BUFFERFLAG_IS_ALT_REF) {
+            self.frames += 1;
+        }
+        if self.live && self.last_update.elapsed() >= PROGRESS_INTERVAL {
+            self.last_update = Instant::now();
+            eprint!("\r{}", self.status());
+            self.line_open = true;
+        }
+    }
+
+    fn status(&self) -> String {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let fps = self.frames as f64 / elapsed.max(f64::EPSILON);
+        let kbps = if self.frames == 0 {
+            0.0
+        } else {
+            self.bytes as f64 * 8.0 * self.frame_rate / self.frames as f64 / 1000.0
+        };
+        format!(
+            "Encoding: {} frames, {fps:.2} fps, {kbps:.2} kbps",
+            self.frames
+        )
+    }
+
+    fn finish(&mut self) {
+        if !self.quiet {
+            eprintln!(
+                "\r{}, {:.2} s",
+                self.status(),
+                self.start.elapsed().as_secs_f64()
+            );
+            self.line_open = false;
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        // Error message goes on its own line
+        if self.line_open {
+            eprintln!();
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let input_path = args
+        .input
+        .as_deref()
+        .ok_or_else(|| usage_error("missing --input"))?;
+    let output_path = args
+        .output
+        .as_deref()
+        .ok_or_else(|| usage_error("missing --output"))?;
+    let format = args.output_format(output_path)?;
+
+    let mut input = Input::open(args, input_path)?;
+    let mut config = match &input {
+        Input::Y4m(reader) => reader.header().encoder_config(None),
+        Input::Raw(reader) => reader.encoder_config(None),
+    }
+    .map_err(|err| format!("can't encode {input_path:?}: {err}"))?;
+    for (name, value) in &args.parameters {
+        config
+            .set_parameter_from_str(name, value)
+            .map_err(|err| format!("invalid encoder parameter --{name} {value}: {err}"))?;
+    }
+    let frame_rate =
+        config.config.frame_rate_numerator as f64 / config.config.frame_rate_denominator as f64;
+    let encoder = config
+        .into_encoder()
+        .map_err(|err| format!("failed to initialize encoder: {err}"))?;
+    let mut output = Output::create(output_path, format, &encoder)?;
+    let write_error = |err: io::Error| format!("failed to write {output_path:?}: {err}");
+
+    let mut progress = Progress::new(args.quiet, frame_rate);
+    let mut sent = 0;
+    while args.frames.is_none_or(|limit| sent < limit) {
+        let Some(frame) = input
+            .read_frame()
+            .map_err(|err| format!("failed to read frame {sent} of {input_path:?}: {err}"))?
+        else {
+            break;
+        };
+        encoder
+            .send_picture(frame, Some(sent as i64), false)
+            .map_err(|err| format!("failed to encode frame {sent}: {err}"))?;
+        sent += 1;
+        for packet in encoder.packets() {
+            let packet = packet.map_err(|err| format!("failed to receive packet: {err}"))?;
+            output.write_packet(&packet).map_err(write_error)?;
+            progress.packet(&packet);
+        }
+    }
+    encoder
+        .send_eos()
+        .map_err(|err| format!("failed to finish encoding: {err}"))?;
+    for packet in encoder.drain() {
+        let packet = packet.map_err(|err| format!("failed to receive packet: {err}"))?;
+        output.write_packet(&packet).map_err(write_error)?;
+        progress.packet(&packet);
+    }
+    output.finish().map_err(write_error)?;
+    encoder
+        .close()
+        .map_err(|err| format!("failed to close encoder: {err}"))?;
+    progress.finish();
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) if args.help => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) if err.is::<UsageError>() => {
+            eprintln!("error: {err}\n\nRun with --help for usage");
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
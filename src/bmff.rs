@@ -0,0 +1,142 @@
+//! Helpers for writing ISO base media file format (ISO/IEC 14496-12) boxes
+use crate::ffi::{ColorRange, SvtAv1EncConfiguration};
+
+/// Append box with size computed from content written by `body`
+pub(crate) fn write_box(out: &mut Vec<u8>, box_type: &[u8; 4], body: impl FnOnce(&mut Vec<u8>)) {
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(box_type);
+    body(out);
+    let size = (out.len() - start) as u32;
+    out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+/// Append full box, that is box with version and flags
+pub(crate) fn write_full_box(
+    out: &mut Vec<u8>,
+    box_type: &[u8; 4],
+    version: u8,
+    flags: u32,
+    body: impl FnOnce(&mut Vec<u8>),
+) {
+    write_box(out, box_type, |out| {
+        out.push(version);
+        out.extend_from_slice(&flags.to_be_bytes()[1..]);
+        body(out);
+    });
+}
+
+/// `av1C` box with `AV1CodecConfigurationRecord` built by [`av1_codec_config`]
+///
+/// [`av1_codec_config`]: crate::obu::av1_codec_config
+pub(crate) fn av1c_box(out: &mut Vec<u8>, av1c: &[u8]) {
+    write_box(out, b"av1C", |out| out.extend_from_slice(av1c));
+}
+
+/// `colr` box of `nclx` type with color description from encoder configuration
+pub(crate) fn colr_box(out: &mut Vec<u8>, config: &SvtAv1EncConfiguration) {
+    write_box(out, b"colr", |out| {
+        out.extend_from_slice(b"nclx");
+        out.extend_from_slice(&(config.color_primaries as u16).to_be_bytes());
+        out.extend_from_slice(&(config.transfer_characteristics as u16).to_be_bytes());
+        out.extend_from_slice(&(config.matrix_coefficients as u16).to_be_bytes());
+        let full_range = config.color_range == ColorRange::CrFullRange;
+        out.push((full_range as u8) << 7);
+    });
+}
+
+/// `mdcv` and `clli` boxes, if mastering display and content light level are set in configuration
+pub(crate) fn hdr_boxes(out: &mut Vec<u8>, config: &SvtAv1EncConfiguration) {
+    let md = config.mastering_display;
+    if md.max_luma != 0 {
+        write_box(out, b"mdcv", |out| {
+            // Chromaticity is 0.16 fixed point in configuration and 0.00002 units in mdcv
+            let chroma = |value: u16| ((value as u32 * 50000 + (1 << 15)) >> 16) as u16;
+            for point in [md.g, md.b, md.r, md.white_point] {
+                out.extend_from_slice(&chroma(point.x).to_be_bytes());
+                out.extend_from_slice(&chroma(point.y).to_be_bytes());
+            }
+            // Luminance is 24.8 and 18.14 fixed point in configuration and 0.0001 cd/m2 in mdcv
+            let max_luma = (md.max_luma as u64 * 10000) >> 8;
+            let min_luma = (md.min_luma as u64 * 10000) >> 14;
+            out.extend_from_slice(&(max_luma as u32).to_be_bytes());
+            out.extend_from_slice(&(min_luma as u32).to_be_bytes());
+        });
+    }
+    let cll = config.content_light_level;
+    if cll.max_cll != 0 || cll.max_fall != 0 {
+        write_box(out, b"clli", |out| {
+            out.extend_from_slice(&cll.max_cll.to_be_bytes());
+            out.extend_from_slice(&cll.max_fall.to_be_bytes());
+        });
+    }
+}
+
+/// Read box header at `pos`, returns box type and content range. Used by tests to walk written files
+#[cfg(test)]
+pub(crate) fn read_box(data: &[u8], pos: usize) -> ([u8; 4], std::ops::Range<usize>) {
+    let mut size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+    let box_type = data[pos + 4..pos + 8].try_into().unwrap();
+    let mut start = pos + 8;
+    if size == 1 {
+        size = u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap()) as usize;
+        start += 8;
+    }
+    (box_type, start..pos + size)
+}
+
+/// Read all boxes in `range`
+#[cfg(test)]
+pub(crate) fn read_boxes(
+    data: &[u8],
+    range: std::ops::Range<usize>,
+) -> Vec<([u8; 4], std::ops::Range<usize>)> {
+    let mut pos = range.start;
+    let mut out = Vec::new();
+    while pos < range.end {
+        let (box_type, body) = read_box(data, pos);
+        pos = body.end;
+        out.push((box_type, body));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_config;
+
+    #[test]
+    fn box_size() {
+        let mut out = Vec::new();
+        write_full_box(&mut out, b"test", 1, 0x020001, |out| out.push(0xAA));
+        assert_eq!(out, b"\0\0\0\x0dtest\x01\x02\x00\x01\xAA");
+    }
+
+    #[test]
+    fn hdr() {
+        let mut config = test_config(64, 64);
+        let mut out = Vec::new();
+        hdr_boxes(&mut out, &config);
+        assert!(out.is_empty());
+
+        // BT.2020 green primary, 1000 and 0.005 cd/m2
+        config.mastering_display.g.x = (0.170 * 65536.0) as u16;
+        config.mastering_display.max_luma = 1000 << 8;
+        config.mastering_display.min_luma = (0.005 * 16384.0) as u32;
+        config.content_light_level.max_cll = 1000;
+        config.content_light_level.max_fall = 400;
+        hdr_boxes(&mut out, &config);
+        let boxes = read_boxes(&out, 0..out.len());
+        assert_eq!(boxes[0].0, *b"mdcv");
+        let mdcv = &out[boxes[0].1.clone()];
+        assert_eq!(u16::from_be_bytes([mdcv[0], mdcv[1]]), 8500);
+        assert_eq!(
+            u32::from_be_bytes(mdcv[16..20].try_into().unwrap()),
+            10_000_000
+        );
+        assert_eq!(u32::from_be_bytes(mdcv[20..24].try_into().unwrap()), 49);
+        assert_eq!(boxes[1].0, *b"clli");
+        assert_eq!(out[boxes[1].1.clone()], [0x03, 0xE8, 0x01, 0x90]);
+    }
+}
@@ -0,0 +1,416 @@
+//! Chunked encoding, several encoders working on different parts of input at once.
+//!
+//! Input is split into chunks at scene changes found by [`SceneDetector`] with [`split_scenes`],
+//! or by a list of frame ranges. [`ChunkedEncoder`] encodes chunks in parallel, each by its own
+//! [`SvtAv1Encoder`] with `level_of_parallelism` lowered to share cores, and outputs packets of
+//! all chunks in order as one bitstream. Every chunk starts with a key frame and sequence header, which
+//! must be the same for all chunks. Packet timestamps are frame numbers of input.
+//!
+//! ```no_run
+//! # use std::{fs::File, sync::Mutex};
+//! # use svt_av1_psyex_enc::{chunked::{split_scenes, ChunkedEncoder}, obu::TemporalUnitAssembler};
+//! # use svt_av1_psyex_enc::{scene::SceneOptions, yuv::{PixelFormat, YuvReader}};
+//! let file = File::open("capture.yuv").unwrap();
+//! let source = Mutex::new(YuvReader::new(file, 3840, 2160, PixelFormat::P010, 24, 1));
+//! let chunks = split_scenes(&source, &SceneOptions::default()).unwrap();
+//!
+//! let make_config = || source.lock().unwrap().encoder_config(Some(4)).unwrap();
+//! let encoder = ChunkedEncoder::new(make_config, 4);
+//! let mut assembler = TemporalUnitAssembler::new();
+//! encoder.encode(&source, &chunks, |packet| {
+//!     if let Some(unit) = assembler.push(&packet, packet.flags, packet.pts, packet.pic_type) {
+//!         // write temporal unit
+//!     }
+//!     Ok(())
+//! }).unwrap();
+//! ```
+//!
+//! [`SvtAv1Encoder`]: crate::SvtAv1Encoder
+//! [`SceneDetector`]: crate::scene::SceneDetector
+use crate::{
+    ffi::{ErrorType, SvtFlags},
+    scene::{SceneDetector, SceneOptions},
+    yuv::{PlanarLayout, YuvReader},
+    OwnedPacket, SvtAv1EncoderConfig,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Read, Seek},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Condvar, Mutex,
+    },
+    thread,
+};
+
+/// Input with random access to frames, shared by encoder threads
+pub trait FrameSource: Sync {
+    /// Layout of frames
+    fn layout(&self) -> PlanarLayout;
+
+    /// Number of frames
+    fn frame_count(&self) -> io::Result<u64>;
+
+    /// Copy frame with index `index` into `buffer` of [`PlanarLayout::frame_size`] bytes
+    fn read_frame(&self, index: u64, buffer: &mut [u8]) -> io::Result<()>;
+}
+
+/// Frames are read one at a time, each thread seeks to its frame
+impl<R: Read + Seek + Send> FrameSource for Mutex<YuvReader<R>> {
+    fn layout(&self) -> PlanarLayout {
+        *self.lock().unwrap().layout()
+    }
+
+    fn frame_count(&self) -> io::Result<u64> {
+        self.lock().unwrap().frame_count()
+    }
+
+    fn read_frame(&self, index: u64, buffer: &mut [u8]) -> io::Result<()> {
+        let mut reader = self.lock().unwrap();
+        reader.seek_frame(index)?;
+        let frame = reader
+            .read_frame()?
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        // Planes of reader's frame are tightly packed
+        let (luma, chroma) = buffer.split_at_mut(frame.luma.len());
+        let (cb, cr) = chroma.split_at_mut(frame.cb.len());
+        luma.copy_from_slice(frame.luma);
+        cb.copy_from_slice(frame.cb);
+        cr.copy_from_slice(frame.cr);
+        Ok(())
+    }
+}
+
+/// Split all frames of source into chunks starting at key frames chosen by [`SceneDetector`],
+/// so chunk lengths are within key frame intervals of `options`
+pub fn split_scenes<S: FrameSource + ?Sized>(
+    source: &S,
+    options: &SceneOptions,
+) -> io::Result<Vec<Range<u64>>> {
+    let layout = source.layout();
+    let frame_count = source.frame_count()?;
+    let mut detector = SceneDetector::new(layout, *options);
+    let mut buffer = vec![0; layout.frame_size()];
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for index in 0..frame_count {
+        source.read_frame(index, &mut buffer)?;
+        if detector.push(&layout.frame(&buffer), false) && index > start {
+            chunks.push(start..index);
+            start = index;
+        }
+    }
+    if start < frame_count {
+        chunks.push(start..frame_count);
+    }
+    Ok(chunks)
+}
+
+/// Error of chunked encoding
+#[derive(Debug)]
+pub enum ChunkError {
+    /// Chunks are empty, overlapping or not in ascending order
+    InvalidChunks,
+    /// Encoder configuration doesn't match layout of source frames
+    LayoutMismatch,
+    /// Reading frame of chunk failed
+    Read { chunk: usize, source: io::Error },
+    /// Encoder of chunk returned error
+    Encoder { chunk: usize, error: ErrorType },
+    /// Sequence header of chunk differs from the first one, so chunks can't be joined
+    SequenceHeaderMismatch { chunk: usize },
+    /// Packet output returned error
+    Write(io::Error),
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidChunks => {
+                f.write_str("chunks must be non-empty, non-overlapping and in ascending order")
+            }
+            Self::LayoutMismatch => {
+                f.write_str("encoder configuration doesn't match layout of source frames")
+            }
+            Self::Read { chunk, source } => {
+                write!(f, "failed to read frame of chunk {chunk}: {source}")
+            }
+            Self::Encoder { chunk, error } => write!(f, "encoder of chunk {chunk} failed: {error}"),
+            Self::SequenceHeaderMismatch { chunk } => write!(
+                f,
+                "sequence header of chunk {chunk} differs from the first chunk"
+            ),
+            Self::Write(err) => write!(f, "failed to write packet: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read { source, .. } => Some(source),
+            Self::Encoder { error, .. } => Some(error),
+            Self::Write(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Encoded chunk: sequence header and packets
+type ChunkResult = Result<(Vec<u8>, Vec<OwnedPacket>), ChunkError>;
+
+/// Number of chunks passed to output, workers wait for it before starting chunks too far ahead
+#[derive(Default)]
+struct Progress {
+    output: Mutex<usize>,
+    changed: Condvar,
+}
+
+/// Encoder of chunks in parallel, see [module](self) documentation
+#[derive(Debug)]
+pub struct ChunkedEncoder<F> {
+    make_config: F,
+    workers: usize,
+    level_of_parallelism: u32,
+}
+
+impl<F: Fn() -> SvtAv1EncoderConfig + Sync> ChunkedEncoder<F> {
+    /// Create encoder running `workers` encoders at once, with configurations created by
+    /// `make_config`. Level of parallelism of each encoder is chosen from number of cores per encoder
+    pub fn new(make_config: F, workers: usize) -> Self {
+        let workers = workers.max(1);
+        let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+        let cores_per_worker = (cores / workers).max(1);
+        // One level per doubling of cores, levels 1-6 are supported
+        let level = cores_per_worker.next_power_of_two().trailing_zeros() + 1;
+        Self {
+            make_config,
+            workers,
+            level_of_parallelism: level.min(6),
+        }
+    }
+
+    /// Override level of parallelism of each encoder
+    pub fn set_level_of_parallelism(&mut self, level: u32) -> &mut Self {
+        self.level_of_parallelism = level;
+        self
+    }
+
+    pub fn level_of_parallelism(&self) -> u32 {
+        self.level_of_parallelism
+    }
+
+    /// Encode chunks and pass packets to `output` in bitstream order. Packet timestamps are
+    /// frame numbers of source, only the last packet has EOS flag. Returns sequence header
+    /// shared by all chunks. On error, encoders of other chunks finish their current chunk first.
+    ///
+    /// Chunk isn't started until the one `workers` before it is passed to `output`, so at most
+    /// `workers` finished chunks are kept in memory while waiting for a slow one
+    pub fn encode<S: FrameSource + ?Sized>(
+        &self,
+        source: &S,
+        chunks: &[Range<u64>],
+        mut output: impl FnMut(OwnedPacket) -> io::Result<()>,
+    ) -> Result<Vec<u8>, ChunkError> {
+        let ascending = chunks.windows(2).all(|pair| pair[0].end <= pair[1].start);
+        if chunks.iter().any(Range::is_empty) || !ascending {
+            return Err(ChunkError::InvalidChunks);
+        }
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let progress = Progress::default();
+        let (results, received) = mpsc::channel::<(usize, ChunkResult)>();
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers.min(chunks.len()) {
+                let results = results.clone();
+                let (next, failed, progress) = (&next, &failed, &progress);
+                let _ = scope.spawn(move || loop {
+                    let chunk = next.fetch_add(1, Ordering::Relaxed);
+                    if chunk >= chunks.len() || failed.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let output = progress.output.lock().unwrap();
+                    let _output = progress
+                        .changed
+                        .wait_while(output, |output| {
+                            chunk >= *output + self.workers && !failed.load(Ordering::Relaxed)
+                        })
+                        .unwrap();
+                    if failed.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let result = self.encode_chunk(source, chunk, chunks[chunk].clone());
+                    if results.send((chunk, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(results);
+
+            let result = self.join(received, chunks.len(), &progress, &mut output);
+            if result.is_err() {
+                // Under lock, so waiting workers can't miss it
+                let _output = progress.output.lock().unwrap();
+                failed.store(true, Ordering::Relaxed);
+                progress.changed.notify_all();
+            }
+            result
+        })
+    }
+
+    /// Output chunks in order as they are finished
+    fn join(
+        &self,
+        received: mpsc::Receiver<(usize, ChunkResult)>,
+        count: usize,
+        progress: &Progress,
+        output: &mut impl FnMut(OwnedPacket) -> io::Result<()>,
+    ) -> Result<Vec<u8>, ChunkError> {
+        let mut finished = BTreeMap::new();
+        let mut next = 0;
+        let mut sequence_header = None;
+        for (chunk, result) in received {
+            let _ = finished.insert(chunk, result);
+            while let Some(result) = finished.remove(&next) {
+                let (header, packets) = result?;
+                match &sequence_header {
+                    None => sequence_header = Some(header),
+                    Some(first) if *first != header => {
+                        return Err(ChunkError::SequenceHeaderMismatch { chunk: next })
+                    }
+                    Some(_) => {}
+                }
+                let last = next + 1 == count;
+                for mut packet in packets {
+                    if !last {
+                        packet.flags.remove(SvtFlags::BUFFERFLAG_EOS);
+                    }
+                    output(packet).map_err(ChunkError::Write)?;
+                }
+                next += 1;
+                *progress.output.lock().unwrap() = next;
+                progress.changed.notify_all();
+            }
+        }
+        Ok(sequence_header.unwrap_or_default())
+    }
+
+    fn encode_chunk<S: FrameSource + ?Sized>(
+        &self,
+        source: &S,
+        chunk: usize,
+        frames: Range<u64>,
+    ) -> ChunkResult {
+        let encoder_error = |error| ChunkError::Encoder { chunk, error };
+        let layout = source.layout();
+        let mut config = (self.make_config)();
+        config.config.level_of_parallelism = self.level_of_parallelism;
+        let config_layout = PlanarLayout {
+            width: config.config.source_width,
+            height: config.config.source_height,
+            color_format: config.config.encoder_color_format,
+            bit_depth: config.config.encoder_bit_depth,
+        };
+        if config_layout != layout {
+            return Err(ChunkError::LayoutMismatch);
+        }
+        let encoder = config.into_encoder().map_err(encoder_error)?;
+        let header = encoder.stream_header().map_err(encoder_error)?;
+
+        let mut buffer = vec![0; layout.frame_size()];
+        let mut packets = Vec::new();
+        for (pts, index) in frames.clone().enumerate() {
+            source
+                .read_frame(index, &mut buffer)
+                .map_err(|source| ChunkError::Read { chunk, source })?;
+            encoder
+                .send_picture(layout.frame(&buffer), Some(pts as i64), false)
+                .map_err(encoder_error)?;
+            for packet in encoder.packets() {
+                packets.push(OwnedPacket::from(packet.map_err(encoder_error)?));
+            }
+        }
+        encoder.send_eos().map_err(encoder_error)?;
+        for packet in encoder.drain() {
+            packets.push(OwnedPacket::from(packet.map_err(encoder_error)?));
+        }
+        encoder.close().map_err(encoder_error)?;
+
+        // Empty EOS packet carries no frame
+        packets.retain(|packet| !packet.is_empty());
+        for packet in &mut packets {
+            packet.pts += frames.start as i64;
+        }
+        if let Some(packet) = packets.last_mut() {
+            packet.flags.insert(SvtFlags::BUFFERFLAG_EOS);
+        }
+        Ok((header, packets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::ColorFormat;
+    use std::io::Cursor;
+
+    fn source(frames: &[u8]) -> Mutex<YuvReader<Cursor<Vec<u8>>>> {
+        let layout = PlanarLayout {
+            width: 16,
+            height: 16,
+            color_format: ColorFormat::YUV420,
+            bit_depth: 8,
+        };
+        let data = frames
+            .iter()
+            .flat_map(|&luma| {
+                let mut frame = vec![luma; 16 * 16];
+                frame.resize(layout.frame_size(), 128);
+                frame
+            })
+            .collect();
+        Mutex::new(YuvReader::new(
+            Cursor::new(data),
+            16,
+            16,
+            crate::yuv::PixelFormat::I420,
+            30,
+            1,
+        ))
+    }
+
+    #[test]
+    fn split() {
+        // Scenes of 5, 2 and 9 frames
+        let mut frames = vec![20; 5];
+        frames.extend([200; 2]);
+        frames.extend([90; 9]);
+        let source = source(&frames);
+        let mut buffer = vec![0; source.layout().frame_size()];
+        source.read_frame(5, &mut buffer).unwrap();
+        assert_eq!(buffer[0], 200);
+        assert_eq!(buffer[16 * 16], 128);
+
+        let mut options = SceneOptions {
+            threshold: 0.1,
+            min_interval: 1,
+            max_interval: 100,
+        };
+        assert_eq!(
+            split_scenes(&source, &options).unwrap(),
+            [0..5, 5..7, 7..16]
+        );
+        // Second change is too close to the first one
+        options.min_interval = 3;
+        assert_eq!(split_scenes(&source, &options).unwrap(), [0..5, 5..16]);
+        // Change at frame 5 is too close to forced split at frame 4
+        options.max_interval = 4;
+        assert_eq!(
+            split_scenes(&source, &options).unwrap(),
+            [0..4, 4..7, 7..11, 11..15, 15..16]
+        );
+    }
+}
@@ -0,0 +1,638 @@
+//! Conversion of packed, semi-planar and RGB pictures into planar layout accepted by encoder.
+//!
+//! [`Converter`] owns output buffer and row scratch, so converting a frame doesn't allocate.
+//! Inner loops work on whole rows with fixed-size chunks, so compiler is able to vectorize them.
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::convert::{Converter, InputFormat};
+//! # let nv12 = vec![0; 1920 * 1080 * 3 / 2];
+//! let mut converter = Converter::new(InputFormat::Nv12, 1920, 1080);
+//! let mut cfg = svt_av1_psyex_enc::SvtAv1EncoderConfig::new(1920, 1080, None);
+//! converter.apply(&mut cfg.config);
+//! let encoder = cfg.into_encoder().unwrap();
+//! let frame = converter.convert(&nv12, 1920).unwrap();
+//! encoder.send_picture(frame, None, false).unwrap();
+//! ```
+use crate::{
+    ffi::{ColorFormat, ColorRange, MatrixCoefficients, SvtAv1EncConfiguration},
+    yuv::PlanarLayout,
+    Frame,
+};
+use std::fmt;
+
+/// Layout of input pictures
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum InputFormat {
+    /// 8-bit 4:2:0, luma plane followed by interleaved Cb/Cr plane with the same stride
+    Nv12,
+    /// 10-bit NV12, samples in high bits of 16-bit little-endian words
+    P010,
+    /// 8-bit packed 4:2:2, Y0 Cb Y1 Cr
+    Yuy2,
+    /// 8-bit packed 4:2:2, Cb Y0 Cr Y1
+    Uyvy,
+    /// 10-bit packed 4:2:2, 6 pixels in 4 little-endian 32-bit words
+    V210,
+    /// 8-bit R, G, B
+    Rgb24,
+    /// 8-bit B, G, R
+    Bgr24,
+    /// 8-bit R, G, B, alpha is ignored
+    Rgba,
+    /// 8-bit B, G, R, alpha is ignored
+    Bgra,
+}
+
+impl InputFormat {
+    /// Bit depth of output planar picture
+    pub fn bit_depth(self) -> u32 {
+        match self {
+            Self::P010 | Self::V210 => 10,
+            _ => 8,
+        }
+    }
+
+    pub fn is_rgb(self) -> bool {
+        matches!(self, Self::Rgb24 | Self::Bgr24 | Self::Rgba | Self::Bgra)
+    }
+
+    /// Minimal size of input row in bytes
+    pub fn min_stride(self, width: u32) -> usize {
+        let width = width as usize;
+        match self {
+            Self::Nv12 => width.next_multiple_of(2),
+            Self::P010 => width.next_multiple_of(2) * 2,
+            Self::Yuy2 | Self::Uyvy => width.div_ceil(2) * 4,
+            Self::V210 => width.div_ceil(6) * 16,
+            Self::Rgb24 | Self::Bgr24 => width * 3,
+            Self::Rgba | Self::Bgra => width * 4,
+        }
+    }
+
+    /// Number of input rows, including chroma plane of semi-planar formats
+    fn rows(self, height: u32) -> usize {
+        match self {
+            Self::Nv12 | Self::P010 => (height + height.div_ceil(2)) as usize,
+            _ => height as usize,
+        }
+    }
+
+    /// Output chroma formats, first one is default
+    fn color_formats(self) -> &'static [ColorFormat] {
+        match self {
+            Self::Nv12 | Self::P010 => &[ColorFormat::YUV420],
+            Self::Yuy2 | Self::Uyvy | Self::V210 => &[ColorFormat::YUV420, ColorFormat::YUV422],
+            _ => &[ColorFormat::YUV420, ColorFormat::YUV444],
+        }
+    }
+}
+
+/// Conversion error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvertError {
+    /// Matrix coefficients, for which RGB conversion isn't implemented
+    UnsupportedMatrix(MatrixCoefficients),
+    /// Output chroma format can't be produced from input format
+    UnsupportedColorFormat(ColorFormat),
+    /// Stride is smaller than row of input picture
+    InvalidStride { min: usize, actual: usize },
+    /// Input buffer is smaller than picture
+    InputTooSmall { min: usize, actual: usize },
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMatrix(matrix) => {
+                write!(f, "unsupported matrix coefficients {matrix:?}")
+            }
+            Self::UnsupportedColorFormat(format) => {
+                write!(f, "unsupported output color format {format:?}")
+            }
+            Self::InvalidStride { min, actual } => {
+                write!(f, "stride {actual} is smaller than row size {min}")
+            }
+            Self::InputTooSmall { min, actual } => {
+                write!(f, "input size {actual} is smaller than picture size {min}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// RGB to YCbCr coefficients in 16.16 fixed point
+#[derive(Debug, Clone, Copy)]
+struct RgbCoefficients {
+    y: [i32; 3],
+    cb: [i32; 3],
+    cr: [i32; 3],
+    y_offset: i32,
+}
+
+impl RgbCoefficients {
+    fn new(matrix: MatrixCoefficients, range: ColorRange) -> Result<Self, ConvertError> {
+        let (kr, kb) = match matrix {
+            MatrixCoefficients::CicpMcBt709 => (0.2126, 0.0722),
+            MatrixCoefficients::CicpMcBt470BG | MatrixCoefficients::CicpMcBt601 => (0.299, 0.114),
+            MatrixCoefficients::CicpMcBt2020Ncl => (0.2627, 0.0593),
+            _ => return Err(ConvertError::UnsupportedMatrix(matrix)),
+        };
+        let kg = 1.0 - kr - kb;
+        let (y_scale, c_scale, y_offset) = match range {
+            ColorRange::CrStudioRange => (219.0 / 255.0, 224.0 / 255.0, 16),
+            ColorRange::CrFullRange => (1.0, 1.0, 0),
+        };
+        let fixed = |value: f64| (value * 65536.0).round() as i32;
+        let cb = c_scale / (2.0 * (1.0 - kb));
+        let cr = c_scale / (2.0 * (1.0 - kr));
+        Ok(Self {
+            y: [kr, kg, kb].map(|k| fixed(k * y_scale)),
+            cb: [-kr * cb, -kg * cb, (1.0 - kb) * cb].map(fixed),
+            cr: [(1.0 - kr) * cr, -kg * cr, -kb * cr].map(fixed),
+            y_offset,
+        })
+    }
+
+    fn apply(coefficients: [i32; 3], rgb: [i32; 3], offset: i32) -> u16 {
+        let sum = coefficients[0] * rgb[0] + coefficients[1] * rgb[1] + coefficients[2] * rgb[2];
+        (((sum + (1 << 15)) >> 16) + offset).clamp(0, 255) as u16
+    }
+
+    fn luma(&self, rgb: [i32; 3]) -> u16 {
+        Self::apply(self.y, rgb, self.y_offset)
+    }
+
+    fn chroma(&self, rgb: [i32; 3]) -> (u16, u16) {
+        (
+            Self::apply(self.cb, rgb, 128),
+            Self::apply(self.cr, rgb, 128),
+        )
+    }
+}
+
+/// Write samples to plane row, 1 or 2 bytes per sample
+#[inline]
+fn store(out: &mut [u8], samples: &[u16], wide: bool) {
+    if wide {
+        for (out, sample) in out.chunks_exact_mut(2).zip(samples) {
+            out.copy_from_slice(&sample.to_le_bytes());
+        }
+    } else {
+        for (out, sample) in out.iter_mut().zip(samples) {
+            *out = *sample as u8;
+        }
+    }
+}
+
+/// Split planar buffer into mutable planes
+fn planes_mut<'a>(
+    layout: &PlanarLayout,
+    buffer: &'a mut [u8],
+) -> (&'a mut [u8], &'a mut [u8], &'a mut [u8]) {
+    let (chroma_width, chroma_height) = layout.chroma_size();
+    let luma_len = layout.width as usize * layout.height as usize * layout.sample_size();
+    let chroma_len = chroma_width as usize * chroma_height as usize * layout.sample_size();
+    let (luma, chroma) = buffer.split_at_mut(luma_len);
+    let (cb, cr) = chroma.split_at_mut(chroma_len);
+    (luma, cb, cr)
+}
+
+/// Convert NV12 or P010 picture to planar 4:2:0 with the same bit depth.
+/// Chroma plane starts right after `height` luma rows
+pub(crate) fn semi_planar_to_planar(
+    layout: &PlanarLayout,
+    src: &[u8],
+    luma_stride: usize,
+    chroma_stride: usize,
+    dst: &mut [u8],
+) {
+    let wide = layout.sample_size() == 2;
+    let (chroma_width, chroma_height) = layout.chroma_size();
+    let row_len = layout.width as usize * layout.sample_size();
+    let chroma_row_len = chroma_width as usize * layout.sample_size();
+    let (luma, cb, cr) = planes_mut(layout, dst);
+
+    let (src_luma, src_chroma) = src.split_at(luma_stride * layout.height as usize);
+    for (src, out) in src_luma
+        .chunks(luma_stride)
+        .zip(luma.chunks_exact_mut(row_len))
+    {
+        if wide {
+            for (src, out) in src[..row_len].chunks_exact(2).zip(out.chunks_exact_mut(2)) {
+                let sample = u16::from_le_bytes([src[0], src[1]]) >> 6;
+                out.copy_from_slice(&sample.to_le_bytes());
+            }
+        } else {
+            out.copy_from_slice(&src[..row_len]);
+        }
+    }
+
+    let rows = src_chroma
+        .chunks(chroma_stride)
+        .take(chroma_height as usize)
+        .zip(cb.chunks_exact_mut(chroma_row_len))
+        .zip(cr.chunks_exact_mut(chroma_row_len));
+    for ((src, cb), cr) in rows {
+        let src = &src[..chroma_row_len * 2];
+        if wide {
+            let samples = src
+                .chunks_exact(4)
+                .zip(cb.chunks_exact_mut(2))
+                .zip(cr.chunks_exact_mut(2));
+            for ((src, cb), cr) in samples {
+                let u = u16::from_le_bytes([src[0], src[1]]) >> 6;
+                let v = u16::from_le_bytes([src[2], src[3]]) >> 6;
+                cb.copy_from_slice(&u.to_le_bytes());
+                cr.copy_from_slice(&v.to_le_bytes());
+            }
+        } else {
+            for ((src, cb), cr) in src.chunks_exact(2).zip(cb.iter_mut()).zip(cr.iter_mut()) {
+                *cb = src[0];
+                *cr = src[1];
+            }
+        }
+    }
+}
+
+/// Unpacker of one packed 4:2:2 row into luma, Cb and Cr samples
+type DecodeRow = fn(&[u8], &mut [u16], &mut [u16], &mut [u16]);
+
+/// Converter of pictures in [`InputFormat`] into planar [`Frame`]s
+#[derive(Debug)]
+pub struct Converter {
+    format: InputFormat,
+    layout: PlanarLayout,
+    matrix: MatrixCoefficients,
+    color_range: ColorRange,
+    coefficients: Option<RgbCoefficients>,
+    buffer: Vec<u8>,
+    /// Decoded row of luma and chroma, and chroma of previous row or RGB sums for subsampling
+    row_y: Vec<u16>,
+    row_cb: Vec<u16>,
+    row_cr: Vec<u16>,
+    prev_cb: Vec<u16>,
+    prev_cr: Vec<u16>,
+    rgb_sums: Vec<[i32; 4]>,
+}
+
+impl Converter {
+    /// Create converter for pictures of given size. Output is 4:2:0, RGB is converted with BT.709 matrix
+    /// to studio range
+    pub fn new(format: InputFormat, width: u32, height: u32) -> Self {
+        let layout = PlanarLayout {
+            width,
+            height,
+            color_format: ColorFormat::YUV420,
+            bit_depth: format.bit_depth(),
+        };
+        let matrix = MatrixCoefficients::CicpMcBt709;
+        let color_range = ColorRange::CrStudioRange;
+        let coefficients = format
+            .is_rgb()
+            .then(|| RgbCoefficients::new(matrix, color_range).unwrap());
+        let width = width as usize;
+        // v210 rows are decoded in whole groups of 6 pixels
+        let row_width = width.next_multiple_of(6);
+        let mut converter = Self {
+            format,
+            layout,
+            matrix,
+            color_range,
+            coefficients,
+            buffer: Vec::new(),
+            row_y: vec![0; row_width],
+            row_cb: vec![0; row_width / 2],
+            row_cr: vec![0; row_width / 2],
+            prev_cb: vec![0; row_width / 2],
+            prev_cr: vec![0; row_width / 2],
+            rgb_sums: vec![[0; 4]; width.div_ceil(2)],
+        };
+        converter.buffer = vec![0; converter.layout.frame_size()];
+        converter
+    }
+
+    /// Layout of output frames
+    pub fn layout(&self) -> &PlanarLayout {
+        &self.layout
+    }
+
+    /// Set output chroma format. Semi-planar input only supports 4:2:0, packed 4:2:2 input
+    /// supports 4:2:0 and 4:2:2, and RGB supports 4:2:0 and 4:4:4
+    pub fn set_color_format(&mut self, color_format: ColorFormat) -> Result<(), ConvertError> {
+        if !self.format.color_formats().contains(&color_format) {
+            return Err(ConvertError::UnsupportedColorFormat(color_format));
+        }
+        self.layout.color_format = color_format;
+        self.buffer.resize(self.layout.frame_size(), 0);
+        Ok(())
+    }
+
+    /// Set matrix and range for RGB input. BT.709, BT.601 (BT.470BG) and BT.2020 NCL are supported
+    pub fn set_rgb_matrix(
+        &mut self,
+        matrix: MatrixCoefficients,
+        color_range: ColorRange,
+    ) -> Result<(), ConvertError> {
+        let coefficients = RgbCoefficients::new(matrix, color_range)?;
+        if self.format.is_rgb() {
+            self.coefficients = Some(coefficients);
+        }
+        self.matrix = matrix;
+        self.color_range = color_range;
+        Ok(())
+    }
+
+    /// Set bit depth and color format of output. For RGB input also set matrix coefficients and color range
+    pub fn apply(&self, config: &mut SvtAv1EncConfiguration) {
+        config.encoder_bit_depth = self.layout.bit_depth;
+        config.encoder_color_format = self.layout.color_format;
+        if self.format.is_rgb() {
+            config.matrix_coefficients = self.matrix;
+            config.color_range = self.color_range;
+        }
+    }
+
+    /// Convert picture with rows `stride` bytes apart. Returned frame borrows internal buffer,
+    /// which is reused for the next picture
+    pub fn convert(&mut self, src: &[u8], stride: usize) -> Result<Frame<'_>, ConvertError> {
+        let min_stride = self.format.min_stride(self.layout.width);
+        if stride < min_stride {
+            return Err(ConvertError::InvalidStride {
+                min: min_stride,
+                actual: stride,
+            });
+        }
+        let min_size = stride * (self.format.rows(self.layout.height) - 1) + min_stride;
+        if src.len() < min_size {
+            return Err(ConvertError::InputTooSmall {
+                min: min_size,
+                actual: src.len(),
+            });
+        }
+
+        match self.format {
+            InputFormat::Nv12 | InputFormat::P010 => {
+                semi_planar_to_planar(&self.layout, src, stride, stride, &mut self.buffer);
+            }
+            InputFormat::Yuy2 => self.convert_422(src, stride, decode_yuy2::<0>),
+            InputFormat::Uyvy => self.convert_422(src, stride, decode_yuy2::<1>),
+            InputFormat::V210 => self.convert_422(src, stride, decode_v210),
+            InputFormat::Rgb24 => self.convert_rgb::<3, false>(src, stride),
+            InputFormat::Bgr24 => self.convert_rgb::<3, true>(src, stride),
+            InputFormat::Rgba => self.convert_rgb::<4, false>(src, stride),
+            InputFormat::Bgra => self.convert_rgb::<4, true>(src, stride),
+        }
+        Ok(self.layout.frame(&self.buffer))
+    }
+
+    /// Convert packed 4:2:2 picture, `decode` unpacks one row into luma and chroma samples
+    fn convert_422(&mut self, src: &[u8], stride: usize, decode: DecodeRow) {
+        let layout = self.layout;
+        let wide = layout.sample_size() == 2;
+        let width = layout.width as usize;
+        let (chroma_width, _) = layout.chroma_size();
+        let chroma_width = chroma_width as usize;
+        let row_len = width * layout.sample_size();
+        let chroma_row_len = chroma_width * layout.sample_size();
+        let subsample = layout.color_format == ColorFormat::YUV420;
+        let (luma, cb, cr) = planes_mut(&layout, &mut self.buffer);
+
+        for (row, src) in src.chunks(stride).take(layout.height as usize).enumerate() {
+            decode(src, &mut self.row_y, &mut self.row_cb, &mut self.row_cr);
+            store(
+                &mut luma[row * row_len..][..row_len],
+                &self.row_y[..width],
+                wide,
+            );
+
+            let chroma_row = if subsample { row / 2 } else { row };
+            let cb = &mut cb[chroma_row * chroma_row_len..][..chroma_row_len];
+            let cr = &mut cr[chroma_row * chroma_row_len..][..chroma_row_len];
+            if !subsample {
+                store(cb, &self.row_cb[..chroma_width], wide);
+                store(cr, &self.row_cr[..chroma_width], wide);
+            } else if row % 2 == 0 {
+                // Chroma of even row is kept until the next one, last odd row is stored alone
+                self.prev_cb.copy_from_slice(&self.row_cb);
+                self.prev_cr.copy_from_slice(&self.row_cr);
+                if row + 1 == layout.height as usize {
+                    store(cb, &self.row_cb[..chroma_width], wide);
+                    store(cr, &self.row_cr[..chroma_width], wide);
+                }
+            } else {
+                for (cur, prev) in self.row_cb.iter_mut().zip(&self.prev_cb) {
+                    *cur = (*cur + prev + 1) >> 1;
+                }
+                for (cur, prev) in self.row_cr.iter_mut().zip(&self.prev_cr) {
+                    *cur = (*cur + prev + 1) >> 1;
+                }
+                store(cb, &self.row_cb[..chroma_width], wide);
+                store(cr, &self.row_cr[..chroma_width], wide);
+            }
+        }
+    }
+
+    /// Convert 8-bit RGB picture with `N` bytes per pixel, `BGR` for reversed channel order
+    fn convert_rgb<const N: usize, const BGR: bool>(&mut self, src: &[u8], stride: usize) {
+        let layout = self.layout;
+        let coefficients = self.coefficients.unwrap();
+        let width = layout.width as usize;
+        let height = layout.height as usize;
+        let (chroma_width, _) = layout.chroma_size();
+        let chroma_width = chroma_width as usize;
+        let subsample = layout.color_format == ColorFormat::YUV420;
+        let (luma, cb, cr) = planes_mut(&layout, &mut self.buffer);
+        let rgb = |pixel: &[u8]| {
+            let (r, g, b) = if BGR {
+                (pixel[2], pixel[1], pixel[0])
+            } else {
+                (pixel[0], pixel[1], pixel[2])
+            };
+            [r as i32, g as i32, b as i32]
+        };
+
+        for (row, src) in src.chunks(stride).take(height).enumerate() {
+            let src = &src[..width * N];
+            let luma = &mut luma[row * width..][..width];
+            for (pixel, luma) in src.chunks_exact(N).zip(luma.iter_mut()) {
+                *luma = coefficients.luma(rgb(pixel)) as u8;
+            }
+
+            if !subsample {
+                let cb = &mut cb[row * width..][..width];
+                let cr = &mut cr[row * width..][..width];
+                for ((pixel, cb), cr) in src.chunks_exact(N).zip(cb.iter_mut()).zip(cr.iter_mut()) {
+                    let (u, v) = coefficients.chroma(rgb(pixel));
+                    *cb = u as u8;
+                    *cr = v as u8;
+                }
+                continue;
+            }
+
+            // Sum RGB of 2x2 blocks, with count of pixels in the last element for odd sizes
+            if row % 2 == 0 {
+                self.rgb_sums.fill([0; 4]);
+            }
+            for (pixels, sum) in src.chunks(2 * N).zip(self.rgb_sums.iter_mut()) {
+                for pixel in pixels.chunks_exact(N) {
+                    let [r, g, b] = rgb(pixel);
+                    *sum = [sum[0] + r, sum[1] + g, sum[2] + b, sum[3] + 1];
+                }
+            }
+            if row % 2 == 1 || row + 1 == height {
+                let cb = &mut cb[row / 2 * chroma_width..][..chroma_width];
+                let cr = &mut cr[row / 2 * chroma_width..][..chroma_width];
+                for ((sum, cb), cr) in self.rgb_sums.iter().zip(cb.iter_mut()).zip(cr.iter_mut()) {
+                    let count = sum[3];
+                    let average = [0, 1, 2].map(|i| (sum[i] + count / 2) / count);
+                    let (u, v) = coefficients.chroma(average);
+                    *cb = u as u8;
+                    *cr = v as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Unpack YUY2 row (`OFFSET` 0) or UYVY row (`OFFSET` 1)
+fn decode_yuy2<const OFFSET: usize>(src: &[u8], luma: &mut [u16], cb: &mut [u16], cr: &mut [u16]) {
+    let groups = luma.len() / 2;
+    let src = &src[..(src.len() / 4).min(groups) * 4];
+    for (((src, luma), cb), cr) in src
+        .chunks_exact(4)
+        .zip(luma.chunks_exact_mut(2))
+        .zip(cb.iter_mut())
+        .zip(cr.iter_mut())
+    {
+        luma[0] = src[OFFSET] as u16;
+        luma[1] = src[OFFSET + 2] as u16;
+        *cb = src[1 - OFFSET] as u16;
+        *cr = src[3 - OFFSET] as u16;
+    }
+}
+
+/// Unpack v210 row
+fn decode_v210(src: &[u8], luma: &mut [u16], cb: &mut [u16], cr: &mut [u16]) {
+    for (((src, luma), cb), cr) in src
+        .chunks_exact(16)
+        .zip(luma.chunks_exact_mut(6))
+        .zip(cb.chunks_exact_mut(3))
+        .zip(cr.chunks_exact_mut(3))
+    {
+        let mut samples = [0u16; 12];
+        for (word, samples) in src.chunks_exact(4).zip(samples.chunks_exact_mut(3)) {
+            let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            samples[0] = (word & 0x3FF) as u16;
+            samples[1] = ((word >> 10) & 0x3FF) as u16;
+            samples[2] = ((word >> 20) & 0x3FF) as u16;
+        }
+        // Cb0 Y0 Cr0 Y1 Cb1 Y2 Cr1 Y3 Cb2 Y4 Cr2 Y5
+        for i in 0..3 {
+            cb[i] = samples[i * 4];
+            luma[i * 2] = samples[i * 4 + 1];
+            cr[i] = samples[i * 4 + 2];
+            luma[i * 2 + 1] = samples[i * 4 + 3];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nv12() {
+        // 2x2 luma with 1 padding byte per row, then Cb/Cr pair
+        let src = [1, 2, 0, 3, 4, 0, 5, 6];
+        let mut converter = Converter::new(InputFormat::Nv12, 2, 2);
+        let frame = converter.convert(&src, 3).unwrap();
+        assert_eq!(frame.luma, [1, 2, 3, 4]);
+        assert_eq!((frame.cb, frame.cr), (&[5][..], &[6][..]));
+        assert_eq!(
+            converter.convert(&src, 1).unwrap_err(),
+            ConvertError::InvalidStride { min: 2, actual: 1 }
+        );
+        assert_eq!(
+            converter.convert(&src[..7], 3).unwrap_err(),
+            ConvertError::InputTooSmall { min: 8, actual: 7 }
+        );
+    }
+
+    #[test]
+    fn yuy2_and_uyvy() {
+        // 2x3 picture, chroma of rows 0 and 1 is averaged, row 2 is stored alone
+        let yuy2 = [10, 100, 11, 200, 12, 101, 13, 201, 14, 50, 15, 60];
+        let mut converter = Converter::new(InputFormat::Yuy2, 2, 3);
+        let frame = converter.convert(&yuy2, 4).unwrap();
+        assert_eq!(frame.luma, [10, 11, 12, 13, 14, 15]);
+        assert_eq!(frame.cb, [101, 50]);
+        assert_eq!(frame.cr, [201, 60]);
+
+        let uyvy: Vec<u8> = yuy2.chunks(2).flat_map(|pair| [pair[1], pair[0]]).collect();
+        let mut converter = Converter::new(InputFormat::Uyvy, 2, 3);
+        converter.set_color_format(ColorFormat::YUV422).unwrap();
+        let frame = converter.convert(&uyvy, 4).unwrap();
+        assert_eq!(frame.luma, [10, 11, 12, 13, 14, 15]);
+        assert_eq!(frame.cb, [100, 101, 50]);
+        assert_eq!(frame.cr, [200, 201, 60]);
+        assert_eq!(
+            converter.set_color_format(ColorFormat::YUV444),
+            Err(ConvertError::UnsupportedColorFormat(ColorFormat::YUV444))
+        );
+    }
+
+    #[test]
+    fn v210() {
+        // Samples 0..12 in Cb0 Y0 Cr0 Y1 ... order, width 4 uses partial group
+        let mut src = Vec::new();
+        for word in 0..4u32 {
+            let base = word * 3;
+            src.extend_from_slice(&(base | (base + 1) << 10 | (base + 2) << 20).to_le_bytes());
+        }
+        let mut converter = Converter::new(InputFormat::V210, 4, 1);
+        let frame = converter.convert(&src, 16).unwrap();
+        let samples = |data: &[u8]| -> Vec<u16> {
+            data.chunks(2)
+                .map(|s| u16::from_le_bytes([s[0], s[1]]))
+                .collect()
+        };
+        assert_eq!(samples(frame.luma), [1, 3, 5, 7]);
+        assert_eq!(samples(frame.cb), [0, 4]);
+        assert_eq!(samples(frame.cr), [2, 6]);
+        assert_eq!(frame.y_stride, 4);
+    }
+
+    #[test]
+    fn rgb() {
+        // White, black, red and blue, BT.709 studio range
+        let rgb = [255, 255, 255, 0, 0, 0, 255, 0, 0, 0, 0, 255];
+        let mut converter = Converter::new(InputFormat::Rgb24, 2, 2);
+        let frame = converter.convert(&rgb, 6).unwrap();
+        assert_eq!(frame.luma, [235, 16, 63, 32]);
+        // Average is (128, 64, 128)
+        assert_eq!(frame.cb, [150]);
+        assert_eq!(frame.cr, [154]);
+
+        let bgra: Vec<u8> = rgb
+            .chunks(3)
+            .flat_map(|p| [p[2], p[1], p[0], 0xFF])
+            .collect();
+        let mut converter = Converter::new(InputFormat::Bgra, 2, 2);
+        converter.set_color_format(ColorFormat::YUV444).unwrap();
+        converter
+            .set_rgb_matrix(MatrixCoefficients::CicpMcBt601, ColorRange::CrFullRange)
+            .unwrap();
+        let frame = converter.convert(&bgra, 8).unwrap();
+        assert_eq!(frame.luma, [255, 0, 76, 29]);
+        assert_eq!(frame.cb, [128, 128, 85, 255]);
+        assert_eq!(frame.cr, [128, 128, 255, 107]);
+
+        assert_eq!(
+            converter.set_rgb_matrix(MatrixCoefficients::CicpMcIdentity, ColorRange::CrFullRange),
+            Err(ConvertError::UnsupportedMatrix(
+                MatrixCoefficients::CicpMcIdentity
+            ))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! IVF writer for encoded packets.
+//!
+//! [`IvfWriter`] stores every temporal unit as one IVF frame, starting with temporal delimiter,
+//! like `SvtAv1EncApp` does. Time base is frame duration from encoder configuration, so packet
+//! timestamps are expected to be frame numbers.
+use crate::{
+    ffi::SvtAv1EncConfiguration,
+    obu::{TemporalUnit, TemporalUnitAssembler, TEMPORAL_DELIMITER},
+    Packet,
+};
+use std::io::{self, Seek, SeekFrom, Write};
+
+const SIGNATURE: &[u8; 4] = b"DKIF";
+const HEADER_LEN: u16 = 32;
+const FOURCC: &[u8; 4] = b"AV01";
+/// Offset of frame count in file header
+const FRAME_COUNT_POS: u64 = 24;
+
+/// Writes encoder packets into IVF file. Call [`finish`] after the last packet, or
+/// [`finish_with_frame_count`] to also store number of frames in file header.
+///
+/// [`finish`]: method@Self::finish
+/// [`finish_with_frame_count`]: method@Self::finish_with_frame_count
+#[derive(Debug)]
+pub struct IvfWriter<W: Write> {
+    writer: W,
+    frame_count: u32,
+    assembler: TemporalUnitAssembler,
+}
+
+impl<W: Write> IvfWriter<W> {
+    /// Create writer with picture size and frame rate of encoder configuration and write file header
+    pub fn new(mut writer: W, config: &SvtAv1EncConfiguration) -> io::Result<Self> {
+        if config.frame_rate_numerator == 0 || config.frame_rate_denominator == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame rate must be set in encoder configuration",
+            ));
+        }
+        let (Ok(width), Ok(height)) = (
+            u16::try_from(config.source_width),
+            u16::try_from(config.source_height),
+        ) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "picture size doesn't fit IVF header",
+            ));
+        };
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(SIGNATURE);
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&HEADER_LEN.to_le_bytes());
+        header.extend_from_slice(FOURCC);
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        // Time base is denominator / numerator of frame rate
+        header.extend_from_slice(&config.frame_rate_numerator.to_le_bytes());
+        header.extend_from_slice(&config.frame_rate_denominator.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self {
+            writer,
+            frame_count: 0,
+            assembler: TemporalUnitAssembler::new(),
+        })
+    }
+
+    /// Number of frames written
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Write packet received from encoder
+    pub fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        match self.assembler.push_packet(packet) {
+            Some(unit) => self.write_temporal_unit(&unit),
+            None => Ok(()),
+        }
+    }
+
+    /// Write complete temporal unit without temporal delimiter
+    pub fn write_temporal_unit(&mut self, unit: &TemporalUnit) -> io::Result<()> {
+        let size = (TEMPORAL_DELIMITER.len() + unit.data.len()) as u32;
+        let mut frame_header = [0; 12];
+        frame_header[..4].copy_from_slice(&size.to_le_bytes());
+        frame_header[4..].copy_from_slice(&unit.pts.to_le_bytes());
+        self.writer.write_all(&frame_header)?;
+        self.writer.write_all(&TEMPORAL_DELIMITER)?;
+        self.writer.write_all(&unit.data)?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Write remaining data and return inner writer. Frame count in file header stays zero,
+    /// which decoders ignore
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(unit) = self.assembler.flush() {
+            self.write_temporal_unit(&unit)?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write + Seek> IvfWriter<W> {
+    /// Write remaining data, store frame count in file header and return inner writer
+    pub fn finish_with_frame_count(mut self) -> io::Result<W> {
+        if let Some(unit) = self.assembler.flush() {
+            self.write_temporal_unit(&unit)?;
+        }
+        let end = self.writer.stream_position()?;
+        let _ = self.writer.seek(SeekFrom::Start(FRAME_COUNT_POS))?;
+        self.writer.write_all(&self.frame_count.to_le_bytes())?;
+        let _ = self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_config;
+    use std::io::Cursor;
+
+    #[test]
+    fn header_and_frames() {
+        let mut config = test_config(640, 360);
+        config.frame_rate_numerator = 30000;
+        config.frame_rate_denominator = 1001;
+        let mut writer = IvfWriter::new(Cursor::new(Vec::new()), &config).unwrap();
+        for pts in 0..2 {
+            writer
+                .write_temporal_unit(&TemporalUnit {
+                    data: vec![0x32, 0x01, pts as u8],
+                    pts,
+                    keyframe: pts == 0,
+                })
+                .unwrap();
+        }
+        assert_eq!(writer.frame_count(), 2);
+        let data = writer.finish_with_frame_count().unwrap().into_inner();
+
+        assert_eq!(&data[..4], b"DKIF");
+        assert_eq!(&data[6..12], b"\x20\x00AV01");
+        assert_eq!(&data[12..16], &[0x80, 0x02, 0x68, 0x01]);
+        assert_eq!(&data[16..24], &[0x30, 0x75, 0, 0, 0xE9, 0x03, 0, 0]);
+        assert_eq!(&data[24..28], &2u32.to_le_bytes());
+        assert_eq!(data.len(), 32 + 2 * (12 + 5));
+        // Second frame: size, pts, temporal delimiter and unit data
+        assert_eq!(
+            &data[49..],
+            &[5, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0x12, 0x00, 0x32, 0x01, 1]
+        );
+
+        config.source_width = 70000;
+        assert!(IvfWriter::new(Vec::new(), &config).is_err());
+    }
+}
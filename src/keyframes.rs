@@ -0,0 +1,714 @@
+//! Key frames placed by schedule, e.g. at segment boundaries of HLS and DASH.
+//!
+//! [`KeyframeSchedule`] lists key frames by timestamps, frame numbers, fixed interval in seconds
+//! or an expression like `expr:` of ffmpeg `-force_key_frames`. Set it with
+//! [`SvtAv1EncoderConfig::set_keyframe_schedule`] and encoder forces key frames in
+//! [`SvtAv1Encoder::send_picture`]. Time of frame is its pts in frame durations of configuration,
+//! as timestamps are frame numbers elsewhere in this crate, or frame number when pts isn't given.
+//!
+//! Encoder may still code a forced key frame as hidden forward key frame, which is shown later,
+//! so decoding can't start at its position. Forced key frames are checked in output by pts, which
+//! is frame number for frames sent without one, and such ones are reported by
+//! [`SvtAv1Encoder::take_missed_keyframes`].
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::{keyframes::{KeyframeExpr, KeyframeSchedule}, SvtAv1EncoderConfig};
+//! let mut cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
+//! // Key frame every 2 seconds, same as `KeyframeSchedule::Interval(2.0)`
+//! let expr: KeyframeExpr = "gte(t, n_forced * 2)".parse().unwrap();
+//! cfg.set_keyframe_schedule(Some(KeyframeSchedule::Expr(expr)));
+//! ```
+//!
+//! [`SvtAv1EncoderConfig::set_keyframe_schedule`]: crate::SvtAv1EncoderConfig::set_keyframe_schedule
+//! [`SvtAv1Encoder::send_picture`]: crate::SvtAv1Encoder::send_picture
+//! [`SvtAv1Encoder::take_missed_keyframes`]: crate::SvtAv1Encoder::take_missed_keyframes
+use crate::{
+    ffi::{Av1PictureType, SvtAv1EncConfiguration, SvtFlags},
+    scene::SceneDetector,
+    Frame,
+};
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+/// Frames to force as key frames
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyframeSchedule {
+    /// The first frame with pts at or after each of these
+    Timestamps(Vec<i64>),
+    /// Frames with these numbers, counted from the first frame sent
+    Frames(Vec<u64>),
+    /// The first frame at or after each multiple of this many seconds
+    Interval(f64),
+    /// Frames for which expression isn't zero
+    Expr(KeyframeExpr),
+}
+
+/// Applies [`KeyframeSchedule`] to frames in order they are sent
+#[derive(Debug, Clone)]
+pub struct KeyframeScheduler {
+    schedule: KeyframeSchedule,
+    /// Seconds
+    frame_duration: f64,
+    /// Next item of list or time of next interval
+    next: usize,
+    next_time: f64,
+    frame: u64,
+    forced: u64,
+    prev_forced_n: f64,
+    prev_forced_t: f64,
+}
+
+impl KeyframeScheduler {
+    pub fn new(
+        mut schedule: KeyframeSchedule,
+        frame_rate_numerator: u32,
+        frame_rate_denominator: u32,
+    ) -> Self {
+        match &mut schedule {
+            KeyframeSchedule::Timestamps(list) => list.sort_unstable(),
+            KeyframeSchedule::Frames(list) => list.sort_unstable(),
+            _ => {}
+        }
+        Self {
+            schedule,
+            frame_duration: frame_rate_denominator as f64 / frame_rate_numerator as f64,
+            next: 0,
+            next_time: 0.0,
+            frame: 0,
+            forced: 0,
+            prev_forced_n: f64::NAN,
+            prev_forced_t: f64::NAN,
+        }
+    }
+
+    /// Use frame rate of configuration
+    pub fn from_config(schedule: KeyframeSchedule, config: &SvtAv1EncConfiguration) -> Self {
+        Self::new(
+            schedule,
+            config.frame_rate_numerator,
+            config.frame_rate_denominator,
+        )
+    }
+
+    pub fn schedule(&self) -> &KeyframeSchedule {
+        &self.schedule
+    }
+
+    /// Take next frame and return whether it should be key frame
+    pub fn push(&mut self, pts: Option<i64>) -> bool {
+        let n = self.frame;
+        self.frame += 1;
+        let pts = pts.unwrap_or(n as i64);
+        let time = pts as f64 * self.frame_duration;
+        let keyframe = match &self.schedule {
+            KeyframeSchedule::Timestamps(list) => advance(list, &mut self.next, |&due| due <= pts),
+            KeyframeSchedule::Frames(list) => advance(list, &mut self.next, |&due| due <= n),
+            KeyframeSchedule::Interval(seconds) => {
+                let due = *seconds > 0.0 && time >= self.next_time;
+                if due {
+                    self.next_time = ((time / seconds).floor() + 1.0) * seconds;
+                }
+                due
+            }
+            KeyframeSchedule::Expr(expr) => {
+                let result = expr.eval(&[
+                    n as f64,
+                    self.forced as f64,
+                    self.prev_forced_n,
+                    self.prev_forced_t,
+                    time,
+                ]);
+                result != 0.0 && !result.is_nan()
+            }
+        };
+        if keyframe {
+            self.forced += 1;
+            self.prev_forced_n = n as f64;
+            self.prev_forced_t = time;
+        }
+        keyframe
+    }
+}
+
+/// Skip items of sorted list which are due, returns whether there were any
+fn advance<T>(list: &[T], next: &mut usize, due: impl Fn(&T) -> bool) -> bool {
+    let start = *next;
+    while list.get(*next).is_some_and(&due) {
+        *next += 1;
+    }
+    *next > start
+}
+
+/// Key frame forced when sending frame, which wasn't encoded as shown key frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissedKeyframe {
+    pub pts: i64,
+    /// Type of picture encoded instead
+    pub pic_type: Av1PictureType,
+    /// Frame is hidden, it's shown later by another packet
+    pub hidden: bool,
+}
+
+/// Key frame decisions of encoder, in the order schedule, caller and scene detection
+#[derive(Debug, Default)]
+pub(crate) struct KeyframeDecisions {
+    scheduler: Option<KeyframeScheduler>,
+    scene_detector: Option<SceneDetector>,
+    /// Pts of forced key frames not received yet
+    requested: BTreeSet<i64>,
+    missed: Vec<MissedKeyframe>,
+    /// Number of frames sent, pts of frame sent without one
+    sent: i64,
+}
+
+impl KeyframeDecisions {
+    pub(crate) fn new(
+        scheduler: Option<KeyframeScheduler>,
+        scene_detector: Option<SceneDetector>,
+    ) -> Self {
+        Self {
+            scheduler,
+            scene_detector,
+            ..Default::default()
+        }
+    }
+
+    /// Whether frame sent with `pts` should be key frame, `forced` by caller, and pts of the frame,
+    /// which is its number if `pts` isn't given
+    pub(crate) fn decide(
+        &mut self,
+        frame: &Frame<'_>,
+        pts: Option<i64>,
+        forced: bool,
+    ) -> (bool, i64) {
+        let scheduled = self
+            .scheduler
+            .as_mut()
+            .is_some_and(|scheduler| scheduler.push(pts));
+        let forced = forced || scheduled;
+        let keyframe = match &mut self.scene_detector {
+            Some(detector) => detector.push(frame, forced),
+            None => forced,
+        };
+        let pts = pts.unwrap_or(self.sent);
+        self.sent += 1;
+        if keyframe {
+            let _ = self.requested.insert(pts);
+        }
+        (keyframe, pts)
+    }
+
+    /// Check packet received from encoder against forced key frames
+    pub(crate) fn check_packet(&mut self, pts: i64, flags: SvtFlags, pic_type: Av1PictureType) {
+        if !self.requested.remove(&pts) {
+            return;
+        }
+        let hidden = pic_type == Av1PictureType::Av1FwKeyPicture
+            || flags.contains(SvtFlags::BUFFERFLAG_IS_ALT_REF);
+        if hidden || pic_type != Av1PictureType::Av1KeyPicture {
+            self.missed.push(MissedKeyframe {
+                pts,
+                pic_type,
+                hidden,
+            });
+        }
+    }
+
+    pub(crate) fn take_missed(&mut self) -> Vec<MissedKeyframe> {
+        std::mem::take(&mut self.missed)
+    }
+}
+
+/// Expression deciding key frames, evaluated for each frame like ffmpeg `-force_key_frames expr:`.
+/// Frame is key frame when result isn't zero.
+///
+/// Variables are `n` (frame number from 0), `n_forced` (number of key frames forced so far),
+/// `prev_forced_n` and `prev_forced_t` (number and time of previous forced key frame, NAN
+/// before the first one), `t` (time of frame in seconds), constants `PI` and `E`. Operators are
+/// `+ - * / ^` and functions `abs ceil floor trunc round not isnan mod min max gt gte lt lte eq
+/// if ifnot between`.
+/// Optional `expr:` prefix is ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyframeExpr(Node);
+
+impl KeyframeExpr {
+    pub fn parse(expr: &str) -> Result<Self, ExprError> {
+        let expr = expr.strip_prefix("expr:").unwrap_or(expr);
+        let mut parser = Parser {
+            input: expr.as_bytes(),
+            pos: 0,
+        };
+        let node = parser.expr()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(Self(node)),
+            Some(_) => Err(ExprError::Unexpected(parser.pos)),
+        }
+    }
+
+    /// Evaluate with values of [`VARIABLES`]
+    fn eval(&self, variables: &[f64; 5]) -> f64 {
+        self.0.eval(variables)
+    }
+}
+
+impl FromStr for KeyframeExpr {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Error of parsing [`KeyframeExpr`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprError {
+    /// Unexpected character at byte offset
+    Unexpected(usize),
+    UnexpectedEnd,
+    UnknownName(String),
+    /// Function called with wrong number of arguments
+    ArgumentCount(String),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unexpected(pos) => write!(f, "unexpected character at {pos}"),
+            Self::UnexpectedEnd => f.write_str("unexpected end of expression"),
+            Self::UnknownName(name) => write!(f, "unknown variable or function `{name}`"),
+            Self::ArgumentCount(name) => write!(f, "wrong number of arguments of `{name}`"),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// Names of variables, in order of values passed to [`KeyframeExpr::eval`]
+const VARIABLES: [&str; 5] = ["n", "n_forced", "prev_forced_n", "prev_forced_t", "t"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Abs,
+    Ceil,
+    Floor,
+    Trunc,
+    Round,
+    Not,
+    IsNan,
+    Mod,
+    Min,
+    Max,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Eq,
+    If,
+    IfNot,
+    Between,
+}
+
+/// Name, function, minimum and maximum number of arguments
+const FUNCTIONS: &[(&str, Function, usize, usize)] = &[
+    ("abs", Function::Abs, 1, 1),
+    ("ceil", Function::Ceil, 1, 1),
+    ("floor", Function::Floor, 1, 1),
+    ("trunc", Function::Trunc, 1, 1),
+    ("round", Function::Round, 1, 1),
+    ("not", Function::Not, 1, 1),
+    ("isnan", Function::IsNan, 1, 1),
+    ("mod", Function::Mod, 2, 2),
+    ("min", Function::Min, 2, 2),
+    ("max", Function::Max, 2, 2),
+    ("gt", Function::Gt, 2, 2),
+    ("gte", Function::Gte, 2, 2),
+    ("lt", Function::Lt, 2, 2),
+    ("lte", Function::Lte, 2, 2),
+    ("eq", Function::Eq, 2, 2),
+    ("if", Function::If, 2, 3),
+    ("ifnot", Function::IfNot, 2, 3),
+    ("between", Function::Between, 3, 3),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    /// Index in [`VARIABLES`]
+    Variable(usize),
+    Negate(Box<Node>),
+    Binary(u8, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, variables: &[f64; 5]) -> f64 {
+        let truth = |value: bool| if value { 1.0 } else { 0.0 };
+        match self {
+            Self::Number(value) => *value,
+            Self::Variable(index) => variables[*index],
+            Self::Negate(node) => -node.eval(variables),
+            Self::Binary(op, a, b) => {
+                let (a, b) = (a.eval(variables), b.eval(variables));
+                match op {
+                    b'+' => a + b,
+                    b'-' => a - b,
+                    b'*' => a * b,
+                    b'/' => a / b,
+                    _ => a.powf(b),
+                }
+            }
+            Self::Call(function, args) => {
+                let arg = |i: usize| args[i].eval(variables);
+                match function {
+                    Function::Abs => arg(0).abs(),
+                    Function::Ceil => arg(0).ceil(),
+                    Function::Floor => arg(0).floor(),
+                    Function::Trunc => arg(0).trunc(),
+                    Function::Round => arg(0).round(),
+                    Function::Not => truth(arg(0) == 0.0),
+                    Function::IsNan => truth(arg(0).is_nan()),
+                    Function::Mod => {
+                        let (a, b) = (arg(0), arg(1));
+                        a - (a / b).floor() * b
+                    }
+                    Function::Min => arg(0).min(arg(1)),
+                    Function::Max => arg(0).max(arg(1)),
+                    Function::Gt => truth(arg(0) > arg(1)),
+                    Function::Gte => truth(arg(0) >= arg(1)),
+                    Function::Lt => truth(arg(0) < arg(1)),
+                    Function::Lte => truth(arg(0) <= arg(1)),
+                    Function::Eq => truth(arg(0) == arg(1)),
+                    Function::If | Function::IfNot => {
+                        let condition = (arg(0) != 0.0) == (*function == Function::If);
+                        match (condition, args.len()) {
+                            (true, _) => arg(1),
+                            (false, 3) => arg(2),
+                            (false, _) => 0.0,
+                        }
+                    }
+                    Function::Between => {
+                        let value = arg(0);
+                        truth(value >= arg(1) && value <= arg(2))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Recursive descent parser, `^` binds tighter than unary minus
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume `c` after whitespace
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expr(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.term()?;
+        loop {
+            let op = match () {
+                _ if self.eat(b'+') => b'+',
+                _ if self.eat(b'-') => b'-',
+                _ => return Ok(node),
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.unary()?;
+        loop {
+            let op = match () {
+                _ if self.eat(b'*') => b'*',
+                _ if self.eat(b'/') => b'/',
+                _ => return Ok(node),
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        if self.eat(b'-') {
+            Ok(Node::Negate(Box::new(self.unary()?)))
+        } else if self.eat(b'+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Node, ExprError> {
+        let base = self.primary()?;
+        if self.eat(b'^') {
+            // Right associative, exponent may have sign
+            Ok(Node::Binary(b'^', Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, ExprError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek() {
+            None => Err(ExprError::UnexpectedEnd),
+            Some(b'(') => {
+                self.pos += 1;
+                let node = self.expr()?;
+                self.expect(b')')?;
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_')
+                {
+                    self.pos += 1;
+                }
+                // Only ASCII was consumed
+                let name = std::str::from_utf8(&self.input[start..self.pos]).unwrap();
+                if self.eat(b'(') {
+                    self.call(name)
+                } else if let Some(index) = VARIABLES.iter().position(|&v| v == name) {
+                    Ok(Node::Variable(index))
+                } else {
+                    match name {
+                        "PI" => Ok(Node::Number(std::f64::consts::PI)),
+                        "E" => Ok(Node::Number(std::f64::consts::E)),
+                        _ => Err(ExprError::UnknownName(name.to_owned())),
+                    }
+                }
+            }
+            Some(_) => Err(ExprError::Unexpected(start)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Node, ExprError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == b'.') {
+            self.pos += 1;
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let exponent = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                // Not an exponent
+                self.pos = exponent;
+            }
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        std::str::from_utf8(&self.input[start..self.pos])
+            .unwrap()
+            .parse()
+            .map(Node::Number)
+            .map_err(|_| ExprError::Unexpected(start))
+    }
+
+    /// Arguments of function after opening parenthesis
+    fn call(&mut self, name: &str) -> Result<Node, ExprError> {
+        let &(_, function, min, max) = FUNCTIONS
+            .iter()
+            .find(|(function, ..)| *function == name)
+            .ok_or_else(|| ExprError::UnknownName(name.to_owned()))?;
+        let mut args = vec![self.expr()?];
+        while self.eat(b',') {
+            args.push(self.expr()?);
+        }
+        self.expect(b')')?;
+        if !(min..=max).contains(&args.len()) {
+            return Err(ExprError::ArgumentCount(name.to_owned()));
+        }
+        Ok(Node::Call(function, args))
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), ExprError> {
+        if self.eat(c) {
+            Ok(())
+        } else if self.peek().is_none() {
+            Err(ExprError::UnexpectedEnd)
+        } else {
+            Err(ExprError::Unexpected(self.pos))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframes(schedule: KeyframeSchedule, pts: impl IntoIterator<Item = i64>) -> Vec<i64> {
+        // 25 fps
+        let mut scheduler = KeyframeScheduler::new(schedule, 25, 1);
+        pts.into_iter()
+            .filter(|&pts| scheduler.push(Some(pts)))
+            .collect()
+    }
+
+    #[test]
+    fn schedules() {
+        assert_eq!(
+            keyframes(KeyframeSchedule::Timestamps(vec![30, 10, 10]), 0..40),
+            [10, 30]
+        );
+        // Timestamp 5 isn't sent, the next frame is forced
+        assert_eq!(
+            keyframes(KeyframeSchedule::Timestamps(vec![5]), (0..20).step_by(2)),
+            [6]
+        );
+        assert_eq!(
+            keyframes(KeyframeSchedule::Frames(vec![3, 1]), (0..20).step_by(2)),
+            [2, 6]
+        );
+        assert_eq!(
+            keyframes(KeyframeSchedule::Interval(1.5), 0..120),
+            [0, 38, 75, 113]
+        );
+        let expr = KeyframeExpr::parse("expr:gte(t, n_forced * 1.5)").unwrap();
+        assert_eq!(
+            keyframes(KeyframeSchedule::Expr(expr), 0..120),
+            [0, 38, 75, 113]
+        );
+        let expr = "if(isnan(prev_forced_n), 1, gte(n - prev_forced_n, 50))";
+        assert_eq!(
+            keyframes(KeyframeSchedule::Expr(expr.parse().unwrap()), 0..120),
+            [0, 50, 100]
+        );
+        let expr = "not(between(n, 0, 1)) * eq(mod(n, 10), 0)";
+        assert_eq!(
+            keyframes(KeyframeSchedule::Expr(expr.parse().unwrap()), 0..40),
+            [10, 20, 30]
+        );
+    }
+
+    #[test]
+    fn expressions() {
+        let eval = |expr: &str| {
+            KeyframeExpr::parse(expr)
+                .unwrap()
+                .eval(&[4.0, 1.0, 2.0, 0.5, 0.25])
+        };
+        assert_eq!(eval("1 + 2 * 3 - 4 / 2"), 5.0);
+        assert_eq!(eval("-2^2 + 2^-1"), -3.5);
+        assert_eq!(eval("2^3^2"), 512.0);
+        assert_eq!(eval("(n - n_forced) * t / prev_forced_t"), 1.5);
+        assert_eq!(
+            eval("mod(-3, n) + max(1e1, 2.5E-1) + if(0, 5) + ifnot(0, 6, 7)"),
+            17.0
+        );
+        assert_eq!(
+            eval("gt(n,4)+gte(n,4)+lt(n,4)+lte(n,4)+round(2.5)+trunc(-1.5)"),
+            4.0
+        );
+
+        assert_eq!(KeyframeExpr::parse("1 +"), Err(ExprError::UnexpectedEnd));
+        assert_eq!(KeyframeExpr::parse("(1"), Err(ExprError::UnexpectedEnd));
+        assert_eq!(KeyframeExpr::parse("1 2"), Err(ExprError::Unexpected(2)));
+        assert_eq!(
+            KeyframeExpr::parse("gt(1)"),
+            Err(ExprError::ArgumentCount("gt".to_owned()))
+        );
+        assert_eq!(
+            KeyframeExpr::parse("m"),
+            Err(ExprError::UnknownName("m".to_owned()))
+        );
+    }
+
+    #[test]
+    fn missed_keyframes() {
+        let layout = crate::yuv::PlanarLayout {
+            width: 16,
+            height: 16,
+            color_format: crate::ffi::ColorFormat::YUV420,
+            bit_depth: 8,
+        };
+        let buffer = vec![0; layout.frame_size()];
+        let scheduler = KeyframeScheduler::new(KeyframeSchedule::Frames(vec![2, 4]), 25, 1);
+        let mut decisions = KeyframeDecisions::new(Some(scheduler), None);
+        let forced = (0..6)
+            .map(|pts| decisions.decide(&layout.frame(&buffer), Some(pts), pts == 0))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            forced,
+            [
+                (true, 0),
+                (false, 1),
+                (true, 2),
+                (false, 3),
+                (true, 4),
+                (false, 5)
+            ]
+        );
+
+        use Av1PictureType::*;
+        decisions.check_packet(0, SvtFlags::empty(), Av1KeyPicture);
+        decisions.check_packet(4, SvtFlags::BUFFERFLAG_IS_ALT_REF, Av1FwKeyPicture);
+        decisions.check_packet(1, SvtFlags::empty(), Av1InterPicture);
+        decisions.check_packet(4, SvtFlags::empty(), Av1ShowExistingPicture);
+        decisions.check_packet(2, SvtFlags::empty(), Av1InterPicture);
+        assert_eq!(
+            decisions.take_missed(),
+            [
+                MissedKeyframe {
+                    pts: 4,
+                    pic_type: Av1FwKeyPicture,
+                    hidden: true
+                },
+                MissedKeyframe {
+                    pts: 2,
+                    pic_type: Av1InterPicture,
+                    hidden: false
+                }
+            ]
+        );
+        assert!(decisions.take_missed().is_empty());
+
+        // Frames without pts are checked by frame number
+        let scheduler = KeyframeScheduler::new(KeyframeSchedule::Frames(vec![1, 4]), 25, 1);
+        let mut decisions = KeyframeDecisions::new(Some(scheduler), None);
+        let forced = (0..6)
+            .filter_map(
+                |_| match decisions.decide(&layout.frame(&buffer), None, false) {
+                    (true, pts) => Some(pts),
+                    (false, _) => None,
+                },
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(forced, [1, 4]);
+        decisions.check_packet(1, SvtFlags::empty(), Av1KeyPicture);
+        decisions.check_packet(4, SvtFlags::empty(), Av1InterPicture);
+        assert_eq!(
+            decisions.take_missed(),
+            [MissedKeyframe {
+                pts: 4,
+                pic_type: Av1InterPicture,
+                hidden: false
+            }]
+        );
+    }
+}
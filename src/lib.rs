@@ -20,16 +20,47 @@
 //! let encoder = cfg.into_encoder();
 //!
 //! // use encoder.send_picture() to send raw YUV frames to encoder
-//! // use encoder.get_packet() to get done packet
+//! // use encoder.packets() to get done packets, encoder.send_eos() and encoder.drain() to get the rest
 //! ```
 //! More comprehensive example can be found [here]()
 
+pub mod avif;
+mod bmff;
+pub mod chunked;
+pub mod convert;
 pub mod ffi;
+pub mod ivf;
+pub mod keyframes;
+#[cfg(feature = "runtime-load")]
+pub mod loader;
+#[cfg(feature = "matroska")]
+pub mod matroska;
+pub mod metrics;
+#[cfg(feature = "mp4")]
+pub mod mp4;
+pub mod obu;
+pub mod pipeline;
+pub mod pool;
+pub mod scene;
+pub mod stats;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod version;
+#[cfg(feature = "y4m")]
+pub mod y4m;
+pub mod yuv;
 use ffi::*;
+use keyframes::{KeyframeDecisions, KeyframeSchedule, KeyframeScheduler, MissedKeyframe};
+use scene::{SceneDetector, SceneOptions};
+use stats::PacketStats;
 use std::{
     ffi::CString,
     mem::MaybeUninit,
     ops::Deref,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Mutex,
+    },
 };
 
 macro_rules! call_c_code {
@@ -81,62 +112,252 @@ impl<'a> Frame<'a> {
     }
 }
 
-/// Encoded AV1 data (packet) in borrowed form. For owned form, use [`to_vec`]
+/// Encoder component returned by `svt_av1_enc_init_handle` and initialized with `svt_av1_enc_init`.
+/// Shared by [`SvtAv1Encoder`] and its [`Packet`]s, so the library is deinitialized only after
+/// encoder and all output buffers are dropped
+#[derive(Debug)]
+struct EncoderHandle(*mut ComponentType);
+
+// SAFETY: handle is only passed to library calls. Output buffers are released through
+// `svt_av1_enc_release_out_buffer`, which returns them to library's pool under its own lock,
+// so packets can be dropped on any thread. Calls which need exclusive access go through
+// `SvtAv1Encoder`, which isn't `Sync`
+unsafe impl Send for EncoderHandle {}
+unsafe impl Sync for EncoderHandle {}
+
+impl EncoderHandle {
+    /// Deinitialize encoder and free handle, reporting the first error
+    fn close(mut self) -> Result<()> {
+        let handle = std::mem::replace(&mut self.0, std::ptr::null_mut());
+        deinit(handle)
+    }
+}
+
+impl Drop for EncoderHandle {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            let _ = deinit(self.0);
+        }
+    }
+}
+
+/// Deinitialize encoder and free handle, same teardown as in SvtAv1EncApp. Handle is freed
+/// even if deinit fails
+fn deinit(handle: *mut ComponentType) -> Result<()> {
+    let deinit = unsafe { svt_av1_enc_deinit(handle) };
+    call_c_code!(svt_av1_enc_deinit_handle(handle));
+    match deinit {
+        ErrorType::ErrorNone => Ok(()),
+        err => Err(err),
+    }
+}
+
+/// Encoded AV1 data (packet) in library's output buffer. For owned form, use [`to_vec`] or [`OwnedPacket`].
+///
+/// Packet keeps encoder component alive, so it can outlive [`SvtAv1Encoder`] and be moved
+/// to other threads. Output buffer is returned to the library when packet is dropped
 ///
 /// [`to_vec`]: method@Self::to_vec
 #[derive(Debug)]
-pub struct Packet<'a> {
+pub struct Packet {
     pub flags: SvtFlags,
-    data: &'a [u8],
+    /// Presentation timestamp of the frame shown by this packet
+    pub pts: i64,
+    /// Type of the picture contained in this packet
+    pub pic_type: Av1PictureType,
+    /// Quantizer and quality of reconstructed frame
+    pub stats: PacketStats,
     p_buffer: *mut BufferHeaderType,
+    /// Keeps library alive until buffer is released
+    _handle: Arc<EncoderHandle>,
 }
 
-unsafe impl Send for Packet<'_> {}
-unsafe impl Sync for Packet<'_> {}
+// SAFETY: output buffer is owned by packet until release and not accessed by the library
+// meanwhile, see `EncoderHandle` for releasing it from other thread
+unsafe impl Send for Packet {}
+unsafe impl Sync for Packet {}
+
+impl Packet {
+    /// Whether this packet contains a key frame, from which decoding can start
+    pub fn is_keyframe(&self) -> bool {
+        self.pic_type == Av1PictureType::Av1KeyPicture
+    }
 
-impl Packet<'_> {
     /// Clone packed AV1 data and return it as bytes
     pub fn to_vec(self) -> Vec<u8> {
-        self.data.to_owned()
+        self[..].to_vec()
     }
 }
 
-impl Deref for Packet<'_> {
+impl Deref for Packet {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        self.data
+        // SAFETY: buffer is filled by the library and valid until release in drop
+        unsafe {
+            let len = (*self.p_buffer).n_filled_len as usize;
+            if len == 0 {
+                // Pointer of empty (EOS) packet can be null
+                return &[];
+            }
+            std::slice::from_raw_parts((*self.p_buffer).p_buffer, len)
+        }
     }
 }
 
-impl Drop for Packet<'_> {
+impl Drop for Packet {
     fn drop(&mut self) {
-        // SAFETY: we require Packet's lifetime to live not longer
-        // than encoder itself so it's safe to call
+        // SAFETY: `_handle` is still alive, fields are dropped after this call
         unsafe { svt_av1_enc_release_out_buffer(&mut self.p_buffer) };
     }
 }
 
-/// SVT-AV1 Encoder. Use [`send_picture`] to send some data to encoder and [`get_packet`] to get encoded packets
+/// Encoded AV1 data (packet) in owned form, which doesn't hold encoder output buffer
+#[derive(Debug, Clone)]
+pub struct OwnedPacket {
+    pub flags: SvtFlags,
+    /// Presentation timestamp of the frame shown by this packet
+    pub pts: i64,
+    /// Type of the picture contained in this packet
+    pub pic_type: Av1PictureType,
+    /// Quantizer and quality of reconstructed frame
+    pub stats: PacketStats,
+    pub data: Vec<u8>,
+}
+
+impl OwnedPacket {
+    /// Whether this packet contains a key frame, from which decoding can start
+    pub fn is_keyframe(&self) -> bool {
+        self.pic_type == Av1PictureType::Av1KeyPicture
+    }
+}
+
+impl Deref for OwnedPacket {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl From<Packet> for OwnedPacket {
+    fn from(packet: Packet) -> Self {
+        Self {
+            flags: packet.flags,
+            pts: packet.pts,
+            pic_type: packet.pic_type,
+            stats: packet.stats,
+            data: packet[..].to_vec(),
+        }
+    }
+}
+
+/// Stage of encoder lifecycle. Encoder is created in [`Encoding`] state from configured
+/// [`SvtAv1EncoderConfig`], [`send_eos`] moves it to [`Flushing`] and packet with EOS flag to [`Finished`].
+/// Library error moves it to [`Failed`] from any state
+///
+/// [`Encoding`]: EncoderState::Encoding
+/// [`Flushing`]: EncoderState::Flushing
+/// [`Finished`]: EncoderState::Finished
+/// [`Failed`]: EncoderState::Failed
+/// [`send_eos`]: SvtAv1Encoder::send_eos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncoderState {
+    /// Encoder accepts frames
+    Encoding = 0,
+    /// End of stream was sent, remaining packets can be received
+    Flushing = 1,
+    /// Packet with EOS flag was received, encoder has no more output
+    Finished = 2,
+    /// Library returned error, other than empty queue or shutdown, from sending or receiving.
+    /// Encoder doesn't accept frames, and isn't flushed on drop, because the library may never
+    /// reach end of stream
+    Failed = 3,
+}
+
+impl EncoderState {
+    fn from_u8(state: u8) -> Self {
+        match state {
+            0 => Self::Encoding,
+            1 => Self::Flushing,
+            2 => Self::Finished,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// SVT-AV1 Encoder. Use [`send_picture`] to send some data to encoder and [`get_packet`] to get encoded packets.
+/// To send frames and receive packets at the same time, move encoder into [`EncoderPipeline`]
+///
+/// Encoder tracks its [`EncoderState`], so calls which the library doesn't expect return error instead
+/// of reaching it: [`send_picture`] after [`send_eos`] and [`get_packet`] after end of stream return
+/// [`ErrorType::NoErrorFifoShutdown`], repeated [`send_eos`] does nothing. Dropping encoder before
+/// end of stream sends EOS and discards remaining packets, so the library is shut down cleanly,
+/// unless it returned an error before ([`EncoderState::Failed`]).
 ///
 /// [`send_picture`]: method@Self::send_picture
+/// [`send_eos`]: method@Self::send_eos
+/// [`EncoderPipeline`]: pipeline::EncoderPipeline
 /// [`get_packet`]: method@Self::get_packet
 #[derive(Debug)]
 pub struct SvtAv1Encoder {
-    handle: *mut ComponentType,
+    handle: Arc<EncoderHandle>,
+    config: SvtAv1EncConfiguration,
+    /// [`EncoderState`], atomic because pipeline workers share encoder between threads
+    state: AtomicU8,
+    /// Key frame schedule and scene detection of config, locked because of the same sharing
+    keyframes: Mutex<KeyframeDecisions>,
 }
 
 unsafe impl Send for SvtAv1Encoder {}
 
 impl SvtAv1Encoder {
+    /// Configuration the encoder was initialized with
+    pub fn config(&self) -> &SvtAv1EncConfiguration {
+        &self.config
+    }
+
+    /// Current stage of encoder lifecycle
+    pub fn state(&self) -> EncoderState {
+        EncoderState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    /// Get stream headers (sequence header OBU). Can be called at any time after encoder is created
+    pub fn stream_header(&self) -> Result<Vec<u8>> {
+        let mut header: MaybeUninit<*mut BufferHeaderType> = MaybeUninit::uninit();
+        call_c_code!(svt_av1_enc_stream_header(self.handle.0, header.as_mut_ptr()));
+        // SAFETY: we checked that svt_av1_enc_stream_header doesn't return error, so it's initialized
+        let header = unsafe { header.assume_init() };
+        let data = unsafe {
+            std::slice::from_raw_parts((*header).p_buffer, (*header).n_filled_len as usize).to_vec()
+        };
+        call_c_code!(svt_av1_enc_stream_header_release(header));
+        Ok(data)
+    }
+
     /// Send data to Encoder.
     /// * This function takes [`Frame`], which borrows provided YUV data
+    /// * Frame sent without `pts` gets its number, counted from 0, as pts
+    /// * Returns [`ErrorType::NoErrorFifoShutdown`] after [`send_eos`]
+    /// * With [`SvtAv1EncoderConfig::set_keyframe_schedule`] and
+    ///   [`SvtAv1EncoderConfig::set_scene_detection`], scheduled frames and frames starting
+    ///   a new scene are also forced to be key frames. Frames must be sent in presentation order then
+    ///
+    /// [`send_eos`]: method@Self::send_eos
     pub fn send_picture(
         &self,
         frame: Frame<'_>,
         pts: Option<i64>,
         force_keyframe: bool,
     ) -> Result<()> {
+        if self.state() != EncoderState::Encoding {
+            return Err(ErrorType::NoErrorFifoShutdown);
+        }
+        let (force_keyframe, pts) = self
+            .keyframes
+            .lock()
+            .unwrap()
+            .decide(&frame, pts, force_keyframe);
         let mut svt_frame = SvtIOFormat {
             luma: frame.luma as *const [u8] as *const _,
             cb: frame.cb as *const [u8] as *const _,
@@ -150,69 +371,271 @@ impl SvtAv1Encoder {
             pic_type: Av1PictureType::Av1InvalidPicture,
             n_filled_len: frame.size,
             p_buffer: &mut svt_frame as *mut SvtIOFormat as *mut u8,
+            pts,
             ..Default::default()
         };
-        if let Some(pts) = pts {
-            buf.pts = pts
-        }
 
         if force_keyframe {
             buf.pic_type = Av1PictureType::Av1KeyPicture
         }
 
-        call_c_code!(svt_av1_enc_send_picture(self.handle, &mut buf));
+        let res = unsafe { svt_av1_enc_send_picture(self.handle.0, &mut buf) };
+        if res != ErrorType::ErrorNone {
+            return Err(self.fail(res));
+        }
         Ok(())
     }
 
-    /// Send end of stream signal to encoder. Use it when you run out of frames to encode
+    /// Send end of stream signal to encoder. Use it when you run out of frames to encode.
+    /// Does nothing if end of stream was already sent
     pub fn send_eos(&self) -> Result<()> {
+        let sent = self.state.compare_exchange(
+            EncoderState::Encoding as u8,
+            EncoderState::Flushing as u8,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        if sent.is_err() {
+            return Ok(());
+        }
         let mut buf = BufferHeaderType {
             flags: SvtFlags::BUFFERFLAG_EOS.bits(),
             ..Default::default()
         };
-        call_c_code!(svt_av1_enc_send_picture(self.handle, &mut buf));
+        let res = unsafe { svt_av1_enc_send_picture(self.handle.0, &mut buf) };
+        if res != ErrorType::ErrorNone {
+            // EOS didn't reach encoder, so it can't be flushed
+            self.state.store(EncoderState::Encoding as u8, Ordering::Release);
+            return Err(self.fail(res));
+        }
 
         Ok(())
     }
 
+    /// Move to [`EncoderState::Failed`] on library error, which isn't empty queue or shutdown,
+    /// and return the error
+    fn fail(&self, err: ErrorType) -> ErrorType {
+        if !matches!(
+            err,
+            ErrorType::NoErrorEmptyQueue | ErrorType::NoErrorFifoShutdown
+        ) {
+            self.state.store(EncoderState::Failed as u8, Ordering::Release);
+        }
+        err
+    }
+
     ///  Receive packet.
     /// * This function will become blocking if either pic_send_done is set to 1 or if we are in low-delay (pred-struct=1).
     /// * Otherwise, this function is non-blocking and will return NoErrorEmptyQueue if there are no packets available.
-    pub fn get_packet(&self, pic_send_done: u8) -> Result<Packet<'_>> {
+    /// * Returns [`ErrorType::NoErrorFifoShutdown`] after packet with EOS flag, encoder has no more output then.
+    pub fn get_packet(&self, pic_send_done: u8) -> Result<Packet> {
+        if self.state() == EncoderState::Finished {
+            return Err(ErrorType::NoErrorFifoShutdown);
+        }
         let mut pict: MaybeUninit<*mut BufferHeaderType> = MaybeUninit::uninit();
-        call_c_code!(svt_av1_enc_get_packet(
-            self.handle,
-            pict.as_mut_ptr(),
-            pic_send_done
-        ));
+        let res =
+            unsafe { svt_av1_enc_get_packet(self.handle.0, pict.as_mut_ptr(), pic_send_done) };
+        if res != ErrorType::ErrorNone {
+            return Err(self.fail(res));
+        }
         unsafe {
             // SAFETY: we checked that svt_av1_enc_get_packet doesn't return error, so it's initialized
             let header_type = pict.assume_init();
-            let buffer = &*std::ptr::slice_from_raw_parts_mut(
-                (*header_type).p_buffer,
-                (*header_type).n_filled_len as usize,
-            );
             let packet = Packet {
-                data: buffer,
                 p_buffer: header_type,
+                _handle: Arc::clone(&self.handle),
                 flags: SvtFlags::from_bits((*header_type).flags).unwrap(),
+                pts: (*header_type).pts,
+                pic_type: (*header_type).pic_type,
+                stats: PacketStats::from_buffer(&*header_type),
             };
+            if packet.flags.contains(SvtFlags::BUFFERFLAG_EOS) {
+                self.state.store(EncoderState::Finished as u8, Ordering::Release);
+            }
+            self.keyframes
+                .lock()
+                .unwrap()
+                .check_packet(packet.pts, packet.flags, packet.pic_type);
             Ok(packet)
         }
     }
+
+    /// Take key frames forced in [`send_picture`], which came out of [`get_packet`]
+    /// as hidden or not key frames since the previous call
+    ///
+    /// [`send_picture`]: method@Self::send_picture
+    /// [`get_packet`]: method@Self::get_packet
+    pub fn take_missed_keyframes(&self) -> Vec<MissedKeyframe> {
+        self.keyframes.lock().unwrap().take_missed()
+    }
+
+    /// Copy next reconstructed picture into `buffer` and return its pts. Requires `recon_enabled`
+    /// in config. Picture is planar with tightly packed planes, as described by [`PlanarLayout`]
+    /// of source size, bit depth and color format, high bit depth samples take 2 bytes.
+    /// Returns [`ErrorType::NoErrorEmptyQueue`] when no picture is ready.
+    ///
+    /// Panics if `buffer` is smaller than [`PlanarLayout::frame_size`], library copies whole picture
+    /// without checking buffer size
+    ///
+    /// [`PlanarLayout`]: yuv::PlanarLayout
+    /// [`PlanarLayout::frame_size`]: yuv::PlanarLayout::frame_size
+    pub fn get_recon(&self, buffer: &mut [u8]) -> Result<i64> {
+        let layout = yuv::PlanarLayout {
+            width: self.config.source_width,
+            height: self.config.source_height,
+            color_format: self.config.encoder_color_format,
+            bit_depth: self.config.encoder_bit_depth,
+        };
+        assert!(buffer.len() >= layout.frame_size(), "recon buffer is too small");
+        let mut header = BufferHeaderType {
+            size: std::mem::size_of::<BufferHeaderType>() as u32,
+            p_buffer: buffer.as_mut_ptr(),
+            n_alloc_len: buffer.len() as u32,
+            ..Default::default()
+        };
+        call_c_code!(svt_av1_get_recon(self.handle.0, &mut header));
+        if header.n_filled_len == 0 {
+            // Recon of EOS has no picture
+            return Err(ErrorType::NoErrorEmptyQueue);
+        }
+        Ok(header.pts)
+    }
+
+    /// Iterator over packets, which are ready now. Stops when output queue is empty or after packet
+    /// with EOS flag. Doesn't block, except in low-delay mode (pred-struct=1), same as [`get_packet`]
+    ///
+    /// [`get_packet`]: method@Self::get_packet
+    pub fn packets(&self) -> PacketIter<'_> {
+        PacketIter {
+            encoder: self,
+            blocking: false,
+            failed: false,
+        }
+    }
+
+    /// Flush encoder, discarding remaining packets, and deinitialize the library. Unlike drop,
+    /// reports errors of flushing and teardown.
+    ///
+    /// If [`Packet`]s of this encoder are still alive, deinitialization is deferred until the last
+    /// of them is dropped and its errors are ignored. Convert packets to [`OwnedPacket`] or drop them
+    /// before closing to get all errors
+    pub fn close(self) -> Result<()> {
+        self.send_eos()?;
+        for packet in self.drain() {
+            packet?;
+        }
+        let handle = Arc::clone(&self.handle);
+        // Encoder is finished, so its drop doesn't touch the library
+        drop(self);
+        match Arc::into_inner(handle) {
+            Some(handle) => handle.close(),
+            None => Ok(()),
+        }
+    }
+
+    /// Blocking iterator over all remaining packets, stops after packet with EOS flag.
+    /// Use it after [`send_eos`]
+    ///
+    /// [`send_eos`]: method@Self::send_eos
+    pub fn drain(&self) -> PacketIter<'_> {
+        PacketIter {
+            encoder: self,
+            blocking: true,
+            failed: false,
+        }
+    }
 }
 
 impl Drop for SvtAv1Encoder {
     fn drop(&mut self) {
-        unsafe {
-            svt_av1_enc_deinit(self.handle);
-            svt_av1_enc_deinit_handle(self.handle);
+        // Library has to reach end of stream before deinit, remaining packets are discarded.
+        // After library error it may never reach it and drain would block forever
+        if self.state() != EncoderState::Failed && self.send_eos().is_ok() {
+            for packet in self.drain() {
+                if packet.is_err() {
+                    break;
+                }
+            }
+        }
+        // Library is deinitialized when the last of encoder and its packets drops the handle,
+        // errors are ignored, use `close` to get them
+    }
+}
+
+/// Iterator over encoded packets, created by [`SvtAv1Encoder::packets`] and [`SvtAv1Encoder::drain`]
+#[derive(Debug)]
+pub struct PacketIter<'a> {
+    encoder: &'a SvtAv1Encoder,
+    blocking: bool,
+    /// Error was returned, iteration stops after it
+    failed: bool,
+}
+
+impl Iterator for PacketIter<'_> {
+    type Item = Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed
+            || matches!(
+                self.encoder.state(),
+                EncoderState::Finished | EncoderState::Failed
+            )
+        {
+            return None;
+        }
+        match self.encoder.get_packet(self.blocking as u8) {
+            Ok(packet) => Some(Ok(packet)),
+            Err(ErrorType::NoErrorEmptyQueue) if !self.blocking => None,
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Error of creating [`SvtAv1EncoderConfig`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Width must be in 64..=16384 and height in 64..=8704
+    Size { width: u32, height: u32 },
+    /// Preset must be in -2..=13
+    Preset(i8),
+    /// With `dynamic` feature, linked library isn't [`BINDINGS_VERSION`]
+    ///
+    /// [`BINDINGS_VERSION`]: version::BINDINGS_VERSION
+    Version(version::VersionError),
+    /// Library failed to create handle. With `runtime-load` feature it's
+    /// [`ErrorType::ErrorInvalidComponent`] when library can't be loaded
+    Init(ErrorType),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Size { width, height } => write!(
+                f,
+                "picture size {width}x{height} is out of supported range 64x64 to 16384x8704"
+            ),
+            Self::Preset(preset) => write!(f, "preset {preset} isn't in between -2 and 13"),
+            Self::Version(err) => err.fmt(f),
+            Self::Init(err) => write!(f, "failed to init svt av1 handle: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Version(err) => Some(err),
+            Self::Init(err) => Some(err),
+            _ => None,
         }
     }
 }
 
 /// Config for SvtAv1Enc encoder. Construct it with [`new`] function,
-/// than create encoder with [`into_encoder`]
+/// than create encoder with [`into_encoder`]. Dropping config without creating encoder frees its handle
 ///
 /// [`new`]: method@Self::new
 /// [`into_encoder`]: method@Self::into_encoder
@@ -220,38 +643,70 @@ impl Drop for SvtAv1Encoder {
 pub struct SvtAv1EncoderConfig {
     pub config: SvtAv1EncConfiguration,
     handle: *mut ComponentType,
+    scene_detection: Option<SceneOptions>,
+    keyframe_schedule: Option<KeyframeSchedule>,
 }
 
 impl SvtAv1EncoderConfig {
     /// Create new config with default settings and set width and height parameters. Lift of settings can be found [here](https://github.com/BlueSwordM/svt-av1-psyex/blob/master/Docs/Parameters.md#encoder-global-options)
+    ///
+    /// With `dynamic` feature, linked library must be [`BINDINGS_VERSION`], because its
+    /// configuration layout may differ. [`try_new`] returns [`ConfigError::Version`] otherwise,
+    /// or use [`check_version`] before.
+    ///
+    /// [`BINDINGS_VERSION`]: version::BINDINGS_VERSION
+    /// [`check_version`]: version::check_version
+    ///
+    /// # Panics
+    /// Panics on any error of [`try_new`], including library not loaded with `runtime-load` feature
+    ///
+    /// [`try_new`]: method@Self::try_new
     pub fn new(width: u32, height: u32, preset: Option<i8>) -> Self {
-        assert!((64..=16384).contains(&width));
-        assert!((64..=8704).contains(&height));
+        Self::try_new(width, height, preset).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Create new config like [`new`], but return error instead of panicking on invalid size
+    /// or preset and when library can't create handle
+    ///
+    /// [`new`]: method@Self::new
+    pub fn try_new(
+        width: u32,
+        height: u32,
+        preset: Option<i8>,
+    ) -> std::result::Result<Self, ConfigError> {
+        if !(64..=16384).contains(&width) || !(64..=8704).contains(&height) {
+            return Err(ConfigError::Size { width, height });
+        }
+        if let Some(preset) = preset.filter(|preset| !(-2..=13).contains(preset)) {
+            return Err(ConfigError::Preset(preset));
+        }
+        #[cfg(feature = "dynamic")]
+        version::check_version().map_err(ConfigError::Version)?;
         let mut handle: MaybeUninit<*mut ComponentType> = MaybeUninit::uninit();
         let mut config: MaybeUninit<SvtAv1EncConfiguration> = MaybeUninit::uninit();
 
         let mut cfg = unsafe {
             let res =
                 svt_av1_enc_init_handle(handle.as_mut_ptr(), config.as_mut_ptr());
-            assert!(res == ErrorType::ErrorNone, "Failed to init svt av1 handle");
+            if res != ErrorType::ErrorNone {
+                return Err(ConfigError::Init(res));
+            }
 
             // SAFETY: we checked that result is ErrorNone
             SvtAv1EncoderConfig {
                 handle: handle.assume_init(),
                 config: config.assume_init(),
+                scene_detection: None,
+                keyframe_schedule: None,
             }
         };
         cfg.config.source_width = width;
         cfg.config.source_height = height;
         if let Some(preset) = preset {
-            assert!(
-                (-2..=13).contains(&preset),
-                "Preset must be in between -2 and 13"
-            );
             cfg.config.enc_mode = preset;
         }
 
-        cfg
+        Ok(cfg)
     }
 
     /// Set parameter for config from string. Note that string parameter name differs from [`SvtAv1EncConfiguration`] fields
@@ -271,21 +726,90 @@ impl SvtAv1EncoderConfig {
         Ok(())
     }
 
-    /// Apply configuration and initialize encoder. It can return error in case of invalid configuration
+    /// Force key frames at scene changes found by [`SceneDetector`] in frames sent to encoder,
+    /// in addition to ones requested by caller. `None` disables it. Sets `force_key_frames`
+    /// of [`config`] when encoder is created, library ignores forced key frames without it
+    ///
+    /// [`config`]: field@Self::config
+    pub fn set_scene_detection(&mut self, options: Option<SceneOptions>) {
+        self.scene_detection = options;
+    }
+
+    /// Force key frames of schedule in frames sent to encoder, in addition to ones requested
+    /// by caller. Time of frames is counted with frame rate of [`config`]. `None` disables it.
+    /// Sets `force_key_frames` of [`config`] when encoder is created, like scene detection
+    ///
+    /// [`config`]: field@Self::config
+    pub fn set_keyframe_schedule(&mut self, schedule: Option<KeyframeSchedule>) {
+        self.keyframe_schedule = schedule;
+    }
+
+    /// Apply configuration and initialize encoder. It can return error in case of invalid configuration,
+    /// handle is freed then
     pub fn into_encoder(mut self) -> Result<SvtAv1Encoder> {
+        if self.scene_detection.is_some() || self.keyframe_schedule.is_some() {
+            self.config.force_key_frames = true;
+        }
         call_c_code!(svt_av1_enc_set_parameter(self.handle, &mut self.config));
-        call_c_code!(svt_av1_enc_init(self.handle));
+        let res = unsafe { svt_av1_enc_init(self.handle) };
+        if res != ErrorType::ErrorNone {
+            // Release resources created before init failed, handle itself is freed by drop
+            let _ = unsafe { svt_av1_enc_deinit(self.handle) };
+            return Err(res);
+        }
 
+        let handle = std::mem::replace(&mut self.handle, std::ptr::null_mut());
         Ok(SvtAv1Encoder {
-            handle: self.handle,
+            handle: Arc::new(EncoderHandle(handle)),
+            config: self.config,
+            state: AtomicU8::new(EncoderState::Encoding as u8),
+            keyframes: Mutex::new(KeyframeDecisions::new(
+                self.keyframe_schedule
+                    .take()
+                    .map(|schedule| KeyframeScheduler::from_config(schedule, &self.config)),
+                self.scene_detection
+                    .map(|options| SceneDetector::from_config(&self.config, options)),
+            )),
         })
     }
 }
 
+impl Drop for SvtAv1EncoderConfig {
+    fn drop(&mut self) {
+        // Null after handle was moved to encoder
+        if !self.handle.is_null() {
+            let _ = unsafe { svt_av1_enc_deinit_handle(self.handle) };
+        }
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
+    /// Zeroed configuration with valid enum values, for tests which don't need the library
+    pub(crate) fn test_config(width: u32, height: u32) -> SvtAv1EncConfiguration {
+        let mut cfg = MaybeUninit::<SvtAv1EncConfiguration>::zeroed();
+        let p = cfg.as_mut_ptr();
+        // SAFETY: all fields are valid when zeroed, except enums without zero variant
+        unsafe {
+            std::ptr::addr_of_mut!((*p).intra_refresh_type)
+                .write(SvtAv1IntraRefreshType::SvtAv1KfRefresh);
+            std::ptr::addr_of_mut!((*p).sframe_mode).write(SFrameMode::SframeNearestBase);
+            let mut cfg = cfg.assume_init();
+            cfg.source_width = width;
+            cfg.source_height = height;
+            cfg.frame_rate_numerator = 25;
+            cfg.frame_rate_denominator = 1;
+            cfg.encoder_bit_depth = 8;
+            cfg.encoder_color_format = ColorFormat::YUV420;
+            cfg.color_primaries = ColorPrimaries::CicpCpUnspecified;
+            cfg.transfer_characteristics = TransferCharacteristics::CicpTcUnspecified;
+            cfg.matrix_coefficients = MatrixCoefficients::CicpMcUnspecified;
+            cfg
+        }
+    }
+
     #[test]
     fn init() {
         let enc = SvtAv1EncoderConfig::new(1920, 1080, None);
@@ -301,6 +825,29 @@ mod tests {
         cfg.into_encoder().unwrap();
     }
 
+    #[test]
+    fn invalid_size_and_preset() {
+        // Checked before library is called
+        assert_eq!(
+            SvtAv1EncoderConfig::try_new(32, 64, None).unwrap_err(),
+            ConfigError::Size {
+                width: 32,
+                height: 64
+            }
+        );
+        assert_eq!(
+            SvtAv1EncoderConfig::try_new(64, 9000, None).unwrap_err(),
+            ConfigError::Size {
+                width: 64,
+                height: 9000
+            }
+        );
+        assert_eq!(
+            SvtAv1EncoderConfig::try_new(64, 64, Some(14)).unwrap_err(),
+            ConfigError::Preset(14)
+        );
+    }
+
     #[test]
     fn set_parameter() {
         let mut cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
@@ -309,4 +856,162 @@ mod tests {
 
         cfg.into_encoder().unwrap();
     }
+
+    #[test]
+    fn lifecycle() {
+        let mut cfg = SvtAv1EncoderConfig::new(64, 64, Some(13));
+        cfg.config.frame_rate_numerator = 25;
+        let enc = cfg.into_encoder().unwrap();
+        assert_eq!(enc.state(), EncoderState::Encoding);
+
+        let buffer = vec![128; 64 * 64 * 3 / 2];
+        let frame = Frame::new(
+            &buffer[..4096],
+            &buffer[4096..5120],
+            &buffer[5120..],
+            64,
+            32,
+            32,
+            buffer.len() as u32,
+        );
+        enc.send_picture(frame, Some(0), false).unwrap();
+        enc.send_eos().unwrap();
+        assert_eq!(enc.state(), EncoderState::Flushing);
+        assert_eq!(
+            enc.send_picture(frame, Some(1), false),
+            Err(ErrorType::NoErrorFifoShutdown)
+        );
+        enc.send_eos().unwrap();
+
+        assert!(enc.drain().all(|packet| packet.is_ok()));
+        assert_eq!(enc.state(), EncoderState::Finished);
+        assert_eq!(enc.get_packet(1).unwrap_err(), ErrorType::NoErrorFifoShutdown);
+        assert_eq!(enc.drain().count(), 0);
+
+        // Encoder dropped in the middle of stream is flushed by drop
+        let enc = SvtAv1EncoderConfig::new(64, 64, Some(13))
+            .into_encoder()
+            .unwrap();
+        enc.send_picture(frame, Some(0), false).unwrap();
+        drop(enc);
+    }
+
+    #[test]
+    fn drop_after_error() {
+        let enc = SvtAv1EncoderConfig::new(64, 64, Some(13))
+            .into_encoder()
+            .unwrap();
+        let buffer = vec![128; 64 * 64 * 3 / 2];
+        let frame = Frame::new(
+            &buffer[..4096],
+            &buffer[4096..5120],
+            &buffer[5120..],
+            64,
+            32,
+            32,
+            buffer.len() as u32,
+        );
+        enc.send_picture(frame, Some(0), false).unwrap();
+        // Empty queue isn't a failure
+        assert_eq!(
+            enc.fail(ErrorType::NoErrorEmptyQueue),
+            ErrorType::NoErrorEmptyQueue
+        );
+        assert_eq!(enc.state(), EncoderState::Encoding);
+        // Same path as error returned by the library
+        assert_eq!(
+            enc.fail(ErrorType::ErrorUndefined),
+            ErrorType::ErrorUndefined
+        );
+        assert_eq!(enc.state(), EncoderState::Failed);
+        assert_eq!(
+            enc.send_picture(frame, Some(1), false),
+            Err(ErrorType::NoErrorFifoShutdown)
+        );
+        assert_eq!(enc.drain().count(), 0);
+
+        // Drop doesn't wait for end of stream, which the library won't reach
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            drop(enc);
+            done_tx.send(()).unwrap();
+        });
+        done_rx
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .expect("drop of failed encoder blocked");
+    }
+
+    // Run under AddressSanitizer (see README) to catch reads of released packets
+    #[test]
+    fn packet_outlives_encoder() {
+        let enc = SvtAv1EncoderConfig::new(64, 64, Some(13))
+            .into_encoder()
+            .unwrap();
+        let buffer = vec![128; 64 * 64 * 3 / 2];
+        let frame = Frame::new(
+            &buffer[..4096],
+            &buffer[4096..5120],
+            &buffer[5120..],
+            64,
+            32,
+            32,
+            buffer.len() as u32,
+        );
+        for pts in 0..4 {
+            enc.send_picture(frame, Some(pts), false).unwrap();
+        }
+        enc.send_eos().unwrap();
+        let packets = enc.drain().collect::<Result<Vec<_>>>().unwrap();
+        let handle = Arc::downgrade(&enc.handle);
+        let expected = packets.iter().map(|p| p[..].to_vec()).collect::<Vec<_>>();
+
+        // Packets keep library alive after encoder is dropped
+        drop(enc);
+        assert!(handle.upgrade().is_some());
+        assert_eq!(packets[0][..], expected[0]);
+
+        // The last packet is released on another thread, deinit follows there
+        let mut packets = packets.into_iter();
+        let first = packets.next().unwrap();
+        let rest = std::thread::spawn(move || {
+            packets.map(|packet| packet.to_vec()).collect::<Vec<_>>()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(rest, expected[1..]);
+        assert!(handle.upgrade().is_some());
+        drop(first);
+        assert!(handle.upgrade().is_none());
+    }
+
+    // Run under Valgrind or AddressSanitizer (see README) to check that handles are freed
+    #[test]
+    fn teardown() {
+        for _ in 0..100 {
+            drop(SvtAv1EncoderConfig::new(64, 64, None));
+        }
+        for _ in 0..20 {
+            let mut cfg = SvtAv1EncoderConfig::new(64, 64, None);
+            cfg.config.frame_rate_numerator = 0;
+            assert!(cfg.into_encoder().is_err());
+        }
+        for _ in 0..5 {
+            let enc = SvtAv1EncoderConfig::new(64, 64, Some(13))
+                .into_encoder()
+                .unwrap();
+            enc.close().unwrap();
+        }
+
+        // Packets alive at close defer deinit
+        let enc = SvtAv1EncoderConfig::new(64, 64, Some(13))
+            .into_encoder()
+            .unwrap();
+        enc.send_eos().unwrap();
+        let packets = enc.drain().collect::<Result<Vec<_>>>().unwrap();
+        let handle = Arc::downgrade(&enc.handle);
+        enc.close().unwrap();
+        assert!(handle.upgrade().is_some());
+        drop(packets);
+        assert!(handle.upgrade().is_none());
+    }
 }
@@ -0,0 +1,297 @@
+//! Loading SVT-AV1-PSYEX at runtime, enabled by `runtime-load` feature.
+//!
+//! Instead of linking, functions of [`ffi`](crate::ffi) dispatch to a library opened with
+//! [`load`] or [`load_default`]. Safe API is unchanged: the first call into the library loads
+//! it from default search paths, if it isn't loaded yet. This is tried only once, its error is
+//! returned by [`default_load_error`]. When library can't be loaded, calls
+//! return [`ErrorType::ErrorInvalidComponent`] and [`SvtAv1EncoderConfig::try_new`] returns
+//! [`ConfigError::Init`], while [`SvtAv1EncoderConfig::new`] panics. Call [`load_default`] at
+//! startup to find out whether encoding is available and why not.
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::loader;
+//! match loader::load_default() {
+//!     Ok(version) => println!("AV1 encoding with SVT-AV1-PSYEX {version}"),
+//!     Err(err) => eprintln!("AV1 encoding is disabled: {err}"),
+//! }
+//! ```
+//!
+//! [`SvtAv1EncoderConfig::try_new`]: crate::SvtAv1EncoderConfig::try_new
+//! [`SvtAv1EncoderConfig::new`]: crate::SvtAv1EncoderConfig::new
+//! [`ConfigError::Init`]: crate::ConfigError::Init
+use crate::{
+    ffi::{BufferHeaderType, ComponentType, ErrorType, SvtAv1EncConfiguration},
+    version::{self, Version, VersionError},
+};
+use libloading::Library;
+use std::{
+    ffi::{c_char, c_void, OsStr},
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+/// Library names tried by [`load_default`], versioned name first
+#[cfg(target_os = "windows")]
+pub const DEFAULT_NAMES: &[&str] = &["SvtAv1Enc.dll"];
+/// Library names tried by [`load_default`], versioned name first
+#[cfg(target_os = "macos")]
+pub const DEFAULT_NAMES: &[&str] = &["libSvtAv1Enc.3.dylib", "libSvtAv1Enc.dylib"];
+/// Library names tried by [`load_default`], versioned name first
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub const DEFAULT_NAMES: &[&str] = &["libSvtAv1Enc.so.3", "libSvtAv1Enc.so"];
+
+/// Error of loading library
+#[derive(Debug)]
+pub enum LoadError {
+    /// Library file can't be opened
+    Open(libloading::Error),
+    /// Library doesn't export function
+    Symbol {
+        name: &'static str,
+        source: libloading::Error,
+    },
+    /// Library isn't the version bindings were written for
+    Version(VersionError),
+    /// Other library is already loaded, it stays loaded until process exits
+    AlreadyLoaded,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open(err) => write!(f, "failed to open SVT-AV1-PSYEX library: {err}"),
+            Self::Symbol { name, source } => {
+                write!(f, "SVT-AV1-PSYEX library has no function {name}: {source}")
+            }
+            Self::Version(err) => err.fmt(f),
+            Self::AlreadyLoaded => f.write_str("SVT-AV1-PSYEX library is already loaded"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Open(err) | Self::Symbol { source: err, .. } => Some(err),
+            Self::Version(err) => Some(err),
+            Self::AlreadyLoaded => None,
+        }
+    }
+}
+
+/// Declares dispatch table with library functions and wrappers with the same signatures
+/// as `extern "C"` declarations. Wrappers return fallback value, when library isn't loaded
+macro_rules! functions {
+    ($(
+        $(#[$attr:meta])*
+        fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)? = $fallback:expr;
+    )*) => {
+        struct Functions {
+            $($name: unsafe extern "C" fn($($ty),*) $(-> $ret)?,)*
+            /// Keeps function pointers valid
+            _library: Library,
+        }
+
+        impl Functions {
+            /// Resolve all functions
+            unsafe fn resolve(library: Library) -> Result<Self, LoadError> {
+                Ok(Self {
+                    $($name: *library
+                        .get(concat!(stringify!($name), "\0").as_bytes())
+                        .map_err(|source| LoadError::Symbol {
+                            name: stringify!($name),
+                            source,
+                        })?,)*
+                    _library: library,
+                })
+            }
+        }
+
+        /// Functions called by safe API, dispatched to loaded library
+        pub(crate) mod dispatch {
+            use super::*;
+
+            $(
+                $(#[$attr])*
+                ///
+                /// # Safety
+                /// Same requirements as for the library function
+                pub unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+                    match functions() {
+                        Some(functions) => (functions.$name)($($arg),*),
+                        None => $fallback,
+                    }
+                }
+            )*
+        }
+    };
+}
+
+functions! {
+    /// Returns a string containing "v$tag-$commit_count-g$hash${dirty:+-dirty}"
+    fn svt_av1_get_version() -> *const c_char = c"".as_ptr();
+    /// Returns a string containing only the SVT-AV1-PSY micro-release letter
+    fn svt_psy_get_version() -> *const c_char = c"".as_ptr();
+    /// Prints the version header and build information to the file
+    /// specified by the SVT_LOG_FILE environment variable or stderr
+    fn svt_av1_print_version() = ();
+    /// STEP 1: Call the library to construct a Component Handle.
+    fn svt_av1_enc_init_handle(
+        p_handle: *mut *mut ComponentType,
+        config_ptr: *mut SvtAv1EncConfiguration,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// STEP 2: Set all configuration parameters.
+    fn svt_av1_enc_set_parameter(
+        svt_enc_component: *mut ComponentType,
+        config_ptr: *mut SvtAv1EncConfiguration,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// OPTIONAL: Set a single configuration parameter.
+    fn svt_av1_enc_parse_parameter(
+        config_ptr: *mut SvtAv1EncConfiguration,
+        name: *const c_char,
+        value: *const c_char,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// STEP 3: Initialize encoder and allocates memory to necessary buffers.
+    fn svt_av1_enc_init(svt_enc_component: *mut ComponentType) -> ErrorType =
+        ErrorType::ErrorInvalidComponent;
+    /// OPTIONAL: Get stream headers at init time.
+    fn svt_av1_enc_stream_header(
+        svt_enc_component: *mut ComponentType,
+        output_stream_ptr: *mut *mut BufferHeaderType,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// OPTIONAL: Release stream headers at init time.
+    fn svt_av1_enc_stream_header_release(stream_header_ptr: *mut BufferHeaderType) -> ErrorType =
+        ErrorType::ErrorInvalidComponent;
+    /// STEP 4: Send the picture.
+    fn svt_av1_enc_send_picture(
+        svt_enc_component: *mut ComponentType,
+        p_buffer: *mut BufferHeaderType,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// Step 5: Receive packet.
+    fn svt_av1_enc_get_packet(
+        svt_enc_component: *mut ComponentType,
+        p_buffer: *mut *mut BufferHeaderType,
+        pic_send_done: u8,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// STEP 5-1: Release output buffer back into the pool.
+    fn svt_av1_enc_release_out_buffer(p_buffer: *mut *mut BufferHeaderType) = ();
+    /// OPTIONAL: Fill buffer with reconstructed picture.
+    fn svt_av1_get_recon(
+        svt_enc_component: *mut ComponentType,
+        p_buffer: *mut BufferHeaderType,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// OPTIONAL: get stream information
+    fn svt_av1_enc_get_stream_info(
+        svt_enc_component: *mut ComponentType,
+        stream_info_id: u32,
+        info: *mut c_void,
+    ) -> ErrorType = ErrorType::ErrorInvalidComponent;
+    /// STEP 6: Deinitialize encoder library.
+    fn svt_av1_enc_deinit(svt_enc_component: *mut ComponentType) -> ErrorType =
+        ErrorType::ErrorInvalidComponent;
+    /// STEP 7: Deconstruct encoder handler.
+    fn svt_av1_enc_deinit_handle(svt_enc_component: *mut ComponentType) -> ErrorType =
+        ErrorType::ErrorInvalidComponent;
+}
+
+static FUNCTIONS: OnceLock<Functions> = OnceLock::new();
+/// Serializes loading, so only one library is opened and checked at a time
+static LOADING: Mutex<()> = Mutex::new(());
+/// Result of [`load_default`] tried by the first call into the library
+static DEFAULT_ATTEMPT: OnceLock<Result<Version, LoadError>> = OnceLock::new();
+
+/// Loaded functions. If no library is loaded yet, [`load_default`] is tried once and its
+/// failure is kept, so later calls return fallback values without opening libraries again
+fn functions() -> Option<&'static Functions> {
+    if let Some(functions) = FUNCTIONS.get() {
+        return Some(functions);
+    }
+    let _ = DEFAULT_ATTEMPT.get_or_init(load_default);
+    FUNCTIONS.get()
+}
+
+/// Open library from path or by name from system search paths, check its version and use
+/// it for all calls. Library stays loaded until process exits
+pub fn load(path: impl AsRef<OsStr>) -> Result<Version, LoadError> {
+    let _guard = LOADING.lock().unwrap_or_else(|err| err.into_inner());
+    if FUNCTIONS.get().is_some() {
+        return Err(LoadError::AlreadyLoaded);
+    }
+    // SAFETY: SVT-AV1 library has no initialization routines with preconditions
+    let library = unsafe { Library::new(path.as_ref()) }.map_err(LoadError::Open)?;
+    // SAFETY: symbols are declared with signatures from SVT-AV1 headers
+    let functions = unsafe { Functions::resolve(library) }?;
+    let found = unsafe {
+        version::from_library(
+            (functions.svt_av1_get_version)(),
+            (functions.svt_psy_get_version)(),
+        )
+    };
+    let version = found.and_then(version::check).map_err(LoadError::Version)?;
+    let _ = FUNCTIONS.set(functions);
+    Ok(version)
+}
+
+/// Load library trying [`DEFAULT_NAMES`] in order. Returns version of already loaded library,
+/// or error of the last name tried
+pub fn load_default() -> Result<Version, LoadError> {
+    if let Some(functions) = FUNCTIONS.get() {
+        let found = unsafe {
+            version::from_library(
+                (functions.svt_av1_get_version)(),
+                (functions.svt_psy_get_version)(),
+            )
+        };
+        return found.map_err(LoadError::Version);
+    }
+    let mut result = Err(LoadError::AlreadyLoaded);
+    for name in DEFAULT_NAMES {
+        result = load(name);
+        match &result {
+            // Other thread loaded library meanwhile
+            Err(LoadError::AlreadyLoaded) => return load_default(),
+            Err(LoadError::Open(_)) => {}
+            _ => break,
+        }
+    }
+    result
+}
+
+/// Whether library is loaded
+pub fn is_loaded() -> bool {
+    FUNCTIONS.get().is_some()
+}
+
+/// Error of loading library from default search paths by the first call into it, which isn't
+/// retried by later calls. `None` when it wasn't tried or succeeded. Library still can be loaded
+/// with [`load`] or [`load_default`]
+pub fn default_load_error() -> Option<&'static LoadError> {
+    DEFAULT_ATTEMPT.get()?.as_ref().err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_library() {
+        let err = load("/nonexistent/libSvtAv1Enc.so").unwrap_err();
+        // Library can be loaded by other tests
+        assert!(
+            matches!(err, LoadError::Open(_) | LoadError::AlreadyLoaded),
+            "{err}"
+        );
+        assert!(err.to_string().starts_with("failed to open") || is_loaded());
+    }
+
+    #[test]
+    fn default_attempt_is_kept() {
+        let loaded = functions().is_some();
+        assert_eq!(loaded, is_loaded());
+        if !loaded {
+            let err = default_load_error().unwrap() as *const LoadError;
+            assert!(functions().is_none());
+            assert_eq!(default_load_error().unwrap() as *const LoadError, err);
+        }
+    }
+}
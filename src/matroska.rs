@@ -0,0 +1,620 @@
+//! Matroska/WebM muxer for encoded packets.
+//!
+//! [`MatroskaWriter`] writes a single AV1 video track. `CodecPrivate` is built from
+//! encoder stream header, colour metadata is taken from encoder configuration and
+//! every key frame is indexed in `Cues`. Packet timestamps are expected to be in
+//! frame rate units, i.e. frame number, as `SvtAv1EncApp` does.
+use crate::{
+    ffi::{ChromaSamplePosition, ColorFormat, ColorRange, SvtAv1EncConfiguration},
+    obu::{av1_codec_config, TemporalUnit, TemporalUnitAssembler},
+    Packet, SvtAv1Encoder,
+};
+use std::io::{self, Seek, SeekFrom, Write};
+
+const EBML: u32 = 0x1A45DFA3;
+const EBML_VERSION: u32 = 0x4286;
+const EBML_READ_VERSION: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const DOC_TYPE: u32 = 0x4282;
+const DOC_TYPE_VERSION: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+const VOID: u32 = 0xEC;
+
+const SEGMENT: u32 = 0x18538067;
+const SEEK_HEAD: u32 = 0x114D9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+
+const INFO: u32 = 0x1549A966;
+const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+const DURATION: u32 = 0x4489;
+const MUXING_APP: u32 = 0x4D80;
+const WRITING_APP: u32 = 0x5741;
+
+const TRACKS: u32 = 0x1654AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_UID: u32 = 0x73C5;
+const TRACK_TYPE: u32 = 0x83;
+const FLAG_LACING: u32 = 0x9C;
+const DEFAULT_DURATION: u32 = 0x23E383;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+
+const COLOUR: u32 = 0x55B0;
+const MATRIX_COEFFICIENTS: u32 = 0x55B1;
+const BITS_PER_CHANNEL: u32 = 0x55B2;
+const CHROMA_SUBSAMPLING_HORZ: u32 = 0x55B3;
+const CHROMA_SUBSAMPLING_VERT: u32 = 0x55B4;
+const CHROMA_SITING_HORZ: u32 = 0x55B7;
+const CHROMA_SITING_VERT: u32 = 0x55B8;
+const RANGE: u32 = 0x55B9;
+const TRANSFER_CHARACTERISTICS: u32 = 0x55BA;
+const PRIMARIES: u32 = 0x55BB;
+const MAX_CLL: u32 = 0x55BC;
+const MAX_FALL: u32 = 0x55BD;
+const MASTERING_METADATA: u32 = 0x55D0;
+const PRIMARY_R_CHROMATICITY_X: u32 = 0x55D1;
+const PRIMARY_R_CHROMATICITY_Y: u32 = 0x55D2;
+const PRIMARY_G_CHROMATICITY_X: u32 = 0x55D3;
+const PRIMARY_G_CHROMATICITY_Y: u32 = 0x55D4;
+const PRIMARY_B_CHROMATICITY_X: u32 = 0x55D5;
+const PRIMARY_B_CHROMATICITY_Y: u32 = 0x55D6;
+const WHITE_POINT_CHROMATICITY_X: u32 = 0x55D7;
+const WHITE_POINT_CHROMATICITY_Y: u32 = 0x55D8;
+const LUMINANCE_MAX: u32 = 0x55D9;
+const LUMINANCE_MIN: u32 = 0x55DA;
+
+const CLUSTER: u32 = 0x1F43B675;
+const TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+
+const CUES: u32 = 0x1C53BB6B;
+const CUE_POINT: u32 = 0xBB;
+const CUE_TIME: u32 = 0xB3;
+const CUE_TRACK_POSITIONS: u32 = 0xB7;
+const CUE_TRACK: u32 = 0xF7;
+const CUE_CLUSTER_POSITION: u32 = 0xF1;
+
+/// Timestamps are written in milliseconds
+const TIMESTAMP_SCALE_NS: u64 = 1_000_000;
+/// Size of `Seek` element with 4 byte ID and 8 byte position
+const SEEK_ENTRY_LEN: u64 = 21;
+
+/// Kind of file written by [`MatroskaWriter`]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum DocType {
+    /// `.mkv` file
+    Matroska,
+    /// `.webm` file
+    WebM,
+}
+
+impl DocType {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Matroska => "matroska",
+            Self::WebM => "webm",
+        }
+    }
+}
+
+fn write_id(out: &mut Vec<u8>, id: u32) {
+    let bytes = id.to_be_bytes();
+    let skip = (id.leading_zeros() / 8) as usize;
+    out.extend_from_slice(&bytes[skip.min(3)..]);
+}
+
+fn write_size(out: &mut Vec<u8>, size: u64) {
+    let mut len = 1;
+    // All ones value is reserved for unknown size
+    while len < 8 && size >= (1 << (7 * len)) - 1 {
+        len += 1;
+    }
+    let value = size | (1 << (7 * len));
+    out.extend_from_slice(&value.to_be_bytes()[8 - len..]);
+}
+
+/// 8 byte size which can be patched later
+fn fixed_size(size: u64) -> [u8; 8] {
+    (size | (1 << 56)).to_be_bytes()
+}
+
+fn element(out: &mut Vec<u8>, id: u32, body: &[u8]) {
+    write_id(out, id);
+    write_size(out, body.len() as u64);
+    out.extend_from_slice(body);
+}
+
+fn uint_element(out: &mut Vec<u8>, id: u32, value: u64) {
+    let bytes = value.to_be_bytes();
+    let skip = ((value.leading_zeros() / 8) as usize).min(7);
+    element(out, id, &bytes[skip..]);
+}
+
+fn float_element(out: &mut Vec<u8>, id: u32, value: f64) {
+    element(out, id, &value.to_be_bytes());
+}
+
+/// Write Matroska or WebM file with single AV1 track.
+///
+/// Call [`write_packet`] for every packet received from encoder and [`finish`] after
+/// the last one, which writes `Cues` and fills segment size and duration.
+///
+/// [`write_packet`]: method@Self::write_packet
+/// [`finish`]: method@Self::finish
+#[derive(Debug)]
+pub struct MatroskaWriter<W: Write + Seek> {
+    writer: W,
+    /// Frame rate numerator and denominator
+    frame_rate: (u64, u64),
+    segment_size_pos: u64,
+    segment_data_pos: u64,
+    cues_seek_pos: u64,
+    duration_pos: u64,
+    /// Position of current cluster size and its timestamp
+    cluster: Option<(u64, u64)>,
+    cues: Vec<(u64, u64)>,
+    last_timestamp: Option<u64>,
+    assembler: TemporalUnitAssembler,
+}
+
+impl<W: Write + Seek> MatroskaWriter<W> {
+    /// Create writer for encoder output and write file header
+    pub fn new(writer: W, encoder: &SvtAv1Encoder, doc_type: DocType) -> io::Result<Self> {
+        let stream_header = encoder.stream_header().map_err(io::Error::other)?;
+        Self::with_stream_header(writer, encoder.config(), &stream_header, doc_type)
+    }
+
+    /// Create writer from encoder configuration and stream header returned by
+    /// [`SvtAv1Encoder::stream_header`], and write file header
+    pub fn with_stream_header(
+        mut writer: W,
+        config: &SvtAv1EncConfiguration,
+        stream_header: &[u8],
+        doc_type: DocType,
+    ) -> io::Result<Self> {
+        if config.frame_rate_numerator == 0 || config.frame_rate_denominator == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame rate must be set in encoder configuration",
+            ));
+        }
+        let frame_rate = (
+            config.frame_rate_numerator as u64,
+            config.frame_rate_denominator as u64,
+        );
+        let codec_private = av1_codec_config(stream_header)?;
+
+        let mut ebml = Vec::new();
+        uint_element(&mut ebml, EBML_VERSION, 1);
+        uint_element(&mut ebml, EBML_READ_VERSION, 1);
+        uint_element(&mut ebml, EBML_MAX_ID_LENGTH, 4);
+        uint_element(&mut ebml, EBML_MAX_SIZE_LENGTH, 8);
+        element(&mut ebml, DOC_TYPE, doc_type.as_str().as_bytes());
+        uint_element(&mut ebml, DOC_TYPE_VERSION, 4);
+        uint_element(&mut ebml, DOC_TYPE_READ_VERSION, 2);
+
+        let mut header = Vec::new();
+        element(&mut header, EBML, &ebml);
+        write_id(&mut header, SEGMENT);
+        let segment_size_pos = writer.stream_position()? + header.len() as u64;
+        // Unknown size until the file is finished
+        header.extend_from_slice(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let segment_data_pos = segment_size_pos + 8;
+
+        let info = Self::info();
+        let tracks = Self::tracks(config, &codec_private, frame_rate);
+        // Seek entries have fixed size, so SeekHead size is known before positions
+        let seek_head_len = 4 + 1 + 3 * SEEK_ENTRY_LEN;
+        let mut seek_entries = Vec::new();
+        Self::seek_entry(&mut seek_entries, INFO, seek_head_len);
+        Self::seek_entry(&mut seek_entries, TRACKS, seek_head_len + info.len() as u64);
+        Self::seek_entry(&mut seek_entries, CUES, 0);
+        let seek_head_start = header.len();
+        element(&mut header, SEEK_HEAD, &seek_entries);
+        debug_assert_eq!((header.len() - seek_head_start) as u64, seek_head_len);
+        let cues_seek_pos = segment_data_pos + seek_head_len - SEEK_ENTRY_LEN;
+        // Duration is the last element of Info
+        let duration_pos = segment_data_pos + seek_head_len + info.len() as u64 - 8;
+        header.extend_from_slice(&info);
+        header.extend_from_slice(&tracks);
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer,
+            frame_rate,
+            segment_size_pos,
+            segment_data_pos,
+            cues_seek_pos,
+            duration_pos,
+            cluster: None,
+            cues: Vec::new(),
+            last_timestamp: None,
+            assembler: TemporalUnitAssembler::new(),
+        })
+    }
+
+    fn seek_entry(out: &mut Vec<u8>, id: u32, position: u64) {
+        let mut seek = Vec::with_capacity(SEEK_ENTRY_LEN as usize);
+        let mut seek_id = Vec::with_capacity(4);
+        write_id(&mut seek_id, id);
+        element(&mut seek, SEEK_ID, &seek_id);
+        write_id(&mut seek, SEEK_POSITION);
+        write_size(&mut seek, 8);
+        seek.extend_from_slice(&position.to_be_bytes());
+        element(out, SEEK, &seek);
+    }
+
+    fn info() -> Vec<u8> {
+        let app = concat!("svt-av1-psyex-enc ", env!("CARGO_PKG_VERSION"));
+        let mut info = Vec::new();
+        uint_element(&mut info, TIMESTAMP_SCALE, TIMESTAMP_SCALE_NS);
+        element(&mut info, MUXING_APP, app.as_bytes());
+        element(&mut info, WRITING_APP, app.as_bytes());
+        float_element(&mut info, DURATION, 0.0);
+        let mut out = Vec::new();
+        element(&mut out, INFO, &info);
+        out
+    }
+
+    fn tracks(
+        config: &SvtAv1EncConfiguration,
+        codec_private: &[u8],
+        frame_rate: (u64, u64),
+    ) -> Vec<u8> {
+        let mut video = Vec::new();
+        uint_element(&mut video, PIXEL_WIDTH, config.source_width as u64);
+        uint_element(&mut video, PIXEL_HEIGHT, config.source_height as u64);
+        element(&mut video, COLOUR, &Self::colour(config));
+
+        let mut entry = Vec::new();
+        uint_element(&mut entry, TRACK_NUMBER, 1);
+        uint_element(&mut entry, TRACK_UID, 1);
+        uint_element(&mut entry, TRACK_TYPE, 1);
+        uint_element(&mut entry, FLAG_LACING, 0);
+        uint_element(
+            &mut entry,
+            DEFAULT_DURATION,
+            1_000_000_000 * frame_rate.1 / frame_rate.0,
+        );
+        element(&mut entry, CODEC_ID, b"V_AV1");
+        element(&mut entry, CODEC_PRIVATE, codec_private);
+        element(&mut entry, VIDEO, &video);
+
+        let mut tracks = Vec::new();
+        element(&mut tracks, TRACK_ENTRY, &entry);
+        let mut out = Vec::new();
+        element(&mut out, TRACKS, &tracks);
+        out
+    }
+
+    fn colour(config: &SvtAv1EncConfiguration) -> Vec<u8> {
+        let mut colour = Vec::new();
+        uint_element(
+            &mut colour,
+            MATRIX_COEFFICIENTS,
+            config.matrix_coefficients as u64,
+        );
+        uint_element(
+            &mut colour,
+            BITS_PER_CHANNEL,
+            config.encoder_bit_depth as u64,
+        );
+        let subsampling = match config.encoder_color_format {
+            ColorFormat::YUV400 => None,
+            ColorFormat::YUV420 => Some((1, 1)),
+            ColorFormat::YUV422 => Some((1, 0)),
+            ColorFormat::YUV444 => Some((0, 0)),
+        };
+        if let Some((horz, vert)) = subsampling {
+            uint_element(&mut colour, CHROMA_SUBSAMPLING_HORZ, horz);
+            uint_element(&mut colour, CHROMA_SUBSAMPLING_VERT, vert);
+        }
+        // 1 is left/top collocated, 2 is half
+        let siting = match config.chroma_sample_position {
+            ChromaSamplePosition::CspVertical => Some((1, 2)),
+            ChromaSamplePosition::CspColocated => Some((1, 1)),
+            ChromaSamplePosition::CspUnknown | ChromaSamplePosition::CspReserved => None,
+        };
+        if let Some((horz, vert)) = siting {
+            uint_element(&mut colour, CHROMA_SITING_HORZ, horz);
+            uint_element(&mut colour, CHROMA_SITING_VERT, vert);
+        }
+        let range = match config.color_range {
+            ColorRange::CrStudioRange => 1,
+            ColorRange::CrFullRange => 2,
+        };
+        uint_element(&mut colour, RANGE, range);
+        uint_element(
+            &mut colour,
+            TRANSFER_CHARACTERISTICS,
+            config.transfer_characteristics as u64,
+        );
+        uint_element(&mut colour, PRIMARIES, config.color_primaries as u64);
+
+        let cll = config.content_light_level;
+        if cll.max_cll != 0 || cll.max_fall != 0 {
+            uint_element(&mut colour, MAX_CLL, cll.max_cll as u64);
+            uint_element(&mut colour, MAX_FALL, cll.max_fall as u64);
+        }
+        let md = config.mastering_display;
+        if md.max_luma != 0 {
+            // Chromaticity is 0.16 and luminance is 24.8 and 18.14 fixed point, as in AV1 metadata
+            let chroma = |value: u16| value as f64 / (1 << 16) as f64;
+            let mut mastering = Vec::new();
+            float_element(&mut mastering, PRIMARY_R_CHROMATICITY_X, chroma(md.r.x));
+            float_element(&mut mastering, PRIMARY_R_CHROMATICITY_Y, chroma(md.r.y));
+            float_element(&mut mastering, PRIMARY_G_CHROMATICITY_X, chroma(md.g.x));
+            float_element(&mut mastering, PRIMARY_G_CHROMATICITY_Y, chroma(md.g.y));
+            float_element(&mut mastering, PRIMARY_B_CHROMATICITY_X, chroma(md.b.x));
+            float_element(&mut mastering, PRIMARY_B_CHROMATICITY_Y, chroma(md.b.y));
+            float_element(
+                &mut mastering,
+                WHITE_POINT_CHROMATICITY_X,
+                chroma(md.white_point.x),
+            );
+            float_element(
+                &mut mastering,
+                WHITE_POINT_CHROMATICITY_Y,
+                chroma(md.white_point.y),
+            );
+            float_element(
+                &mut mastering,
+                LUMINANCE_MAX,
+                md.max_luma as f64 / (1 << 8) as f64,
+            );
+            float_element(
+                &mut mastering,
+                LUMINANCE_MIN,
+                md.min_luma as f64 / (1 << 14) as f64,
+            );
+            element(&mut colour, MASTERING_METADATA, &mastering);
+        }
+        colour
+    }
+
+    /// Convert packet timestamp to milliseconds
+    fn timestamp(&self, pts: i64) -> u64 {
+        let pts = pts.max(0) as u64;
+        (pts * 1000 * self.frame_rate.1 + self.frame_rate.0 / 2) / self.frame_rate.0
+    }
+
+    /// Write packet received from encoder
+    pub fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        match self.assembler.push_packet(packet) {
+            Some(unit) => self.write_temporal_unit(&unit),
+            None => Ok(()),
+        }
+    }
+
+    /// Write complete temporal unit without temporal delimiter
+    pub fn write_temporal_unit(&mut self, unit: &TemporalUnit) -> io::Result<()> {
+        let timestamp = self.timestamp(unit.pts);
+        let needs_cluster = match self.cluster {
+            None => true,
+            Some((_, cluster_timestamp)) => {
+                unit.keyframe
+                    || timestamp < cluster_timestamp
+                    || timestamp - cluster_timestamp > i16::MAX as u64
+            }
+        };
+        if needs_cluster {
+            self.close_cluster()?;
+            let cluster_pos = self.writer.stream_position()?;
+            let mut cluster = Vec::new();
+            write_id(&mut cluster, CLUSTER);
+            cluster.extend_from_slice(&fixed_size(0));
+            uint_element(&mut cluster, TIMESTAMP, timestamp);
+            self.writer.write_all(&cluster)?;
+            self.cluster = Some((cluster_pos + 4, timestamp));
+            if unit.keyframe {
+                self.cues
+                    .push((timestamp, cluster_pos - self.segment_data_pos));
+            }
+        }
+        let cluster_timestamp = self.cluster.map_or(0, |(_, ts)| ts);
+
+        let mut block = Vec::with_capacity(unit.data.len() + 16);
+        write_id(&mut block, SIMPLE_BLOCK);
+        write_size(&mut block, unit.data.len() as u64 + 4);
+        // Track number 1 as EBML varint
+        block.push(0x81);
+        block.extend_from_slice(&((timestamp - cluster_timestamp) as i16).to_be_bytes());
+        block.push(if unit.keyframe { 0x80 } else { 0x00 });
+        self.writer.write_all(&block)?;
+        self.writer.write_all(&unit.data)?;
+        self.last_timestamp = Some(
+            self.last_timestamp
+                .map_or(timestamp, |ts| ts.max(timestamp)),
+        );
+        Ok(())
+    }
+
+    fn close_cluster(&mut self) -> io::Result<()> {
+        if let Some((size_pos, _)) = self.cluster.take() {
+            let end = self.writer.stream_position()?;
+            self.writer.seek(SeekFrom::Start(size_pos))?;
+            self.writer.write_all(&fixed_size(end - size_pos - 8))?;
+            self.writer.seek(SeekFrom::Start(end))?;
+        }
+        Ok(())
+    }
+
+    /// Write remaining data, `Cues`, segment size and duration. Returns inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(unit) = self.assembler.flush() {
+            self.write_temporal_unit(&unit)?;
+        }
+        self.close_cluster()?;
+
+        let cues_pos = self.writer.stream_position()?;
+        let mut seek = Vec::with_capacity(SEEK_ENTRY_LEN as usize);
+        if self.cues.is_empty() {
+            // Cues must not be empty, replace its Seek entry with Void
+            write_id(&mut seek, VOID);
+            write_size(&mut seek, SEEK_ENTRY_LEN - 2);
+            seek.resize(SEEK_ENTRY_LEN as usize, 0);
+        } else {
+            let mut cue_points = Vec::new();
+            for &(time, cluster_pos) in &self.cues {
+                let mut positions = Vec::new();
+                uint_element(&mut positions, CUE_TRACK, 1);
+                uint_element(&mut positions, CUE_CLUSTER_POSITION, cluster_pos);
+                let mut point = Vec::new();
+                uint_element(&mut point, CUE_TIME, time);
+                element(&mut point, CUE_TRACK_POSITIONS, &positions);
+                element(&mut cue_points, CUE_POINT, &point);
+            }
+            let mut cues = Vec::new();
+            element(&mut cues, CUES, &cue_points);
+            self.writer.write_all(&cues)?;
+            Self::seek_entry(&mut seek, CUES, cues_pos - self.segment_data_pos);
+        }
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.cues_seek_pos))?;
+        self.writer.write_all(&seek)?;
+
+        let frame_duration = 1000.0 * self.frame_rate.1 as f64 / self.frame_rate.0 as f64;
+        let duration = self
+            .last_timestamp
+            .map_or(0.0, |ts| ts as f64 + frame_duration);
+        self.writer.seek(SeekFrom::Start(self.duration_pos))?;
+        self.writer.write_all(&duration.to_be_bytes())?;
+        self.writer.seek(SeekFrom::Start(self.segment_size_pos))?;
+        self.writer
+            .write_all(&fixed_size(end - self.segment_data_pos))?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{obu::tests::stream_header, tests::test_config};
+    use std::io::Cursor;
+
+    /// Read EBML element at `pos`, returns ID, body range
+    fn read_element(data: &[u8], pos: usize) -> (u32, std::ops::Range<usize>) {
+        let id_len = data[pos].leading_zeros() as usize + 1;
+        let id = data[pos..pos + id_len]
+            .iter()
+            .fold(0u32, |id, b| (id << 8) | *b as u32);
+        let pos = pos + id_len;
+        let size_len = data[pos].leading_zeros() as usize + 1;
+        let mut size = (data[pos] as u64) & (0xFF >> size_len);
+        for b in &data[pos + 1..pos + size_len] {
+            size = (size << 8) | *b as u64;
+        }
+        let start = pos + size_len;
+        (id, start..start + size as usize)
+    }
+
+    fn children(data: &[u8], range: std::ops::Range<usize>) -> Vec<(u32, std::ops::Range<usize>)> {
+        let mut pos = range.start;
+        let mut out = Vec::new();
+        while pos < range.end {
+            let (id, body) = read_element(data, pos);
+            pos = body.end;
+            out.push((id, body));
+        }
+        out
+    }
+
+    fn write_test_file(keyframes: &[bool]) -> Vec<u8> {
+        let mut config = test_config(640, 360);
+        config.color_range = ColorRange::CrFullRange;
+        config.content_light_level.max_cll = 1000;
+        let mut writer = MatroskaWriter::with_stream_header(
+            Cursor::new(Vec::new()),
+            &config,
+            &stream_header(),
+            DocType::WebM,
+        )
+        .unwrap();
+        for (pts, &keyframe) in keyframes.iter().enumerate() {
+            writer
+                .write_temporal_unit(&TemporalUnit {
+                    data: vec![0x32, 0x01, pts as u8],
+                    pts: pts as i64,
+                    keyframe,
+                })
+                .unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn vint() {
+        let mut out = Vec::new();
+        write_size(&mut out, 0);
+        write_size(&mut out, 126);
+        write_size(&mut out, 127);
+        write_size(&mut out, 300);
+        assert_eq!(out, [0x80, 0xFE, 0x40, 0x7F, 0x41, 0x2C]);
+        out.clear();
+        write_id(&mut out, CLUSTER);
+        write_id(&mut out, SIMPLE_BLOCK);
+        assert_eq!(out, [0x1F, 0x43, 0xB6, 0x75, 0xA3]);
+    }
+
+    #[test]
+    fn file_structure() {
+        let data = write_test_file(&[true, false, false, true, false]);
+        let top = children(&data, 0..data.len());
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, EBML);
+        assert_eq!(top[1].0, SEGMENT);
+        assert_eq!(top[1].1.end, data.len());
+        let segment_start = top[1].1.start;
+
+        let segment = children(&data, top[1].1.clone());
+        let ids = segment.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids, [SEEK_HEAD, INFO, TRACKS, CLUSTER, CLUSTER, CUES]);
+
+        // Every SeekHead entry points to the element it names
+        for (_, seek) in children(&data, segment[0].1.clone()) {
+            let fields = children(&data, seek);
+            let id = data[fields[0].1.clone()]
+                .iter()
+                .fold(0u32, |id, b| (id << 8) | *b as u32);
+            let pos = data[fields[1].1.clone()]
+                .iter()
+                .fold(0usize, |pos, b| (pos << 8) | *b as usize);
+            assert_eq!(read_element(&data, segment_start + pos).0, id);
+        }
+
+        let blocks = children(&data, segment[3].1.clone());
+        assert_eq!(blocks[0].0, TIMESTAMP);
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(data[blocks[1].1.start + 3], 0x80);
+        assert_eq!(data[blocks[2].1.start + 3], 0x00);
+        // Second block is 40 ms after cluster start
+        assert_eq!(data[blocks[2].1.start + 1..][..2], [0, 40]);
+
+        let cue_points = children(&data, segment[5].1.clone());
+        assert_eq!(cue_points.len(), 2);
+
+        let info = children(&data, segment[1].1.clone());
+        let duration = &data[info.last().unwrap().1.clone()];
+        assert_eq!(f64::from_be_bytes(duration.try_into().unwrap()), 200.0);
+    }
+
+    #[test]
+    fn no_keyframes() {
+        let data = write_test_file(&[false, false]);
+        let top = children(&data, 0..data.len());
+        let segment = children(&data, top[1].1.clone());
+        let ids = segment.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids, [SEEK_HEAD, INFO, TRACKS, CLUSTER]);
+        let seek_head = children(&data, segment[0].1.clone());
+        assert_eq!(seek_head.last().unwrap().0, VOID);
+    }
+}
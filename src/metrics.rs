@@ -0,0 +1,576 @@
+//! Objective quality metrics computed in Rust, independent of `stat_report`.
+//!
+//! [`Comparator`] keeps copies of source frames and compares them with reconstructed or decoded
+//! frames of the same pts, which come later and in output order. Each pair gives PSNR, SSIM,
+//! MS-SSIM and XPSNR per plane, for 8-bit and high bit depth pictures. Reconstructed pictures
+//! are available with [`SvtAv1Encoder::get_recon`] when `recon_enabled` is set.
+//!
+//! SSIM uses 8x8 windows with step of 4 samples. MS-SSIM uses 5 scales with weights of Wang et al.,
+//! fewer when picture is too small to downscale. XPSNR follows Helmrich et al.: squared error
+//! of each block is weighted by inverse square root of source activity (high-pass filtered
+//! source plus difference to previous source frame), weights are normalized to mean of 1,
+//! so evenly spread error gives PSNR. It isn't bit-exact with other XPSNR implementations.
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::{metrics::Comparator, pool::FramePool, SvtAv1EncoderConfig};
+//! let mut cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
+//! cfg.config.recon_enabled = true;
+//! let pool = FramePool::from_config(&cfg.config);
+//! let mut comparator = Comparator::from_config(&cfg.config);
+//! let encoder = cfg.into_encoder().unwrap();
+//! let mut recon = vec![0; pool.layout().frame_size()];
+//! for pts in 0..100 {
+//!     let frame = pool.get();
+//!     comparator.add_source(pts, frame.as_frame());
+//!     encoder.send_picture(frame.as_frame(), Some(pts), false).unwrap();
+//!     while let Ok(pts) = encoder.get_recon(&mut recon) {
+//!         let metrics = comparator.add_distorted(pts, pool.layout().frame(&recon)).unwrap();
+//!         println!("{pts}: XPSNR-Y {:.2} dB", metrics.xpsnr[0]);
+//!     }
+//! }
+//! ```
+//!
+//! [`SvtAv1Encoder::get_recon`]: crate::SvtAv1Encoder::get_recon
+use crate::{ffi::SvtAv1EncConfiguration, stats, yuv::PlanarLayout, Frame};
+use std::collections::BTreeMap;
+
+/// Size of SSIM window
+const WINDOW: usize = 8;
+/// Distance between SSIM windows
+const WINDOW_STEP: usize = 4;
+/// Weights of MS-SSIM scales, from full resolution
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+/// Weight of temporal activity in XPSNR
+const TEMPORAL_WEIGHT: f64 = 2.0;
+
+/// Metrics of one frame, planes are in Y, U, V order. Absent (monochrome chroma) planes are zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameMetrics {
+    pub pts: i64,
+    /// Sum of squared errors
+    pub sse: [u64; 3],
+    /// PSNR in dB
+    pub psnr: [f64; 3],
+    pub ssim: [f64; 3],
+    pub ms_ssim: [f64; 3],
+    /// XPSNR in dB
+    pub xpsnr: [f64; 3],
+}
+
+/// Averages over all compared frames, planes are in Y, U, V order
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub frames: usize,
+    /// Arithmetic mean of per-frame PSNR
+    pub average_psnr: [f64; 3],
+    /// PSNR of mean per-frame MSE
+    pub overall_psnr: [f64; 3],
+    pub average_ssim: [f64; 3],
+    pub average_ms_ssim: [f64; 3],
+    pub average_xpsnr: [f64; 3],
+}
+
+/// Plane with samples widened to 16 bits
+#[derive(Debug, Clone)]
+struct Plane {
+    samples: Vec<u16>,
+    width: usize,
+    height: usize,
+}
+
+impl Plane {
+    /// Copy plane with `stride` in samples
+    fn new(data: &[u8], stride: usize, width: usize, height: usize, high_bit_depth: bool) -> Self {
+        let mut samples = Vec::with_capacity(width * height);
+        for y in 0..height {
+            if high_bit_depth {
+                let row = &data[y * stride * 2..][..width * 2];
+                samples.extend(
+                    row.chunks_exact(2)
+                        .map(|sample| u16::from_le_bytes([sample[0], sample[1]])),
+                );
+            } else {
+                let row = &data[y * stride..][..width];
+                samples.extend(row.iter().map(|&sample| sample as u16));
+            }
+        }
+        Self {
+            samples,
+            width,
+            height,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    fn get(&self, x: usize, y: usize) -> i32 {
+        self.samples[y * self.width + x] as i32
+    }
+
+    /// Sample with coordinates clamped to plane
+    fn get_clamped(&self, x: isize, y: isize) -> i32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.get(x, y)
+    }
+
+    /// Half size plane, each sample is rounded average of 2x2 block
+    fn downscale(&self) -> Self {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let mut samples = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let sum = self.get(2 * x, 2 * y)
+                    + self.get(2 * x + 1, 2 * y)
+                    + self.get(2 * x, 2 * y + 1)
+                    + self.get(2 * x + 1, 2 * y + 1);
+                samples.push(((sum + 2) / 4) as u16);
+            }
+        }
+        Self {
+            samples,
+            width,
+            height,
+        }
+    }
+}
+
+fn planes(layout: &PlanarLayout, frame: &Frame<'_>) -> [Plane; 3] {
+    let high_bit_depth = layout.bit_depth > 8;
+    let (width, height) = (layout.width as usize, layout.height as usize);
+    let (chroma_width, chroma_height) = layout.chroma_size();
+    let (chroma_width, chroma_height) = (chroma_width as usize, chroma_height as usize);
+    [
+        Plane::new(
+            frame.luma,
+            frame.y_stride as usize,
+            width,
+            height,
+            high_bit_depth,
+        ),
+        Plane::new(
+            frame.cb,
+            frame.cb_stride as usize,
+            chroma_width,
+            chroma_height,
+            high_bit_depth,
+        ),
+        Plane::new(
+            frame.cr,
+            frame.cr_stride as usize,
+            chroma_width,
+            chroma_height,
+            high_bit_depth,
+        ),
+    ]
+}
+
+fn sse(source: &Plane, distorted: &Plane) -> u64 {
+    source
+        .samples
+        .iter()
+        .zip(&distorted.samples)
+        .map(|(&a, &b)| {
+            let diff = a as i64 - b as i64;
+            (diff * diff) as u64
+        })
+        .sum()
+}
+
+/// Mean SSIM and mean contrast-structure term over windows.
+/// Plane smaller than window is one window
+fn ssim_terms(source: &Plane, distorted: &Plane, max_value: f64) -> (f64, f64) {
+    let c1 = (0.01 * max_value).powi(2);
+    let c2 = (0.03 * max_value).powi(2);
+    let (window_width, window_height) = (WINDOW.min(source.width), WINDOW.min(source.height));
+    let n = (window_width * window_height) as f64;
+    let (mut ssim, mut cs, mut count) = (0.0, 0.0, 0);
+    for y0 in (0..=source.height - window_height).step_by(WINDOW_STEP) {
+        for x0 in (0..=source.width - window_width).step_by(WINDOW_STEP) {
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0u64, 0u64, 0u64, 0u64, 0u64);
+            for y in y0..y0 + window_height {
+                for x in x0..x0 + window_width {
+                    let a = source.get(x, y) as u64;
+                    let b = distorted.get(x, y) as u64;
+                    sum_a += a;
+                    sum_b += b;
+                    sum_aa += a * a;
+                    sum_bb += b * b;
+                    sum_ab += a * b;
+                }
+            }
+            let mean_a = sum_a as f64 / n;
+            let mean_b = sum_b as f64 / n;
+            let var_a = sum_aa as f64 / n - mean_a * mean_a;
+            let var_b = sum_bb as f64 / n - mean_b * mean_b;
+            let covariance = sum_ab as f64 / n - mean_a * mean_b;
+            let luminance = (2.0 * mean_a * mean_b + c1) / (mean_a * mean_a + mean_b * mean_b + c1);
+            let contrast_structure = (2.0 * covariance + c2) / (var_a + var_b + c2);
+            ssim += luminance * contrast_structure;
+            cs += contrast_structure;
+            count += 1;
+        }
+    }
+    (ssim / count as f64, cs / count as f64)
+}
+
+fn ms_ssim(source: &Plane, distorted: &Plane, max_value: f64) -> f64 {
+    let mut contrast_structure = Vec::new();
+    let mut scaled: Option<(Plane, Plane)> = None;
+    let ssim = loop {
+        let (a, b) = scaled.as_ref().map_or((source, distorted), |(a, b)| (a, b));
+        let (ssim, cs) = ssim_terms(a, b, max_value);
+        let last = contrast_structure.len() + 1 == MS_SSIM_WEIGHTS.len();
+        if last || a.width / 2 < WINDOW || a.height / 2 < WINDOW {
+            break ssim;
+        }
+        contrast_structure.push(cs);
+        let next = (a.downscale(), b.downscale());
+        scaled = Some(next);
+    };
+    let weights = &MS_SSIM_WEIGHTS[..contrast_structure.len() + 1];
+    let total = weights.iter().sum::<f64>();
+    contrast_structure
+        .iter()
+        .chain([&ssim])
+        .zip(weights)
+        .map(|(value, weight)| value.max(0.0).powf(weight / total))
+        .product()
+}
+
+/// XPSNR weights of luma blocks
+#[derive(Debug, Clone)]
+struct BlockWeights {
+    size: usize,
+    columns: usize,
+    weights: Vec<f64>,
+}
+
+impl BlockWeights {
+    /// Block size is 128 for 3840x2160 and scales with square root of picture area
+    fn new(source: &Plane, previous: Option<&Plane>, bit_depth: u32) -> Self {
+        let (width, height) = (source.width, source.height);
+        let ratio = (width * height) as f64 / (3840.0 * 2160.0);
+        let size = ((32.0 * ratio.sqrt()).round() as usize * 4).max(4);
+        let columns = width.div_ceil(size);
+        let rows = height.div_ceil(size);
+        let min_activity = (1u32 << (bit_depth - 6)) as f64;
+        let previous = previous.filter(|previous| previous.samples.len() == source.samples.len());
+
+        let mut weights = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x0, y0) = (column * size, row * size);
+                let (x1, y1) = ((x0 + size).min(width), (y0 + size).min(height));
+                let (mut spatial, mut temporal) = (0u64, 0u64);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let (xi, yi) = (x as isize, y as isize);
+                        let at = |dx, dy| source.get_clamped(xi + dx, yi + dy);
+                        let high_pass = 12 * at(0, 0)
+                            - 2 * (at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1))
+                            - (at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1));
+                        spatial += high_pass.unsigned_abs() as u64;
+                        if let Some(previous) = previous {
+                            temporal +=
+                                (source.get(x, y) - previous.get(x, y)).unsigned_abs() as u64;
+                        }
+                    }
+                }
+                let samples = ((x1 - x0) * (y1 - y0)) as f64;
+                let activity = (spatial as f64 + TEMPORAL_WEIGHT * temporal as f64) / samples;
+                weights.push(1.0 / activity.max(min_activity).sqrt());
+            }
+        }
+        let mean = weights.iter().sum::<f64>() / weights.len().max(1) as f64;
+        for weight in &mut weights {
+            *weight /= mean;
+        }
+        Self {
+            size,
+            columns,
+            weights,
+        }
+    }
+
+    /// Weighted SSE of plane subsampled by `shift` relative to luma
+    fn weighted_sse(&self, source: &Plane, distorted: &Plane, shift: (u32, u32)) -> f64 {
+        let mut sum = 0.0;
+        for y in 0..source.height {
+            let row = (y << shift.1) / self.size * self.columns;
+            for x in 0..source.width {
+                let weight = self.weights[row + (x << shift.0) / self.size];
+                let diff = (source.get(x, y) - distorted.get(x, y)) as f64;
+                sum += weight * diff * diff;
+            }
+        }
+        sum
+    }
+}
+
+/// Source frame waiting for its distorted counterpart
+#[derive(Debug)]
+struct Source {
+    planes: [Plane; 3],
+    weights: BlockWeights,
+}
+
+/// Pairs source and distorted frames by pts and computes their metrics, see [module](self) documentation
+#[derive(Debug)]
+pub struct Comparator {
+    layout: PlanarLayout,
+    pending: BTreeMap<i64, Source>,
+    /// Luma of the last source, for temporal activity
+    previous: Option<Plane>,
+    frames: Vec<FrameMetrics>,
+}
+
+impl Comparator {
+    /// Compare frames of given layout
+    pub fn new(layout: PlanarLayout) -> Self {
+        Self {
+            layout,
+            pending: BTreeMap::new(),
+            previous: None,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Compare frames of source size, bit depth and color format of configuration
+    pub fn from_config(config: &SvtAv1EncConfiguration) -> Self {
+        Self::new(PlanarLayout {
+            width: config.source_width,
+            height: config.source_height,
+            color_format: config.encoder_color_format,
+            bit_depth: config.encoder_bit_depth,
+        })
+    }
+
+    /// Copy source frame. Sources must be added in presentation order, previous one is used
+    /// for temporal activity of XPSNR
+    pub fn add_source(&mut self, pts: i64, frame: Frame<'_>) {
+        let planes = planes(&self.layout, &frame);
+        let weights = BlockWeights::new(&planes[0], self.previous.as_ref(), self.layout.bit_depth);
+        self.previous = Some(planes[0].clone());
+        let _ = self.pending.insert(pts, Source { planes, weights });
+    }
+
+    /// Compare distorted frame with source of the same pts and release the source.
+    /// Returns `None`, if there is no such source
+    pub fn add_distorted(&mut self, pts: i64, frame: Frame<'_>) -> Option<&FrameMetrics> {
+        let source = self.pending.remove(&pts)?;
+        let distorted = planes(&self.layout, &frame);
+        let max_value = ((1u32 << self.layout.bit_depth) - 1) as f64;
+        let shifts = [(0, 0), self.chroma_shift(), self.chroma_shift()];
+
+        let mut metrics = FrameMetrics {
+            pts,
+            sse: [0; 3],
+            psnr: [0.0; 3],
+            ssim: [0.0; 3],
+            ms_ssim: [0.0; 3],
+            xpsnr: [0.0; 3],
+        };
+        for (i, (source_plane, distorted_plane)) in source.planes.iter().zip(&distorted).enumerate()
+        {
+            if source_plane.is_empty() {
+                continue;
+            }
+            let samples = source_plane.samples.len() as u64;
+            metrics.sse[i] = sse(source_plane, distorted_plane);
+            metrics.psnr[i] = stats::psnr(metrics.sse[i] as f64, samples, max_value);
+            metrics.ssim[i] = ssim_terms(source_plane, distorted_plane, max_value).0;
+            metrics.ms_ssim[i] = ms_ssim(source_plane, distorted_plane, max_value);
+            let weighted = source
+                .weights
+                .weighted_sse(source_plane, distorted_plane, shifts[i]);
+            metrics.xpsnr[i] = stats::psnr(weighted, samples, max_value);
+        }
+        self.frames.push(metrics);
+        self.frames.last()
+    }
+
+    /// Number of sources without distorted frame yet
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Metrics of compared frames, in order of distorted frames
+    pub fn frames(&self) -> &[FrameMetrics] {
+        &self.frames
+    }
+
+    /// Averages over compared frames, all zero without frames
+    pub fn summary(&self) -> Summary {
+        let count = self.frames.len();
+        if count == 0 {
+            return Summary::default();
+        }
+        let n = count as f64;
+        let mean =
+            |value: &dyn Fn(&FrameMetrics) -> f64| self.frames.iter().map(value).sum::<f64>() / n;
+        let max_value = ((1u32 << self.layout.bit_depth) - 1) as f64;
+        let (chroma_width, chroma_height) = self.layout.chroma_size();
+        let chroma = chroma_width as u64 * chroma_height as u64;
+        let samples = [
+            self.layout.width as u64 * self.layout.height as u64,
+            chroma,
+            chroma,
+        ];
+        Summary {
+            frames: count,
+            average_psnr: std::array::from_fn(|i| mean(&|frame| frame.psnr[i])),
+            overall_psnr: std::array::from_fn(|i| {
+                stats::psnr(mean(&|frame| frame.sse[i] as f64), samples[i], max_value)
+            }),
+            average_ssim: std::array::from_fn(|i| mean(&|frame| frame.ssim[i])),
+            average_ms_ssim: std::array::from_fn(|i| mean(&|frame| frame.ms_ssim[i])),
+            average_xpsnr: std::array::from_fn(|i| mean(&|frame| frame.xpsnr[i])),
+        }
+    }
+
+    /// Horizontal and vertical chroma subsampling as shifts
+    fn chroma_shift(&self) -> (u32, u32) {
+        let (chroma_width, chroma_height) = self.layout.chroma_size();
+        let shift = |luma: u32, chroma: u32| (chroma < luma) as u32;
+        (
+            shift(self.layout.width, chroma_width),
+            shift(self.layout.height, chroma_height),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::ColorFormat;
+
+    fn layout(bit_depth: u32) -> PlanarLayout {
+        PlanarLayout {
+            width: 64,
+            height: 64,
+            color_format: ColorFormat::YUV420,
+            bit_depth,
+        }
+    }
+
+    /// Gradient picture with noise-like texture, `offset` is added to every sample
+    fn picture(layout: &PlanarLayout, seed: u32, offset: i32) -> Vec<u8> {
+        let max = (1i32 << layout.bit_depth) - 1;
+        let samples = layout.frame_size() / layout.sample_size();
+        let mut buffer = Vec::with_capacity(layout.frame_size());
+        for i in 0..samples as u32 {
+            let noise = (i.wrapping_mul(2654435761).wrapping_add(seed) >> 24) as i32 % 32;
+            let value = ((i % 64) as i32 * (max + 1) / 128 + noise + offset).clamp(0, max);
+            if layout.bit_depth > 8 {
+                buffer.extend((value as u16).to_le_bytes());
+            } else {
+                buffer.push(value as u8);
+            }
+        }
+        buffer
+    }
+
+    #[test]
+    fn identical_and_offset() {
+        for bit_depth in [8, 10] {
+            let layout = layout(bit_depth);
+            let source = picture(&layout, 0, 0);
+            let mut comparator = Comparator::new(layout);
+
+            comparator.add_source(0, layout.frame(&source));
+            let same = *comparator.add_distorted(0, layout.frame(&source)).unwrap();
+            assert_eq!(same.sse, [0; 3]);
+            assert_eq!(same.psnr, same.xpsnr);
+            for plane in 0..3 {
+                assert!((same.ssim[plane] - 1.0).abs() < 1e-9);
+                assert!((same.ms_ssim[plane] - 1.0).abs() < 1e-9);
+            }
+
+            // Error of 1 in every sample, MSE is 1 everywhere, so XPSNR equals PSNR
+            comparator.add_source(1, layout.frame(&source));
+            let offset = picture(&layout, 0, 1);
+            let shifted = *comparator.add_distorted(1, layout.frame(&offset)).unwrap();
+            let max_value = ((1u32 << bit_depth) - 1) as f64;
+            let expected = 20.0 * max_value.log10();
+            for plane in 0..3 {
+                assert!((shifted.psnr[plane] - expected).abs() < 1e-9);
+                assert!((shifted.xpsnr[plane] - expected).abs() < 1e-9);
+                assert!(shifted.ssim[plane] > 0.99 && shifted.ssim[plane] < 1.0);
+                assert!(shifted.ms_ssim[plane] > 0.99 && shifted.ms_ssim[plane] < 1.0);
+            }
+            assert_eq!(comparator.summary().frames, 2);
+        }
+    }
+
+    #[test]
+    fn pairs_by_pts() {
+        let layout = layout(8);
+        let mut comparator = Comparator::new(layout);
+        let sources = (0..3)
+            .map(|seed| picture(&layout, seed, 0))
+            .collect::<Vec<_>>();
+        for (pts, source) in sources.iter().enumerate() {
+            comparator.add_source(pts as i64, layout.frame(source));
+        }
+        // Distorted frames come in coding order
+        for pts in [0, 2, 1] {
+            let metrics = comparator
+                .add_distorted(pts, layout.frame(&sources[pts as usize]))
+                .unwrap();
+            assert_eq!(metrics.pts, pts);
+            assert_eq!(metrics.sse, [0; 3]);
+        }
+        assert!(comparator
+            .add_distorted(1, layout.frame(&sources[1]))
+            .is_none());
+        assert_eq!(comparator.pending(), 0);
+        let order = comparator
+            .frames()
+            .iter()
+            .map(|frame| frame.pts)
+            .collect::<Vec<_>>();
+        assert_eq!(order, [0, 2, 1]);
+    }
+
+    #[test]
+    fn block_noise_weighs_less() {
+        // Same error in flat and in textured area, XPSNR is higher when it's in texture
+        let layout = PlanarLayout {
+            width: 64,
+            height: 64,
+            color_format: ColorFormat::YUV400,
+            bit_depth: 8,
+        };
+        let mut source = vec![128u8; 64 * 64];
+        for y in 0..64 {
+            for x in 32..64 {
+                source[y * 64 + x] = if (x + y) % 2 == 0 { 40 } else { 220 };
+            }
+        }
+        let distort = |x0: usize| {
+            let mut distorted = source.clone();
+            for y in 0..64 {
+                for x in x0..x0 + 32 {
+                    distorted[y * 64 + x] = distorted[y * 64 + x].wrapping_add(4);
+                }
+            }
+            distorted
+        };
+        let mut comparator = Comparator::new(layout);
+        comparator.add_source(0, layout.frame(&source));
+        let flat = *comparator
+            .add_distorted(0, layout.frame(&distort(0)))
+            .unwrap();
+        comparator.add_source(1, layout.frame(&source));
+        let textured = *comparator
+            .add_distorted(1, layout.frame(&distort(32)))
+            .unwrap();
+        assert_eq!(flat.psnr, textured.psnr);
+        assert!(textured.xpsnr[0] > flat.xpsnr[0] + 3.0);
+        assert_eq!(flat.psnr[1], 0.0);
+    }
+}
@@ -0,0 +1,645 @@
+//! ISO-BMFF/MP4 muxer for encoded packets.
+//!
+//! Two layouts are supported:
+//! * [`Mp4Writer`] writes progressive MP4, with all samples in a single `mdat` followed by `moov`
+//! * [`FragmentedMp4Writer`] writes fragmented MP4 (CMAF compatible), with initialization
+//!   segment followed by `moof`/`mdat` pairs, each fragment starting with a key frame
+//!
+//! Sample entry is `av01` with `av1C` built from the sequence header, `colr` (`nclx`) from
+//! configuration color fields, and `mdcv`/`clli` when mastering display and content light
+//! level are set. Timescale is frame rate numerator and packet timestamps are expected to be
+//! in frame rate units, i.e. frame number, as `SvtAv1EncApp` does.
+use crate::{
+    bmff::{av1c_box, colr_box, hdr_boxes, write_box, write_full_box},
+    ffi::SvtAv1EncConfiguration,
+    obu::{av1_codec_config, TemporalUnit, TemporalUnitAssembler},
+    Packet, SvtAv1Encoder,
+};
+use std::io::{self, Seek, SeekFrom, Write};
+
+const TRACK_ID: u32 = 1;
+/// `sample_depends_on` = 2, sample doesn't depend on others
+const SYNC_SAMPLE_FLAGS: u32 = 0x0200_0000;
+/// `sample_depends_on` = 1 and `sample_is_non_sync_sample`
+const NON_SYNC_SAMPLE_FLAGS: u32 = 0x0101_0000;
+
+#[derive(Debug, Copy, Clone)]
+struct Sample {
+    size: u32,
+    pts: i64,
+    sync: bool,
+}
+
+/// Track description shared by both layouts
+#[derive(Debug)]
+struct TrackInfo {
+    width: u32,
+    height: u32,
+    /// Timescale, frame rate numerator
+    timescale: u32,
+    /// Duration of a frame in timescale units, frame rate denominator
+    frame_duration: u32,
+    sample_entry: Vec<u8>,
+}
+
+impl TrackInfo {
+    fn new(config: &SvtAv1EncConfiguration, stream_header: &[u8]) -> io::Result<Self> {
+        if config.frame_rate_numerator == 0 || config.frame_rate_denominator == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame rate must be set in encoder configuration",
+            ));
+        }
+        let av1c = av1_codec_config(stream_header)?;
+        let mut sample_entry = Vec::new();
+        write_box(&mut sample_entry, b"av01", |out| {
+            // reserved and data_reference_index
+            out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+            // pre_defined and reserved
+            out.extend_from_slice(&[0; 16]);
+            out.extend_from_slice(&(config.source_width as u16).to_be_bytes());
+            out.extend_from_slice(&(config.source_height as u16).to_be_bytes());
+            // horizontal and vertical resolution, 72 dpi
+            out.extend_from_slice(&0x0048_0000u32.to_be_bytes());
+            out.extend_from_slice(&0x0048_0000u32.to_be_bytes());
+            out.extend_from_slice(&[0; 4]);
+            // frame_count
+            out.extend_from_slice(&1u16.to_be_bytes());
+            let mut compressor_name = [0u8; 32];
+            let name = b"AOM Coding";
+            compressor_name[0] = name.len() as u8;
+            compressor_name[1..=name.len()].copy_from_slice(name);
+            out.extend_from_slice(&compressor_name);
+            // depth and pre_defined
+            out.extend_from_slice(&0x0018u16.to_be_bytes());
+            out.extend_from_slice(&(-1i16).to_be_bytes());
+            av1c_box(out, &av1c);
+            colr_box(out, config);
+            hdr_boxes(out, config);
+        });
+
+        Ok(Self {
+            width: config.source_width,
+            height: config.source_height,
+            timescale: config.frame_rate_numerator,
+            frame_duration: config.frame_rate_denominator,
+            sample_entry,
+        })
+    }
+
+    /// Decode time of sample in timescale units
+    fn decode_time(&self, pts: i64) -> u64 {
+        pts.max(0) as u64 * self.frame_duration as u64
+    }
+
+    /// Durations of samples, based on timestamp of the next sample
+    fn durations(&self, samples: &[Sample], next_pts: Option<i64>) -> Vec<u32> {
+        samples
+            .iter()
+            .enumerate()
+            .map(
+                |(i, sample)| match samples.get(i + 1).map(|next| next.pts).or(next_pts) {
+                    Some(next) if next > sample.pts => {
+                        (self.decode_time(next) - self.decode_time(sample.pts)) as u32
+                    }
+                    _ => self.frame_duration,
+                },
+            )
+            .collect()
+    }
+
+    fn ftyp(out: &mut Vec<u8>, brands: &[&[u8; 4]]) {
+        write_box(out, b"ftyp", |out| {
+            out.extend_from_slice(brands[0]);
+            out.extend_from_slice(&0u32.to_be_bytes());
+            for brand in brands {
+                out.extend_from_slice(*brand);
+            }
+        });
+    }
+
+    /// `moov` box. Sample tables are empty for fragmented layout
+    fn moov(
+        &self,
+        out: &mut Vec<u8>,
+        duration: u64,
+        stbl: impl FnOnce(&mut Vec<u8>),
+        fragmented: bool,
+    ) {
+        let matrix = [0x0001_0000u32, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+        write_box(out, b"moov", |out| {
+            write_full_box(out, b"mvhd", 1, 0, |out| {
+                // creation and modification time
+                out.extend_from_slice(&[0; 16]);
+                out.extend_from_slice(&self.timescale.to_be_bytes());
+                out.extend_from_slice(&duration.to_be_bytes());
+                // rate, volume and reserved
+                out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+                out.extend_from_slice(&0x0100u16.to_be_bytes());
+                out.extend_from_slice(&[0; 10]);
+                matrix
+                    .iter()
+                    .for_each(|v| out.extend_from_slice(&v.to_be_bytes()));
+                // pre_defined
+                out.extend_from_slice(&[0; 24]);
+                out.extend_from_slice(&(TRACK_ID + 1).to_be_bytes());
+            });
+            write_box(out, b"trak", |out| {
+                // track enabled and in movie
+                write_full_box(out, b"tkhd", 1, 0x3, |out| {
+                    out.extend_from_slice(&[0; 16]);
+                    out.extend_from_slice(&TRACK_ID.to_be_bytes());
+                    out.extend_from_slice(&[0; 4]);
+                    out.extend_from_slice(&duration.to_be_bytes());
+                    // reserved, layer, alternate_group, volume and reserved
+                    out.extend_from_slice(&[0; 16]);
+                    matrix
+                        .iter()
+                        .for_each(|v| out.extend_from_slice(&v.to_be_bytes()));
+                    out.extend_from_slice(&(self.width << 16).to_be_bytes());
+                    out.extend_from_slice(&(self.height << 16).to_be_bytes());
+                });
+                write_box(out, b"mdia", |out| {
+                    write_full_box(out, b"mdhd", 1, 0, |out| {
+                        out.extend_from_slice(&[0; 16]);
+                        out.extend_from_slice(&self.timescale.to_be_bytes());
+                        out.extend_from_slice(&duration.to_be_bytes());
+                        // language "und" and pre_defined
+                        out.extend_from_slice(&0x55C4u16.to_be_bytes());
+                        out.extend_from_slice(&[0; 2]);
+                    });
+                    write_full_box(out, b"hdlr", 0, 0, |out| {
+                        out.extend_from_slice(&[0; 4]);
+                        out.extend_from_slice(b"vide");
+                        out.extend_from_slice(&[0; 12]);
+                        out.extend_from_slice(b"VideoHandler\0");
+                    });
+                    write_box(out, b"minf", |out| {
+                        write_full_box(out, b"vmhd", 0, 1, |out| out.extend_from_slice(&[0; 8]));
+                        write_box(out, b"dinf", |out| {
+                            write_full_box(out, b"dref", 0, 0, |out| {
+                                out.extend_from_slice(&1u32.to_be_bytes());
+                                // media data is in the same file
+                                write_full_box(out, b"url ", 0, 1, |_| {});
+                            });
+                        });
+                        write_box(out, b"stbl", |out| {
+                            write_full_box(out, b"stsd", 0, 0, |out| {
+                                out.extend_from_slice(&1u32.to_be_bytes());
+                                out.extend_from_slice(&self.sample_entry);
+                            });
+                            stbl(out);
+                        });
+                    });
+                });
+            });
+            if fragmented {
+                write_box(out, b"mvex", |out| {
+                    write_full_box(out, b"trex", 0, 0, |out| {
+                        out.extend_from_slice(&TRACK_ID.to_be_bytes());
+                        // default sample description index, duration, size and flags
+                        out.extend_from_slice(&1u32.to_be_bytes());
+                        out.extend_from_slice(&self.frame_duration.to_be_bytes());
+                        out.extend_from_slice(&0u32.to_be_bytes());
+                        out.extend_from_slice(&NON_SYNC_SAMPLE_FLAGS.to_be_bytes());
+                    });
+                });
+            }
+        });
+    }
+}
+
+/// Progressive MP4 writer. Samples are written into single `mdat` box as they come,
+/// and `moov` with sample tables is written by [`finish`].
+///
+/// [`finish`]: method@Self::finish
+#[derive(Debug)]
+pub struct Mp4Writer<W: Write + Seek> {
+    writer: W,
+    track: TrackInfo,
+    mdat_pos: u64,
+    samples: Vec<Sample>,
+    assembler: TemporalUnitAssembler,
+}
+
+impl<W: Write + Seek> Mp4Writer<W> {
+    /// Create writer for encoder output and write file header
+    pub fn new(writer: W, encoder: &SvtAv1Encoder) -> io::Result<Self> {
+        let stream_header = encoder.stream_header().map_err(io::Error::other)?;
+        Self::with_stream_header(writer, encoder.config(), &stream_header)
+    }
+
+    /// Create writer from encoder configuration and stream header returned by
+    /// [`SvtAv1Encoder::stream_header`], and write file header
+    pub fn with_stream_header(
+        mut writer: W,
+        config: &SvtAv1EncConfiguration,
+        stream_header: &[u8],
+    ) -> io::Result<Self> {
+        let track = TrackInfo::new(config, stream_header)?;
+        let mut header = Vec::new();
+        TrackInfo::ftyp(&mut header, &[b"isom", b"iso6", b"av01", b"mp41"]);
+        let mdat_pos = writer.stream_position()? + header.len() as u64;
+        // mdat with 64 bit size, which is filled when finished
+        header.extend_from_slice(&1u32.to_be_bytes());
+        header.extend_from_slice(b"mdat");
+        header.extend_from_slice(&0u64.to_be_bytes());
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer,
+            track,
+            mdat_pos,
+            samples: Vec::new(),
+            assembler: TemporalUnitAssembler::new(),
+        })
+    }
+
+    /// Write packet received from encoder
+    pub fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        match self.assembler.push_packet(packet) {
+            Some(unit) => self.write_temporal_unit(&unit),
+            None => Ok(()),
+        }
+    }
+
+    /// Write complete temporal unit without temporal delimiter as a sample
+    pub fn write_temporal_unit(&mut self, unit: &TemporalUnit) -> io::Result<()> {
+        self.writer.write_all(&unit.data)?;
+        self.samples.push(Sample {
+            size: unit.data.len() as u32,
+            pts: unit.pts,
+            sync: unit.keyframe,
+        });
+        Ok(())
+    }
+
+    /// Write remaining data and `moov` box. Returns inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(unit) = self.assembler.flush() {
+            self.write_temporal_unit(&unit)?;
+        }
+        let moov_pos = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.mdat_pos + 8))?;
+        self.writer
+            .write_all(&(moov_pos - self.mdat_pos).to_be_bytes())?;
+        self.writer.seek(SeekFrom::Start(moov_pos))?;
+
+        let durations = self.track.durations(&self.samples, None);
+        let duration = durations.iter().map(|d| *d as u64).sum();
+        let samples = &self.samples;
+        let first_sample_pos = self.mdat_pos + 16;
+        let mut moov = Vec::new();
+        self.track.moov(
+            &mut moov,
+            duration,
+            |out| {
+                write_full_box(out, b"stts", 0, 0, |out| {
+                    let mut runs: Vec<(u32, u32)> = Vec::new();
+                    for &duration in &durations {
+                        match runs.last_mut() {
+                            Some((count, delta)) if *delta == duration => *count += 1,
+                            _ => runs.push((1, duration)),
+                        }
+                    }
+                    out.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+                    for (count, delta) in runs {
+                        out.extend_from_slice(&count.to_be_bytes());
+                        out.extend_from_slice(&delta.to_be_bytes());
+                    }
+                });
+                if samples.iter().any(|s| !s.sync) {
+                    write_full_box(out, b"stss", 0, 0, |out| {
+                        let sync = samples
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, s)| s.sync)
+                            .map(|(i, _)| i as u32 + 1)
+                            .collect::<Vec<_>>();
+                        out.extend_from_slice(&(sync.len() as u32).to_be_bytes());
+                        sync.iter()
+                            .for_each(|i| out.extend_from_slice(&i.to_be_bytes()));
+                    });
+                }
+                // All samples are in a single chunk
+                write_full_box(out, b"stsc", 0, 0, |out| {
+                    if samples.is_empty() {
+                        out.extend_from_slice(&0u32.to_be_bytes());
+                    } else {
+                        out.extend_from_slice(&1u32.to_be_bytes());
+                        out.extend_from_slice(&1u32.to_be_bytes());
+                        out.extend_from_slice(&(samples.len() as u32).to_be_bytes());
+                        out.extend_from_slice(&1u32.to_be_bytes());
+                    }
+                });
+                write_full_box(out, b"stsz", 0, 0, |out| {
+                    out.extend_from_slice(&0u32.to_be_bytes());
+                    out.extend_from_slice(&(samples.len() as u32).to_be_bytes());
+                    samples
+                        .iter()
+                        .for_each(|s| out.extend_from_slice(&s.size.to_be_bytes()));
+                });
+                write_full_box(out, b"co64", 0, 0, |out| {
+                    let chunks = !samples.is_empty() as u32;
+                    out.extend_from_slice(&chunks.to_be_bytes());
+                    if chunks > 0 {
+                        out.extend_from_slice(&first_sample_pos.to_be_bytes());
+                    }
+                });
+            },
+            false,
+        );
+        self.writer.write_all(&moov)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Fragmented MP4 writer. Initialization segment (`ftyp` and `moov`) is written on creation,
+/// then every fragment is written as `moof` and `mdat` pair. A new fragment is started at every
+/// key frame, so each fragment can be used as CMAF segment. Doesn't require [`Seek`], so it
+/// can write to pipes and sockets.
+#[derive(Debug)]
+pub struct FragmentedMp4Writer<W: Write> {
+    writer: W,
+    track: TrackInfo,
+    sequence_number: u32,
+    max_fragment_frames: Option<usize>,
+    samples: Vec<Sample>,
+    data: Vec<u8>,
+    assembler: TemporalUnitAssembler,
+}
+
+impl<W: Write> FragmentedMp4Writer<W> {
+    /// Create writer for encoder output and write initialization segment
+    pub fn new(writer: W, encoder: &SvtAv1Encoder) -> io::Result<Self> {
+        let stream_header = encoder.stream_header().map_err(io::Error::other)?;
+        Self::with_stream_header(writer, encoder.config(), &stream_header)
+    }
+
+    /// Create writer from encoder configuration and stream header returned by
+    /// [`SvtAv1Encoder::stream_header`], and write initialization segment
+    pub fn with_stream_header(
+        mut writer: W,
+        config: &SvtAv1EncConfiguration,
+        stream_header: &[u8],
+    ) -> io::Result<Self> {
+        let track = TrackInfo::new(config, stream_header)?;
+        let mut header = Vec::new();
+        TrackInfo::ftyp(&mut header, &[b"iso6", b"cmfc", b"av01", b"isom"]);
+        track.moov(
+            &mut header,
+            0,
+            |out| {
+                write_full_box(out, b"stts", 0, 0, |out| out.extend_from_slice(&[0; 4]));
+                write_full_box(out, b"stsc", 0, 0, |out| out.extend_from_slice(&[0; 4]));
+                write_full_box(out, b"stsz", 0, 0, |out| out.extend_from_slice(&[0; 8]));
+                write_full_box(out, b"stco", 0, 0, |out| out.extend_from_slice(&[0; 4]));
+            },
+            true,
+        );
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer,
+            track,
+            sequence_number: 0,
+            max_fragment_frames: None,
+            samples: Vec::new(),
+            data: Vec::new(),
+            assembler: TemporalUnitAssembler::new(),
+        })
+    }
+
+    /// Limit number of frames in a fragment. Fragments which don't start with
+    /// a key frame can be used as CMAF chunks for low-latency delivery
+    pub fn set_max_fragment_frames(&mut self, frames: Option<usize>) {
+        self.max_fragment_frames = frames.map(|frames| frames.max(1));
+    }
+
+    /// Write packet received from encoder
+    pub fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        match self.assembler.push_packet(packet) {
+            Some(unit) => self.write_temporal_unit(&unit),
+            None => Ok(()),
+        }
+    }
+
+    /// Add complete temporal unit without temporal delimiter as a sample. Previous fragment
+    /// is written when this sample starts a new one
+    pub fn write_temporal_unit(&mut self, unit: &TemporalUnit) -> io::Result<()> {
+        let fragment_full = self
+            .max_fragment_frames
+            .is_some_and(|max| self.samples.len() >= max);
+        if unit.keyframe || fragment_full {
+            self.write_fragment(Some(unit.pts))?;
+        }
+        self.data.extend_from_slice(&unit.data);
+        self.samples.push(Sample {
+            size: unit.data.len() as u32,
+            pts: unit.pts,
+            sync: unit.keyframe,
+        });
+        Ok(())
+    }
+
+    fn write_fragment(&mut self, next_pts: Option<i64>) -> io::Result<()> {
+        if self.samples.is_empty() {
+            return Ok(());
+        }
+        self.sequence_number += 1;
+        let durations = self.track.durations(&self.samples, next_pts);
+        let base_decode_time = self.track.decode_time(self.samples[0].pts);
+        let samples = &self.samples;
+
+        let mut moof = Vec::new();
+        let mut data_offset_pos = 0;
+        write_box(&mut moof, b"moof", |out| {
+            write_full_box(out, b"mfhd", 0, 0, |out| {
+                out.extend_from_slice(&self.sequence_number.to_be_bytes());
+            });
+            write_box(out, b"traf", |out| {
+                // default-base-is-moof
+                write_full_box(out, b"tfhd", 0, 0x02_0000, |out| {
+                    out.extend_from_slice(&TRACK_ID.to_be_bytes());
+                });
+                write_full_box(out, b"tfdt", 1, 0, |out| {
+                    out.extend_from_slice(&base_decode_time.to_be_bytes());
+                });
+                // data-offset, sample-duration, sample-size and sample-flags present
+                write_full_box(out, b"trun", 0, 0x00_0701, |out| {
+                    out.extend_from_slice(&(samples.len() as u32).to_be_bytes());
+                    data_offset_pos = out.len();
+                    out.extend_from_slice(&0u32.to_be_bytes());
+                    for (sample, duration) in samples.iter().zip(&durations) {
+                        let flags = if sample.sync {
+                            SYNC_SAMPLE_FLAGS
+                        } else {
+                            NON_SYNC_SAMPLE_FLAGS
+                        };
+                        out.extend_from_slice(&duration.to_be_bytes());
+                        out.extend_from_slice(&sample.size.to_be_bytes());
+                        out.extend_from_slice(&flags.to_be_bytes());
+                    }
+                });
+            });
+        });
+        // Sample data starts right after mdat header
+        let data_offset = (moof.len() + 8) as u32;
+        moof[data_offset_pos..data_offset_pos + 4].copy_from_slice(&data_offset.to_be_bytes());
+        moof.extend_from_slice(&((self.data.len() + 8) as u32).to_be_bytes());
+        moof.extend_from_slice(b"mdat");
+
+        self.writer.write_all(&moof)?;
+        self.writer.write_all(&self.data)?;
+        self.samples.clear();
+        self.data.clear();
+        Ok(())
+    }
+
+    /// Write remaining data as the last fragment. Returns inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(unit) = self.assembler.flush() {
+            self.write_temporal_unit(&unit)?;
+        }
+        self.write_fragment(None)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bmff::{read_box, read_boxes},
+        obu::tests::stream_header,
+        tests::test_config,
+    };
+    use std::io::Cursor;
+
+    fn units(keyframes: &[bool]) -> Vec<TemporalUnit> {
+        keyframes
+            .iter()
+            .enumerate()
+            .map(|(pts, &keyframe)| TemporalUnit {
+                data: vec![pts as u8; pts + 1],
+                pts: pts as i64,
+                keyframe,
+            })
+            .collect()
+    }
+
+    /// Find box by path of types, starting from top level
+    fn find_box(data: &[u8], path: &[&[u8; 4]]) -> std::ops::Range<usize> {
+        let mut range = 0..data.len();
+        for box_type in path {
+            let boxes = read_boxes(data, range);
+            range = boxes.into_iter().find(|(t, _)| t == *box_type).unwrap().1;
+        }
+        range
+    }
+
+    fn u32_at(data: &[u8], pos: usize) -> u32 {
+        u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn progressive() {
+        let mut config = test_config(640, 360);
+        config.content_light_level.max_cll = 1000;
+        let mut writer =
+            Mp4Writer::with_stream_header(Cursor::new(Vec::new()), &config, &stream_header())
+                .unwrap();
+        for unit in units(&[true, false, false, true]) {
+            writer.write_temporal_unit(&unit).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+
+        let top = read_boxes(&data, 0..data.len());
+        let types = top.iter().map(|(t, _)| t).collect::<Vec<_>>();
+        assert_eq!(types, [b"ftyp", b"mdat", b"moov"]);
+        assert_eq!(data[top[1].1.clone()], [0, 1, 1, 2, 2, 2, 3, 3, 3, 3]);
+
+        let stbl = [b"moov", b"trak", b"mdia", b"minf", b"stbl"];
+        let stsd = find_box(&data, &[&stbl[..], &[b"stsd"]].concat());
+        let (entry_type, entry) = read_box(&data, stsd.start + 8);
+        assert_eq!(&entry_type, b"av01");
+        let children = read_boxes(&data, entry.start + 78..entry.end);
+        let types = children.iter().map(|(t, _)| t).collect::<Vec<_>>();
+        assert_eq!(types, [b"av1C", b"colr", b"clli"]);
+
+        let stss = find_box(&data, &[&stbl[..], &[b"stss"]].concat());
+        assert_eq!(u32_at(&data, stss.start + 4), 2);
+        assert_eq!(u32_at(&data, stss.start + 8), 1);
+        assert_eq!(u32_at(&data, stss.start + 12), 4);
+
+        let stts = find_box(&data, &[&stbl[..], &[b"stts"]].concat());
+        assert_eq!(
+            data[stts.start + 4..stts.end],
+            [0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 1]
+        );
+
+        let co64 = find_box(&data, &[&stbl[..], &[b"co64"]].concat());
+        let offset = u64::from_be_bytes(data[co64.start + 8..co64.end].try_into().unwrap());
+        assert_eq!(offset as usize, top[1].1.start);
+    }
+
+    #[test]
+    fn fragmented() {
+        let config = test_config(640, 360);
+        let mut writer =
+            FragmentedMp4Writer::with_stream_header(Vec::new(), &config, &stream_header()).unwrap();
+        for unit in units(&[true, false, false, true, false]) {
+            writer.write_temporal_unit(&unit).unwrap();
+        }
+        let data = writer.finish().unwrap();
+
+        let top = read_boxes(&data, 0..data.len());
+        let types = top.iter().map(|(t, _)| t).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [b"ftyp", b"moov", b"moof", b"mdat", b"moof", b"mdat"]
+        );
+        assert!(!find_box(&data, &[b"moov", b"mvex", b"trex"]).is_empty());
+
+        // Second fragment starts at fourth frame
+        let moof = top[4].1.clone();
+        let moof_start = moof.start - 8;
+        let traf = find_box(&data[moof.clone()], &[b"traf"]);
+        let traf = traf.start + moof.start..traf.end + moof.start;
+        let boxes = read_boxes(&data, traf);
+        assert_eq!(&boxes[1].0, b"tfdt");
+        let tfdt = u64::from_be_bytes(
+            data[boxes[1].1.start + 4..boxes[1].1.end]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(tfdt, 3);
+        let trun = boxes[2].1.start;
+        assert_eq!(u32_at(&data, trun + 4), 2);
+        let data_offset = u32_at(&data, trun + 8) as usize;
+        assert_eq!(moof_start + data_offset, top[5].1.start);
+        // first sample is sync, second isn't
+        assert_eq!(u32_at(&data, trun + 20), SYNC_SAMPLE_FLAGS);
+        assert_eq!(u32_at(&data, trun + 32), NON_SYNC_SAMPLE_FLAGS);
+        assert_eq!(data[top[5].1.clone()], [3, 3, 3, 3, 4, 4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn fragment_size_limit() {
+        let config = test_config(640, 360);
+        let mut writer =
+            FragmentedMp4Writer::with_stream_header(Vec::new(), &config, &stream_header()).unwrap();
+        writer.set_max_fragment_frames(Some(2));
+        for unit in units(&[true, false, false, false, false]) {
+            writer.write_temporal_unit(&unit).unwrap();
+        }
+        let data = writer.finish().unwrap();
+        let moofs = read_boxes(&data, 0..data.len())
+            .into_iter()
+            .filter(|(t, _)| t == b"moof")
+            .count();
+        assert_eq!(moofs, 3);
+    }
+}
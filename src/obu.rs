@@ -0,0 +1,954 @@
+//! Parsing and writing of AV1 Open Bitstream Units (OBUs).
+//!
+//! [`Packet`] data produced by the encoder is a sequence of OBUs in the low-overhead
+//! bitstream format (AV1 specification, Section 5), where every OBU carries its own size.
+//! This module can split that data into individual [`Obu`]s and re-emit it either as a
+//! Section 5 stream or as length-delimited Annex-B temporal units with [`ObuWriter`].
+//!
+//! [`Packet`]: crate::Packet
+use crate::{
+    ffi::{Av1PictureType, SvtFlags},
+    Packet,
+};
+use std::{fmt, io};
+
+/// Temporal delimiter OBU with `obu_has_size_field` set and zero payload size
+pub(crate) const TEMPORAL_DELIMITER: [u8; 2] = [0x12, 0x00];
+
+/// Type of OBU as stored in `obu_type` field of OBU header
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum ObuType {
+    SequenceHeader,
+    TemporalDelimiter,
+    FrameHeader,
+    TileGroup,
+    Metadata,
+    Frame,
+    RedundantFrameHeader,
+    TileList,
+    Padding,
+    /// Reserved OBU type, decoders must ignore it
+    Reserved(u8),
+}
+
+impl ObuType {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            1 => Self::SequenceHeader,
+            2 => Self::TemporalDelimiter,
+            3 => Self::FrameHeader,
+            4 => Self::TileGroup,
+            5 => Self::Metadata,
+            6 => Self::Frame,
+            7 => Self::RedundantFrameHeader,
+            8 => Self::TileList,
+            15 => Self::Padding,
+            other => Self::Reserved(other),
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Self::SequenceHeader => 1,
+            Self::TemporalDelimiter => 2,
+            Self::FrameHeader => 3,
+            Self::TileGroup => 4,
+            Self::Metadata => 5,
+            Self::Frame => 6,
+            Self::RedundantFrameHeader => 7,
+            Self::TileList => 8,
+            Self::Padding => 15,
+            Self::Reserved(bits) => bits,
+        }
+    }
+}
+
+/// Optional OBU extension header, present for scalable streams
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct ObuExtension {
+    pub temporal_id: u8,
+    pub spatial_id: u8,
+}
+
+/// Single OBU borrowed from packet data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Obu<'a> {
+    pub obu_type: ObuType,
+    pub extension: Option<ObuExtension>,
+    /// Whether OBU was stored with `obu_has_size_field` set
+    pub has_size_field: bool,
+    /// OBU payload without header and size field
+    pub payload: &'a [u8],
+}
+
+impl Obu<'_> {
+    /// Length of OBU header, including extension header if present
+    pub fn header_len(&self) -> usize {
+        1 + self.extension.is_some() as usize
+    }
+
+    fn write_header(&self, has_size_field: bool, out: &mut Vec<u8>) {
+        let mut header = self.obu_type.bits() << 3;
+        if self.extension.is_some() {
+            header |= 0x04;
+        }
+        if has_size_field {
+            header |= 0x02;
+        }
+        out.push(header);
+        if let Some(ext) = self.extension {
+            out.push((ext.temporal_id << 5) | ((ext.spatial_id & 0x03) << 3));
+        }
+    }
+
+    /// Append OBU in Section 5 format, that is with `obu_has_size_field` set
+    pub fn write_section5(&self, out: &mut Vec<u8>) {
+        self.write_header(true, out);
+        write_leb128(self.payload.len() as u64, out);
+        out.extend_from_slice(self.payload);
+    }
+
+    /// Append OBU in Annex-B format, that is prefixed with `obu_length` and without size field
+    pub fn write_annexb(&self, out: &mut Vec<u8>) {
+        write_leb128((self.header_len() + self.payload.len()) as u64, out);
+        self.write_header(false, out);
+        out.extend_from_slice(self.payload);
+    }
+}
+
+/// Error returned when OBU data is malformed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ObuError {
+    /// Data ended in the middle of OBU header, size field or payload
+    UnexpectedEnd,
+    /// `leb128()` value is longer than 8 bytes or doesn't fit into 32 bits
+    InvalidLeb128,
+    /// `obu_forbidden_bit` is set
+    ForbiddenBit,
+    /// Data doesn't contain sequence header OBU
+    MissingSequenceHeader,
+}
+
+impl fmt::Display for ObuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::UnexpectedEnd => "unexpected end of OBU data",
+            Self::InvalidLeb128 => "invalid leb128 value",
+            Self::ForbiddenBit => "OBU forbidden bit is set",
+            Self::MissingSequenceHeader => "sequence header OBU not found",
+        })
+    }
+}
+
+impl std::error::Error for ObuError {}
+
+impl From<ObuError> for io::Error {
+    fn from(err: ObuError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Read `leb128()` value from the start of `data`. Returns value and number of bytes read
+pub fn read_leb128(data: &[u8]) -> Result<(u64, usize), ObuError> {
+    let mut value = 0u64;
+    for i in 0..8 {
+        let byte = *data.get(i).ok_or(ObuError::UnexpectedEnd)?;
+        value |= ((byte & 0x7f) as u64) << (i * 7);
+        if byte & 0x80 == 0 {
+            if value > u32::MAX as u64 {
+                return Err(ObuError::InvalidLeb128);
+            }
+            return Ok((value, i + 1));
+        }
+    }
+    Err(ObuError::InvalidLeb128)
+}
+
+/// Append `value` encoded as `leb128()` using minimal number of bytes
+pub fn write_leb128(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Iterator over OBUs of Section 5 formatted data. Created by [`parse_obus`]
+#[derive(Debug, Clone)]
+pub struct ObuIter<'a> {
+    data: &'a [u8],
+}
+
+/// Split Section 5 formatted data (for example [`Packet`] content) into OBUs
+pub fn parse_obus(data: &[u8]) -> ObuIter<'_> {
+    ObuIter { data }
+}
+
+impl<'a> ObuIter<'a> {
+    fn parse_next(&mut self) -> Result<Obu<'a>, ObuError> {
+        let data = self.data;
+        let header = data[0];
+        if header & 0x80 != 0 {
+            return Err(ObuError::ForbiddenBit);
+        }
+        let obu_type = ObuType::from_bits((header >> 3) & 0x0f);
+        let has_extension = header & 0x04 != 0;
+        let has_size_field = header & 0x02 != 0;
+        let mut pos = 1;
+        let extension = if has_extension {
+            let ext = *data.get(1).ok_or(ObuError::UnexpectedEnd)?;
+            pos += 1;
+            Some(ObuExtension {
+                temporal_id: ext >> 5,
+                spatial_id: (ext >> 3) & 0x03,
+            })
+        } else {
+            None
+        };
+        let payload_len = if has_size_field {
+            let (size, read) = read_leb128(&data[pos..])?;
+            pos += read;
+            size as usize
+        } else {
+            data.len() - pos
+        };
+        let payload = data
+            .get(pos..pos + payload_len)
+            .ok_or(ObuError::UnexpectedEnd)?;
+        self.data = &data[pos + payload_len..];
+
+        Ok(Obu {
+            obu_type,
+            extension,
+            has_size_field,
+            payload,
+        })
+    }
+}
+
+impl<'a> Iterator for ObuIter<'a> {
+    type Item = Result<Obu<'a>, ObuError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let res = self.parse_next();
+        if res.is_err() {
+            // Stop after first error, the rest of data can't be trusted
+            self.data = &[];
+        }
+        Some(res)
+    }
+}
+
+/// Output bitstream format of [`ObuWriter`]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum ObuFormat {
+    /// Low-overhead bitstream format, every OBU has `obu_has_size_field` set
+    Section5,
+    /// Length-delimited bitstream format. Every temporal unit and frame unit is
+    /// prefixed with its size and OBUs don't have size field
+    AnnexB,
+}
+
+/// Writes encoder packets as raw `.obu` stream in Section 5 or Annex-B format.
+///
+/// Encoder may output ALT-REF frame as a separate packet (with [`SvtFlags::BUFFERFLAG_IS_ALT_REF`] set)
+/// which belongs to the same temporal unit as the next packet, so such packets are buffered
+/// until the temporal unit is complete. Call [`finish`] after the last packet.
+///
+/// [`finish`]: method@Self::finish
+#[derive(Debug)]
+pub struct ObuWriter<W: io::Write> {
+    writer: W,
+    format: ObuFormat,
+    temporal_delimiters: bool,
+    pending: Vec<u8>,
+    pending_has_td: bool,
+}
+
+impl<W: io::Write> ObuWriter<W> {
+    /// Create new writer. If `temporal_delimiters` is true, every temporal unit will start with
+    /// temporal delimiter OBU (inserting it if encoder didn't emit one), otherwise all temporal
+    /// delimiters are dropped
+    pub fn new(writer: W, format: ObuFormat, temporal_delimiters: bool) -> Self {
+        Self {
+            writer,
+            format,
+            temporal_delimiters,
+            pending: Vec::new(),
+            pending_has_td: false,
+        }
+    }
+
+    /// Write encoded packet
+    pub fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        self.write_data(packet, packet.flags)
+    }
+
+    /// Write raw Section 5 packet data with flags reported by encoder for that packet
+    pub fn write_data(&mut self, data: &[u8], flags: SvtFlags) -> io::Result<()> {
+        if self.pending.is_empty() {
+            self.pending_has_td = flags.contains(SvtFlags::BUFFERFLAG_HAS_TD);
+        }
+        self.pending.extend_from_slice(data);
+        if flags.contains(SvtFlags::BUFFERFLAG_IS_ALT_REF) {
+            // Shown frame of this temporal unit will come with next packet
+            return Ok(());
+        }
+        self.flush_temporal_unit()
+    }
+
+    /// Write buffered data, if any, and return inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            self.flush_temporal_unit()?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn flush_temporal_unit(&mut self) -> io::Result<()> {
+        let data = std::mem::take(&mut self.pending);
+        let insert_td = self.temporal_delimiters && !self.pending_has_td;
+        let out = match self.format {
+            ObuFormat::Section5 => self.section5_temporal_unit(&data, insert_td)?,
+            ObuFormat::AnnexB => self.annexb_temporal_unit(&data, insert_td)?,
+        };
+        self.pending = data;
+        self.pending.clear();
+        self.writer.write_all(&out)
+    }
+
+    fn section5_temporal_unit(&self, data: &[u8], insert_td: bool) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(data.len() + TEMPORAL_DELIMITER.len());
+        if insert_td {
+            out.extend_from_slice(&TEMPORAL_DELIMITER);
+        }
+        for obu in parse_obus(data) {
+            let obu = obu?;
+            if obu.obu_type == ObuType::TemporalDelimiter && !self.temporal_delimiters {
+                continue;
+            }
+            obu.write_section5(&mut out);
+        }
+        Ok(out)
+    }
+
+    fn annexb_temporal_unit(&self, data: &[u8], insert_td: bool) -> io::Result<Vec<u8>> {
+        let mut frame_units = Vec::new();
+        let mut unit = Vec::new();
+        let mut unit_has_frame = false;
+        if insert_td {
+            Obu {
+                obu_type: ObuType::TemporalDelimiter,
+                extension: None,
+                has_size_field: false,
+                payload: &[],
+            }
+            .write_annexb(&mut unit);
+        }
+        for obu in parse_obus(data) {
+            let obu = obu?;
+            match obu.obu_type {
+                ObuType::TemporalDelimiter if !self.temporal_delimiters => continue,
+                // These OBUs belong to frame which is already in the unit
+                ObuType::TileGroup | ObuType::RedundantFrameHeader | ObuType::Padding => {}
+                // Anything else after a complete frame starts a new frame unit
+                _ if unit_has_frame => {
+                    write_leb128(unit.len() as u64, &mut frame_units);
+                    frame_units.append(&mut unit);
+                    unit_has_frame = false;
+                }
+                _ => {}
+            }
+            if matches!(obu.obu_type, ObuType::Frame | ObuType::FrameHeader) {
+                unit_has_frame = true;
+            }
+            obu.write_annexb(&mut unit);
+        }
+        if !unit.is_empty() {
+            write_leb128(unit.len() as u64, &mut frame_units);
+            frame_units.append(&mut unit);
+        }
+
+        let mut out = Vec::with_capacity(frame_units.len() + 8);
+        write_leb128(frame_units.len() as u64, &mut out);
+        out.append(&mut frame_units);
+        Ok(out)
+    }
+}
+
+/// Remove temporal delimiter OBU from the start of packet data, if present.
+/// Containers (Matroska, ISO-BMFF) require temporal units to be stored without it
+pub fn strip_temporal_delimiter(data: &[u8]) -> &[u8] {
+    let mut obus = parse_obus(data);
+    match obus.next() {
+        Some(Ok(obu)) if obu.obu_type == ObuType::TemporalDelimiter => obus.data,
+        _ => data,
+    }
+}
+
+/// Complete temporal unit assembled from one or more encoder packets, with temporal delimiter removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemporalUnit {
+    pub data: Vec<u8>,
+    /// Presentation timestamp of the shown frame
+    pub pts: i64,
+    /// Whether temporal unit starts with a key frame
+    pub keyframe: bool,
+}
+
+/// Joins encoder packets into complete temporal units, as stored by containers.
+///
+/// ALT-REF frame is output by encoder as a separate packet, but it belongs to the temporal
+/// unit of the next shown frame.
+#[derive(Debug, Default)]
+pub struct TemporalUnitAssembler {
+    pending: Option<TemporalUnit>,
+}
+
+impl TemporalUnitAssembler {
+    /// Create new assembler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add packet. Returns temporal unit when it's complete
+    pub fn push_packet(&mut self, packet: &Packet) -> Option<TemporalUnit> {
+        self.push(packet, packet.flags, packet.pts, packet.pic_type)
+    }
+
+    /// Add raw packet data with properties reported by encoder. Returns temporal unit when it's complete
+    pub fn push(
+        &mut self,
+        data: &[u8],
+        flags: SvtFlags,
+        pts: i64,
+        pic_type: Av1PictureType,
+    ) -> Option<TemporalUnit> {
+        let unit = self.pending.get_or_insert_with(|| TemporalUnit {
+            data: Vec::with_capacity(data.len()),
+            pts,
+            keyframe: pic_type == Av1PictureType::Av1KeyPicture,
+        });
+        unit.data.extend_from_slice(strip_temporal_delimiter(data));
+        unit.pts = pts;
+        if flags.contains(SvtFlags::BUFFERFLAG_IS_ALT_REF) {
+            return None;
+        }
+        self.pending.take()
+    }
+
+    /// Return incomplete temporal unit, if any. Use it after the last packet
+    pub fn flush(&mut self) -> Option<TemporalUnit> {
+        self.pending.take()
+    }
+}
+
+/// Reader of fixed-width and variable-length fields from OBU payload
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Result<bool, ObuError> {
+        let byte = self.data.get(self.pos / 8).ok_or(ObuError::UnexpectedEnd)?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(bit == 1)
+    }
+
+    /// `f(n)` from the specification
+    fn bits(&mut self, n: u32) -> Result<u32, ObuError> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            value = (value << 1) | self.bit()? as u32;
+        }
+        Ok(value)
+    }
+
+    /// `uvlc()` from the specification
+    fn uvlc(&mut self) -> Result<u32, ObuError> {
+        let mut leading_zeros = 0;
+        while !self.bit()? {
+            leading_zeros += 1;
+        }
+        if leading_zeros >= 32 {
+            return Ok(u32::MAX);
+        }
+        Ok(self.bits(leading_zeros)? + ((1u64 << leading_zeros) - 1) as u32)
+    }
+}
+
+/// Fields of sequence header OBU which are needed by containers
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct SequenceHeader {
+    pub seq_profile: u8,
+    pub still_picture: bool,
+    pub reduced_still_picture_header: bool,
+    /// Level of the first operating point
+    pub seq_level_idx_0: u8,
+    /// Tier of the first operating point
+    pub seq_tier_0: u8,
+    /// `initial_display_delay_minus_1` of the first operating point, if present
+    pub initial_display_delay_minus_1_0: Option<u8>,
+    pub max_frame_width: u32,
+    pub max_frame_height: u32,
+    pub bit_depth: u8,
+    pub mono_chrome: bool,
+    pub subsampling_x: bool,
+    pub subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub color_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub color_range: bool,
+    pub film_grain_params_present: bool,
+}
+
+impl SequenceHeader {
+    /// Find sequence header OBU in Section 5 formatted data and parse it
+    pub fn find(data: &[u8]) -> Result<Self, ObuError> {
+        for obu in parse_obus(data) {
+            let obu = obu?;
+            if obu.obu_type == ObuType::SequenceHeader {
+                return Self::parse(obu.payload);
+            }
+        }
+        Err(ObuError::MissingSequenceHeader)
+    }
+
+    /// Parse payload of sequence header OBU
+    pub fn parse(payload: &[u8]) -> Result<Self, ObuError> {
+        let mut r = BitReader::new(payload);
+        let seq_profile = r.bits(3)? as u8;
+        let still_picture = r.bit()?;
+        let reduced_still_picture_header = r.bit()?;
+        let mut seq_tier_0 = 0;
+        let mut initial_display_delay_minus_1_0 = None;
+        let seq_level_idx_0;
+        if reduced_still_picture_header {
+            seq_level_idx_0 = r.bits(5)? as u8;
+        } else {
+            let timing_info_present = r.bit()?;
+            let mut decoder_model_info_present = false;
+            let mut buffer_delay_length = 0;
+            if timing_info_present {
+                let _num_units_in_display_tick = r.bits(32)?;
+                let _time_scale = r.bits(32)?;
+                if r.bit()? {
+                    let _num_ticks_per_picture_minus_1 = r.uvlc()?;
+                }
+                decoder_model_info_present = r.bit()?;
+                if decoder_model_info_present {
+                    buffer_delay_length = r.bits(5)? + 1;
+                    let _num_units_in_decoding_tick = r.bits(32)?;
+                    let _buffer_removal_time_length_minus_1 = r.bits(5)?;
+                    let _frame_presentation_time_length_minus_1 = r.bits(5)?;
+                }
+            }
+            let initial_display_delay_present = r.bit()?;
+            let operating_points_cnt = r.bits(5)? + 1;
+            let mut first = (0, 0, None);
+            for i in 0..operating_points_cnt {
+                let _operating_point_idc = r.bits(12)?;
+                let level = r.bits(5)? as u8;
+                let tier = if level > 7 { r.bits(1)? as u8 } else { 0 };
+                if decoder_model_info_present && r.bit()? {
+                    let _decoder_buffer_delay = r.bits(buffer_delay_length)?;
+                    let _encoder_buffer_delay = r.bits(buffer_delay_length)?;
+                    let _low_delay_mode_flag = r.bit()?;
+                }
+                let mut display_delay = None;
+                if initial_display_delay_present && r.bit()? {
+                    display_delay = Some(r.bits(4)? as u8);
+                }
+                if i == 0 {
+                    first = (level, tier, display_delay);
+                }
+            }
+            (seq_level_idx_0, seq_tier_0, initial_display_delay_minus_1_0) = first;
+        }
+
+        let frame_width_bits = r.bits(4)? + 1;
+        let frame_height_bits = r.bits(4)? + 1;
+        let max_frame_width = r.bits(frame_width_bits)? + 1;
+        let max_frame_height = r.bits(frame_height_bits)? + 1;
+        if !reduced_still_picture_header && r.bit()? {
+            let _delta_frame_id_length_minus_2 = r.bits(4)?;
+            let _additional_frame_id_length_minus_1 = r.bits(3)?;
+        }
+        let _use_128x128_superblock = r.bit()?;
+        let _enable_filter_intra = r.bit()?;
+        let _enable_intra_edge_filter = r.bit()?;
+        if !reduced_still_picture_header {
+            let _enable_interintra_compound = r.bit()?;
+            let _enable_masked_compound = r.bit()?;
+            let _enable_warped_motion = r.bit()?;
+            let _enable_dual_filter = r.bit()?;
+            let enable_order_hint = r.bit()?;
+            if enable_order_hint {
+                let _enable_jnt_comp = r.bit()?;
+                let _enable_ref_frame_mvs = r.bit()?;
+            }
+            let seq_force_screen_content_tools = if r.bit()? { 2 } else { r.bits(1)? };
+            if seq_force_screen_content_tools > 0 && !r.bit()? {
+                let _seq_force_integer_mv = r.bit()?;
+            }
+            if enable_order_hint {
+                let _order_hint_bits_minus_1 = r.bits(3)?;
+            }
+        }
+        let _enable_superres = r.bit()?;
+        let _enable_cdef = r.bit()?;
+        let _enable_restoration = r.bit()?;
+
+        // color_config()
+        let high_bitdepth = r.bit()?;
+        let bit_depth = match (seq_profile, high_bitdepth) {
+            (2, true) if r.bit()? => 12,
+            (_, true) => 10,
+            (_, false) => 8,
+        };
+        let mono_chrome = seq_profile != 1 && r.bit()?;
+        let (color_primaries, transfer_characteristics, matrix_coefficients) = if r.bit()? {
+            (r.bits(8)? as u8, r.bits(8)? as u8, r.bits(8)? as u8)
+        } else {
+            (2, 2, 2)
+        };
+        let color_range;
+        let mut subsampling_x = true;
+        let mut subsampling_y = true;
+        let mut chroma_sample_position = 0;
+        if mono_chrome {
+            color_range = r.bit()?;
+        } else {
+            if color_primaries == 1 && transfer_characteristics == 13 && matrix_coefficients == 0 {
+                color_range = true;
+                subsampling_x = false;
+                subsampling_y = false;
+            } else {
+                color_range = r.bit()?;
+                match seq_profile {
+                    0 => {}
+                    1 => {
+                        subsampling_x = false;
+                        subsampling_y = false;
+                    }
+                    _ if bit_depth == 12 => {
+                        subsampling_x = r.bit()?;
+                        subsampling_y = subsampling_x && r.bit()?;
+                    }
+                    _ => subsampling_y = false,
+                }
+                if subsampling_x && subsampling_y {
+                    chroma_sample_position = r.bits(2)? as u8;
+                }
+            }
+            let _separate_uv_delta_q = r.bit()?;
+        }
+        let film_grain_params_present = r.bit()?;
+
+        Ok(Self {
+            seq_profile,
+            still_picture,
+            reduced_still_picture_header,
+            seq_level_idx_0,
+            seq_tier_0,
+            initial_display_delay_minus_1_0,
+            max_frame_width,
+            max_frame_height,
+            bit_depth,
+            mono_chrome,
+            subsampling_x,
+            subsampling_y,
+            chroma_sample_position,
+            color_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            color_range,
+            film_grain_params_present,
+        })
+    }
+}
+
+/// Build `AV1CodecConfigurationRecord` (`av1C`) from stream header returned by
+/// [`SvtAv1Encoder::stream_header`]. It's used as Matroska `CodecPrivate` and
+/// ISO-BMFF `av1C` box payload.
+///
+/// [`SvtAv1Encoder::stream_header`]: method@crate::SvtAv1Encoder::stream_header
+pub fn av1_codec_config(stream_header: &[u8]) -> Result<Vec<u8>, ObuError> {
+    let obu = parse_obus(stream_header)
+        .find(|obu| {
+            obu.as_ref()
+                .map_or(true, |obu| obu.obu_type == ObuType::SequenceHeader)
+        })
+        .ok_or(ObuError::MissingSequenceHeader)??;
+    let seq = SequenceHeader::parse(obu.payload)?;
+
+    let mut out = Vec::with_capacity(4 + 2 + obu.header_len() + obu.payload.len());
+    // marker and version
+    out.push(0x81);
+    out.push((seq.seq_profile << 5) | (seq.seq_level_idx_0 & 0x1f));
+    out.push(
+        (seq.seq_tier_0 << 7)
+            | (((seq.bit_depth > 8) as u8) << 6)
+            | (((seq.bit_depth == 12) as u8) << 5)
+            | ((seq.mono_chrome as u8) << 4)
+            | ((seq.subsampling_x as u8) << 3)
+            | ((seq.subsampling_y as u8) << 2)
+            | seq.chroma_sample_position,
+    );
+    out.push(match seq.initial_display_delay_minus_1_0 {
+        Some(delay) => 0x10 | delay,
+        None => 0,
+    });
+    // configOBUs contain only the sequence header
+    obu.write_section5(&mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // TD, sequence header (3 bytes) and frame (4 bytes), all with size field
+    const TU: [u8; 13] = [
+        0x12, 0x00, 0x0a, 0x03, 0xaa, 0xbb, 0xcc, 0x32, 0x04, 0x01, 0x02, 0x03, 0x04,
+    ];
+
+    #[test]
+    fn leb128() {
+        for value in [0u64, 1, 127, 128, 300, 16383, 16384, u32::MAX as u64] {
+            let mut buf = Vec::new();
+            write_leb128(value, &mut buf);
+            assert_eq!(read_leb128(&buf).unwrap(), (value, buf.len()));
+        }
+        assert_eq!(read_leb128(&[0x80, 0x80]), Err(ObuError::UnexpectedEnd));
+        assert_eq!(read_leb128(&[0xff; 9]), Err(ObuError::InvalidLeb128));
+    }
+
+    #[test]
+    fn parse() {
+        let obus = parse_obus(&TU).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(obus.len(), 3);
+        assert_eq!(obus[0].obu_type, ObuType::TemporalDelimiter);
+        assert_eq!(obus[1].obu_type, ObuType::SequenceHeader);
+        assert_eq!(obus[1].payload, &[0xaa, 0xbb, 0xcc]);
+        assert_eq!(obus[2].obu_type, ObuType::Frame);
+        assert_eq!(obus[2].payload, &[0x01, 0x02, 0x03, 0x04]);
+
+        let mut out = Vec::new();
+        obus.iter().for_each(|obu| obu.write_section5(&mut out));
+        assert_eq!(out, TU);
+
+        assert_eq!(
+            parse_obus(&TU[..10]).last(),
+            Some(Err(ObuError::UnexpectedEnd))
+        );
+        assert_eq!(
+            parse_obus(&[0x80]).next(),
+            Some(Err(ObuError::ForbiddenBit))
+        );
+    }
+
+    #[test]
+    fn extension_header() {
+        let data = [0x36, 0x48, 0x01, 0xff];
+        let obu = parse_obus(&data).next().unwrap().unwrap();
+        assert_eq!(obu.obu_type, ObuType::Frame);
+        assert_eq!(
+            obu.extension,
+            Some(ObuExtension {
+                temporal_id: 2,
+                spatial_id: 1
+            })
+        );
+        let mut out = Vec::new();
+        obu.write_section5(&mut out);
+        assert_eq!(out, data);
+    }
+
+    /// Pack string of '0' and '1' into bytes, ignoring any other characters
+    fn pack_bits(bits: &str) -> Vec<u8> {
+        let bits = bits
+            .chars()
+            .filter_map(|c| c.to_digit(2))
+            .collect::<Vec<_>>();
+        bits.chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << (7 - i)))
+            })
+            .collect()
+    }
+
+    /// Sequence header of 1920x1080 10-bit 4:2:0 BT.2020 PQ stream
+    fn sequence_header_payload() -> Vec<u8> {
+        pack_bits(concat!(
+            "000 0 0",                    // profile, still_picture, reduced_still_picture_header
+            "0 0 00000", // timing info, initial display delay, operating_points_cnt_minus_1
+            "000000000000 01000 0", // operating_point_idc, seq_level_idx = 8, seq_tier
+            "1010 1010", // frame width and height bits
+            "11101111111 10000110111", // max frame width and height minus 1
+            "0 0 1 1",   // frame id numbers, 128x128, filter intra, intra edge
+            "0 0 0 0 1 0 1", // interintra .. order hint, jnt comp, ref frame mvs
+            "1 1 101",   // screen content tools, integer mv, order hint bits
+            "0 1 1",     // superres, cdef, restoration
+            "1 0 1",     // high_bitdepth, mono_chrome, color description
+            "00001001 00010000 00001001", // BT.2020, PQ, BT.2020 NCL
+            "0 00 0 0",  // color range, chroma sample position, separate uv dq, film grain
+        ))
+    }
+
+    #[test]
+    fn sequence_header() {
+        let payload = sequence_header_payload();
+        let seq = SequenceHeader::parse(&payload).unwrap();
+        assert_eq!(seq.seq_profile, 0);
+        assert_eq!(seq.seq_level_idx_0, 8);
+        assert_eq!(seq.max_frame_width, 1920);
+        assert_eq!(seq.max_frame_height, 1080);
+        assert_eq!(seq.bit_depth, 10);
+        assert!(!seq.mono_chrome);
+        assert!(seq.subsampling_x && seq.subsampling_y);
+        assert_eq!(
+            (
+                seq.color_primaries,
+                seq.transfer_characteristics,
+                seq.matrix_coefficients
+            ),
+            (9, 16, 9)
+        );
+        assert!(!seq.color_range);
+        assert!(!seq.film_grain_params_present);
+    }
+
+    /// Temporal delimiter and sequence header, as returned by encoder
+    pub(crate) fn stream_header() -> Vec<u8> {
+        let payload = sequence_header_payload();
+        let mut stream_header = TEMPORAL_DELIMITER.to_vec();
+        Obu {
+            obu_type: ObuType::SequenceHeader,
+            extension: None,
+            has_size_field: true,
+            payload: &payload,
+        }
+        .write_section5(&mut stream_header);
+        stream_header
+    }
+
+    #[test]
+    fn codec_config() {
+        let stream_header = stream_header();
+        let av1c = av1_codec_config(&stream_header).unwrap();
+        assert_eq!(av1c[..4], [0x81, 0x08, 0x4c, 0x00]);
+        assert_eq!(av1c[4..], stream_header[2..]);
+        assert_eq!(
+            av1_codec_config(&TEMPORAL_DELIMITER),
+            Err(ObuError::MissingSequenceHeader)
+        );
+    }
+
+    #[test]
+    fn temporal_units() {
+        let mut assembler = TemporalUnitAssembler::new();
+        let alt_ref = assembler.push(
+            &TU,
+            SvtFlags::BUFFERFLAG_HAS_TD | SvtFlags::BUFFERFLAG_IS_ALT_REF,
+            8,
+            Av1PictureType::Av1AltRefPicture,
+        );
+        assert_eq!(alt_ref, None);
+        let unit = assembler
+            .push(
+                &[0x32, 0x01, 0x05],
+                SvtFlags::empty(),
+                1,
+                Av1PictureType::Av1InterPicture,
+            )
+            .unwrap();
+        assert_eq!(unit.data, [&TU[2..], &[0x32, 0x01, 0x05]].concat());
+        assert_eq!(unit.pts, 1);
+        assert!(!unit.keyframe);
+
+        let unit = assembler
+            .push(
+                &TU,
+                SvtFlags::BUFFERFLAG_HAS_TD,
+                2,
+                Av1PictureType::Av1KeyPicture,
+            )
+            .unwrap();
+        assert_eq!(unit.data, &TU[2..]);
+        assert!(unit.keyframe);
+        assert_eq!(assembler.flush(), None);
+    }
+
+    #[test]
+    fn section5_temporal_delimiters() {
+        let mut writer = ObuWriter::new(Vec::new(), ObuFormat::Section5, false);
+        writer.write_data(&TU, SvtFlags::BUFFERFLAG_HAS_TD).unwrap();
+        assert_eq!(writer.finish().unwrap(), &TU[2..]);
+
+        let mut writer = ObuWriter::new(Vec::new(), ObuFormat::Section5, true);
+        writer.write_data(&TU[2..], SvtFlags::empty()).unwrap();
+        writer.write_data(&TU, SvtFlags::BUFFERFLAG_HAS_TD).unwrap();
+        assert_eq!(writer.finish().unwrap(), [TU, TU].concat());
+    }
+
+    #[test]
+    fn annexb() {
+        let mut writer = ObuWriter::new(Vec::new(), ObuFormat::AnnexB, true);
+        writer.write_data(&TU, SvtFlags::BUFFERFLAG_HAS_TD).unwrap();
+        let out = writer.finish().unwrap();
+        assert_eq!(
+            out,
+            [
+                14, // temporal_unit_size
+                13, // frame_unit_size
+                1, 0x10, // TD
+                4, 0x08, 0xaa, 0xbb, 0xcc, // sequence header
+                5, 0x30, 0x01, 0x02, 0x03, 0x04, // frame
+            ]
+        );
+    }
+
+    #[test]
+    fn annexb_alt_ref_temporal_unit() {
+        let frame = [0x32, 0x01, 0x05];
+        let mut writer = ObuWriter::new(Vec::new(), ObuFormat::AnnexB, false);
+        writer
+            .write_data(
+                &TU,
+                SvtFlags::BUFFERFLAG_HAS_TD | SvtFlags::BUFFERFLAG_IS_ALT_REF,
+            )
+            .unwrap();
+        writer.write_data(&frame, SvtFlags::empty()).unwrap();
+        let out = writer.finish().unwrap();
+        assert_eq!(
+            out,
+            [
+                16, // temporal_unit_size
+                11, // first frame_unit_size
+                4, 0x08, 0xaa, 0xbb, 0xcc, // sequence header
+                5, 0x30, 0x01, 0x02, 0x03, 0x04, // ALT-REF frame
+                3,    // second frame_unit_size
+                2, 0x30, 0x05, // shown frame
+            ]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Encoding with frames sent and packets received at the same time.
+//!
+//! [`EncoderPipeline`] owns encoder and runs two worker threads. Send worker takes [`OwnedFrame`]s
+//! from bounded channel and passes them to [`send_picture`], receive worker waits in [`get_packet`]
+//! and pushes [`OwnedPacket`]s to another bounded channel. When either channel is full, its producer
+//! blocks, so slow consumer doesn't make encoder buffer frames or packets without limit.
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::{pipeline::EncoderPipeline, pool::FramePool, SvtAv1EncoderConfig};
+//! let cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
+//! let pool = FramePool::from_config(&cfg.config);
+//! let mut pipeline = EncoderPipeline::new(cfg.into_encoder().unwrap(), 4);
+//! let frames = pipeline.frame_sender().unwrap();
+//! std::thread::spawn(move || {
+//!     for _ in 0..100 {
+//!         frames.send(pool.get()).unwrap();
+//!     }
+//!     // EOS is sent when last sender is dropped
+//! });
+//! pipeline.send_eos();
+//! for packet in pipeline.iter() {
+//!     let packet = packet.unwrap();
+//!     // write packet
+//! }
+//! ```
+//!
+//! [`send_picture`]: crate::SvtAv1Encoder::send_picture
+//! [`get_packet`]: crate::SvtAv1Encoder::get_packet
+use crate::{ffi::SvtFlags, pool::OwnedFrame, OwnedPacket, Result, SvtAv1Encoder};
+use std::{
+    sync::{
+        mpsc::{self, Receiver, SendError, SyncSender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Encoder shared between send and receive workers, also used by `stream` module
+pub(crate) struct SharedEncoder(pub(crate) SvtAv1Encoder);
+
+// SAFETY: SVT-AV1 passes input pictures and output packets through separate internal
+// FIFOs guarded by its own semaphores, so `svt_av1_enc_send_picture` and
+// `svt_av1_enc_get_packet` may run concurrently. Each worker calls only one of them
+// (send worker also calls `send_eos`, which is `send_picture` with EOS flag), and encoder
+// is destroyed only after both workers are joined and last reference is dropped.
+unsafe impl Sync for SharedEncoder {}
+
+/// Sender of frames to [`EncoderPipeline`]. Can be moved to producer thread.
+/// End of stream is signaled to encoder, when all senders are dropped
+pub type FrameSender = SyncSender<OwnedFrame>;
+
+/// Encoder running on worker threads, see [module documentation](self).
+///
+/// Dropping pipeline closes input and waits until encoder reaches end of stream, discarding remaining packets.
+/// Senders from [`frame_sender`] must be dropped before, otherwise drop waits for them.
+///
+/// [`frame_sender`]: method@Self::frame_sender
+#[derive(Debug)]
+pub struct EncoderPipeline {
+    frames: Option<FrameSender>,
+    packets: Option<Receiver<Result<OwnedPacket>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl EncoderPipeline {
+    /// Start workers. `capacity` is the number of frames and packets, which can wait in channels
+    pub fn new(encoder: SvtAv1Encoder, capacity: usize) -> Self {
+        let encoder = Arc::new(SharedEncoder(encoder));
+        let (frames, frame_rx) = mpsc::sync_channel::<OwnedFrame>(capacity);
+        let (packet_tx, packets) = mpsc::sync_channel(capacity);
+
+        let send_encoder = Arc::clone(&encoder);
+        let send_errors = packet_tx.clone();
+        let send = thread::spawn(move || {
+            let encoder = &send_encoder.0;
+            for frame in frame_rx {
+                let res = encoder.send_picture(frame.as_frame(), frame.pts, frame.force_keyframe);
+                if let Err(err) = res {
+                    let _ = send_errors.send(Err(err));
+                    break;
+                }
+            }
+            // Receive worker waits for EOS packet, so EOS is sent even after error
+            if let Err(err) = encoder.send_eos() {
+                let _ = send_errors.send(Err(err));
+            }
+        });
+
+        let receive = thread::spawn(move || {
+            let encoder = &encoder.0;
+            loop {
+                match encoder.get_packet(1) {
+                    Ok(packet) => {
+                        let eos = packet.flags.contains(SvtFlags::BUFFERFLAG_EOS);
+                        // Pipeline may be dropped, then packets are drained without sending
+                        let _ = packet_tx.send(Ok(packet.into()));
+                        if eos {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        let _ = packet_tx.send(Err(err));
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            frames: Some(frames),
+            packets: Some(packets),
+            workers: vec![send, receive],
+        }
+    }
+
+    /// Send frame, blocks when channel is full. Returns frame back if input is already closed
+    /// with [`send_eos`] or send worker stopped on error
+    ///
+    /// [`send_eos`]: method@Self::send_eos
+    pub fn send(&self, frame: OwnedFrame) -> std::result::Result<(), SendError<OwnedFrame>> {
+        match &self.frames {
+            Some(frames) => frames.send(frame),
+            None => Err(SendError(frame)),
+        }
+    }
+
+    /// Clone of frame sender for producer thread, `None` after [`send_eos`]
+    ///
+    /// [`send_eos`]: method@Self::send_eos
+    pub fn frame_sender(&self) -> Option<FrameSender> {
+        self.frames.clone()
+    }
+
+    /// Close input. Encoder gets end of stream after all frames sent before
+    /// and after all senders from [`frame_sender`] are dropped
+    ///
+    /// [`frame_sender`]: method@Self::frame_sender
+    pub fn send_eos(&mut self) {
+        self.frames = None;
+    }
+
+    /// Receive next packet, blocks until one is ready. `None` after packet with EOS flag
+    /// or error was received
+    pub fn recv(&self) -> Option<Result<OwnedPacket>> {
+        self.packets.as_ref()?.recv().ok()
+    }
+
+    /// Receive packet if one is ready
+    pub fn try_recv(&self) -> Option<Result<OwnedPacket>> {
+        self.packets.as_ref()?.try_recv().ok()
+    }
+
+    /// Blocking iterator over remaining packets
+    pub fn iter(&self) -> impl Iterator<Item = Result<OwnedPacket>> + '_ {
+        std::iter::from_fn(|| self.recv())
+    }
+}
+
+impl Drop for EncoderPipeline {
+    fn drop(&mut self) {
+        // Closing input makes send worker send EOS, closing output makes receive worker
+        // drain remaining packets without blocking on full channel
+        self.frames = None;
+        self.packets = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Owned frame buffers and pool for reusing them.
+//!
+//! [`OwnedFrame`] holds planes in one allocation with each plane aligned to [`PLANE_ALIGN`] bytes
+//! and rows padded to stride alignment. [`FramePool`] hands out frames and takes their buffers back
+//! when frames are dropped, so producer doesn't allocate per frame. Reused frames keep content
+//! of the previous frame, unless taken with [`FramePool::get_zeroed`].
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::{pool::FramePool, SvtAv1EncoderConfig};
+//! let cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
+//! let pool = FramePool::from_config(&cfg.config);
+//! let encoder = cfg.into_encoder().unwrap();
+//! for _ in 0..100 {
+//!     let mut frame = pool.get();
+//!     frame.plane_mut(0).fill(128);
+//!     encoder.send_picture(frame.as_frame(), None, false).unwrap();
+//!     // frame buffer goes back to pool here
+//! }
+//! ```
+use crate::{ffi::SvtAv1EncConfiguration, yuv::PlanarLayout, Frame};
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex, Weak},
+};
+
+/// Alignment of plane start in bytes
+pub const PLANE_ALIGN: usize = 64;
+/// Default alignment of rows in bytes
+pub const DEFAULT_STRIDE_ALIGN: usize = 64;
+
+type FreeList = Mutex<Vec<Vec<u8>>>;
+
+/// Frame with owned planes. Obtain it from [`FramePool::get`] to reuse buffers,
+/// or create standalone one with [`new`].
+///
+/// [`new`]: method@Self::new
+#[derive(Debug)]
+pub struct OwnedFrame {
+    /// Presentation timestamp passed to encoder, used by [`EncoderPipeline`]
+    ///
+    /// [`EncoderPipeline`]: crate::pipeline::EncoderPipeline
+    pub pts: Option<i64>,
+    /// Request key frame, used by [`EncoderPipeline`]
+    ///
+    /// [`EncoderPipeline`]: crate::pipeline::EncoderPipeline
+    pub force_keyframe: bool,
+    layout: PlanarLayout,
+    data: Vec<u8>,
+    /// Offsets of Y, Cb and Cr planes in `data`
+    offsets: [usize; 3],
+    /// Strides in bytes
+    strides: [usize; 3],
+    pool: Weak<FreeList>,
+}
+
+impl OwnedFrame {
+    /// Allocate zeroed frame, rows are padded to multiple of `stride_align` bytes
+    pub fn new(layout: PlanarLayout, stride_align: usize) -> Self {
+        Self::with_buffer(layout, stride_align, Vec::new(), true, Weak::new())
+    }
+
+    /// Lay out planes in `data`, which is zeroed if `zero` is set or it has different size
+    fn with_buffer(
+        layout: PlanarLayout,
+        stride_align: usize,
+        mut data: Vec<u8>,
+        zero: bool,
+        pool: Weak<FreeList>,
+    ) -> Self {
+        let (chroma_width, chroma_height) = layout.chroma_size();
+        let sample_size = layout.sample_size();
+        let luma_stride = (layout.width as usize * sample_size).next_multiple_of(stride_align);
+        let chroma_stride = (chroma_width as usize * sample_size).next_multiple_of(stride_align);
+        let sizes = [
+            luma_stride * layout.height as usize,
+            chroma_stride * chroma_height as usize,
+            chroma_stride * chroma_height as usize,
+        ];
+
+        // Vec doesn't guarantee alignment, so allocate more and skip to aligned address
+        let total = sizes
+            .iter()
+            .map(|size| size.next_multiple_of(PLANE_ALIGN))
+            .sum::<usize>()
+            + PLANE_ALIGN;
+        if zero || data.len() != total {
+            data.clear();
+        }
+        data.resize(total, 0);
+        let mut start = data.as_ptr().align_offset(PLANE_ALIGN);
+        let offsets = sizes.map(|size| {
+            let offset = start;
+            start += size.next_multiple_of(PLANE_ALIGN);
+            offset
+        });
+
+        Self {
+            pts: None,
+            force_keyframe: false,
+            layout,
+            data,
+            offsets,
+            strides: [luma_stride, chroma_stride, chroma_stride],
+            pool,
+        }
+    }
+
+    pub fn layout(&self) -> &PlanarLayout {
+        &self.layout
+    }
+
+    fn plane_range(&self, plane: usize) -> Range<usize> {
+        let rows = match plane {
+            0 => self.layout.height,
+            _ => self.layout.chroma_size().1,
+        } as usize;
+        self.offsets[plane]..self.offsets[plane] + rows * self.strides[plane]
+    }
+
+    /// Stride of plane (0 is Y, 1 is Cb, 2 is Cr) in bytes
+    pub fn stride(&self, plane: usize) -> usize {
+        self.strides[plane]
+    }
+
+    /// Plane data, including row padding
+    pub fn plane(&self, plane: usize) -> &[u8] {
+        &self.data[self.plane_range(plane)]
+    }
+
+    /// Mutable plane data, including row padding
+    pub fn plane_mut(&mut self, plane: usize) -> &mut [u8] {
+        let range = self.plane_range(plane);
+        &mut self.data[range]
+    }
+
+    /// Mutable row of plane, without padding
+    pub fn row_mut(&mut self, plane: usize, row: usize) -> &mut [u8] {
+        let (chroma_width, _) = self.layout.chroma_size();
+        let width = match plane {
+            0 => self.layout.width,
+            _ => chroma_width,
+        } as usize;
+        let len = width * self.layout.sample_size();
+        let start = self.offsets[plane] + row * self.strides[plane];
+        &mut self.data[start..start + len]
+    }
+
+    /// Copy planes from tightly packed planar buffer, as returned by [`PlanarLayout::frame`]
+    pub fn copy_from_planar(&mut self, buffer: &[u8]) {
+        assert_eq!(buffer.len(), self.layout.frame_size());
+        let (chroma_width, chroma_height) = self.layout.chroma_size();
+        let sample_size = self.layout.sample_size();
+        let mut src = buffer;
+        for plane in 0..3 {
+            let (width, height) = match plane {
+                0 => (self.layout.width, self.layout.height),
+                _ => (chroma_width, chroma_height),
+            };
+            let len = width as usize * sample_size;
+            for row in 0..height as usize {
+                let (data, rest) = src.split_at(len);
+                self.row_mut(plane, row).copy_from_slice(data);
+                src = rest;
+            }
+        }
+    }
+
+    /// Borrow planes as [`Frame`] for [`send_picture`]
+    ///
+    /// [`send_picture`]: crate::SvtAv1Encoder::send_picture
+    pub fn as_frame(&self) -> Frame<'_> {
+        let size = (0..3)
+            .map(|plane| self.plane_range(plane).len())
+            .sum::<usize>();
+        let sample_size = self.layout.sample_size();
+        Frame::new(
+            self.plane(0),
+            self.plane(1),
+            self.plane(2),
+            (self.strides[0] / sample_size) as u32,
+            (self.strides[1] / sample_size) as u32,
+            (self.strides[2] / sample_size) as u32,
+            size as u32,
+        )
+    }
+}
+
+impl Drop for OwnedFrame {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.upgrade() {
+            let data = std::mem::take(&mut self.data);
+            pool.lock().unwrap().push(data);
+        }
+    }
+}
+
+/// Pool of frame buffers with the same layout. Cloned pools share buffers
+#[derive(Debug, Clone)]
+pub struct FramePool {
+    layout: PlanarLayout,
+    stride_align: usize,
+    free: Arc<FreeList>,
+}
+
+impl FramePool {
+    /// Create empty pool of frames with given layout, rows are padded to multiple of `stride_align` bytes
+    pub fn new(layout: PlanarLayout, stride_align: usize) -> Self {
+        assert!(stride_align > 0);
+        Self {
+            layout,
+            stride_align,
+            free: Arc::default(),
+        }
+    }
+
+    /// Create pool for frames matching source size, bit depth and color format of configuration
+    pub fn from_config(config: &SvtAv1EncConfiguration) -> Self {
+        let layout = PlanarLayout {
+            width: config.source_width,
+            height: config.source_height,
+            color_format: config.encoder_color_format,
+            bit_depth: config.encoder_bit_depth,
+        };
+        Self::new(layout, DEFAULT_STRIDE_ALIGN)
+    }
+
+    pub fn layout(&self) -> &PlanarLayout {
+        &self.layout
+    }
+
+    /// Get frame, reusing returned buffer if there is one. Reused frame keeps content of the
+    /// frame it was returned from, for callers overwriting whole frame. Pts and key frame request
+    /// are reset
+    pub fn get(&self) -> OwnedFrame {
+        self.take(false)
+    }
+
+    /// Get frame like [`get`], with zeroed content
+    ///
+    /// [`get`]: method@Self::get
+    pub fn get_zeroed(&self) -> OwnedFrame {
+        self.take(true)
+    }
+
+    fn take(&self, zero: bool) -> OwnedFrame {
+        let data = self.free.lock().unwrap().pop().unwrap_or_default();
+        OwnedFrame::with_buffer(
+            self.layout,
+            self.stride_align,
+            data,
+            zero,
+            Arc::downgrade(&self.free),
+        )
+    }
+
+    /// Number of buffers waiting for reuse
+    pub fn available(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ffi::ColorFormat, tests::test_config};
+
+    #[test]
+    fn layout() {
+        let mut config = test_config(100, 50);
+        config.encoder_bit_depth = 10;
+        let pool = FramePool::from_config(&config);
+        let mut frame = pool.get();
+        assert_eq!(frame.stride(0), 256);
+        assert_eq!(frame.stride(1), 128);
+        for plane in 0..3 {
+            assert_eq!(frame.plane(plane).as_ptr() as usize % PLANE_ALIGN, 0);
+        }
+        assert_eq!(frame.plane(0).len(), 256 * 50);
+        assert_eq!(frame.plane(2).len(), 128 * 25);
+        assert_eq!(frame.row_mut(1, 24).len(), 100);
+
+        let raw = frame.as_frame();
+        assert_eq!((raw.y_stride, raw.cb_stride, raw.cr_stride), (128, 64, 64));
+        assert_eq!(raw.size as usize, 256 * 50 + 2 * 128 * 25);
+    }
+
+    #[test]
+    fn copy_from_planar() {
+        let layout = PlanarLayout {
+            width: 3,
+            height: 2,
+            color_format: ColorFormat::YUV420,
+            bit_depth: 8,
+        };
+        let mut frame = OwnedFrame::new(layout, 4);
+        frame.copy_from_planar(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(frame.plane(0), [1, 2, 3, 0, 4, 5, 6, 0]);
+        assert_eq!(frame.plane(1), [7, 8, 0, 0]);
+        assert_eq!(frame.plane(2), [9, 10, 0, 0]);
+    }
+
+    #[test]
+    fn reuse() {
+        let pool = FramePool::from_config(&test_config(64, 64));
+        let mut frame = pool.get();
+        frame.plane_mut(0).fill(1);
+        let ptr = frame.plane(0).as_ptr();
+        drop(frame);
+        assert_eq!(pool.available(), 1);
+
+        let mut frame = pool.get();
+        assert_eq!(pool.available(), 0);
+        assert_eq!(frame.plane(0).as_ptr(), ptr);
+        assert!(frame.plane(0).iter().all(|&b| b == 1));
+        frame.pts = Some(1);
+        drop(frame);
+
+        let frame = pool.get_zeroed();
+        assert_eq!(frame.plane(0).as_ptr(), ptr);
+        assert!(frame.plane(0).iter().all(|&b| b == 0));
+        assert_eq!(frame.pts, None);
+
+        // Frame outliving pool just frees its buffer
+        drop(pool);
+        drop(frame);
+    }
+}
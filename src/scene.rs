@@ -0,0 +1,198 @@
+//! Scene change detection deciding key frames before frames reach the encoder.
+//!
+//! [`SceneDetector`] compares luma of consecutive frames downscaled by averaging 8x8 blocks.
+//! Frame starts a new scene when mean absolute difference, relative to maximum sample value,
+//! is above threshold and enough frames passed since the previous key frame. Scenes longer
+//! than maximum interval are split. It's independent of `scene_change_detection` of the encoder.
+//!
+//! Set [`SvtAv1EncoderConfig::set_scene_detection`] to force key frames at detected scene
+//! changes in [`SvtAv1Encoder::send_picture`], or use the detector directly:
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::{pool::FramePool, scene::{SceneDetector, SceneOptions}, SvtAv1EncoderConfig};
+//! let cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
+//! let pool = FramePool::from_config(&cfg.config);
+//! let mut detector = SceneDetector::from_config(&cfg.config, SceneOptions::default());
+//! let encoder = cfg.into_encoder().unwrap();
+//! for pts in 0..100 {
+//!     let frame = pool.get();
+//!     let keyframe = detector.push(&frame.as_frame(), false);
+//!     encoder.send_picture(frame.as_frame(), Some(pts), keyframe).unwrap();
+//! }
+//! ```
+//!
+//! [`SvtAv1EncoderConfig::set_scene_detection`]: crate::SvtAv1EncoderConfig::set_scene_detection
+//! [`SvtAv1Encoder::send_picture`]: crate::SvtAv1Encoder::send_picture
+use crate::{ffi::SvtAv1EncConfiguration, yuv::PlanarLayout, Frame};
+
+/// Size of blocks averaged into one sample of downscaled luma
+const SCALE: usize = 8;
+
+/// Thresholds of [`SceneDetector`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneOptions {
+    /// Mean absolute difference of downscaled luma between frames, relative to maximum sample value,
+    /// above which scene changes
+    pub threshold: f64,
+    /// Minimum distance between key frames, scene changes closer to previous key frame are ignored
+    pub min_interval: u64,
+    /// Maximum distance between key frames, longer scenes are split
+    pub max_interval: u64,
+}
+
+impl Default for SceneOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.12,
+            min_interval: 24,
+            max_interval: 240,
+        }
+    }
+}
+
+/// Key frame decisions from frame content, see [module](self) documentation
+#[derive(Debug, Clone)]
+pub struct SceneDetector {
+    layout: PlanarLayout,
+    options: SceneOptions,
+    previous: Option<Vec<u32>>,
+    /// Frames since the last key frame
+    distance: u64,
+    score: f64,
+}
+
+impl SceneDetector {
+    pub fn new(layout: PlanarLayout, options: SceneOptions) -> Self {
+        Self {
+            layout,
+            options,
+            previous: None,
+            distance: 0,
+            score: 0.0,
+        }
+    }
+
+    /// Detect scene changes in frames of source size, bit depth and color format of configuration
+    pub fn from_config(config: &SvtAv1EncConfiguration, options: SceneOptions) -> Self {
+        Self::new(
+            PlanarLayout {
+                width: config.source_width,
+                height: config.source_height,
+                color_format: config.encoder_color_format,
+                bit_depth: config.encoder_bit_depth,
+            },
+            options,
+        )
+    }
+
+    pub fn options(&self) -> &SceneOptions {
+        &self.options
+    }
+
+    /// Difference of the last frame to the one before it, compared with threshold
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Analyze next frame in presentation order and return whether it should be key frame.
+    /// The first frame always is. `forced` marks key frame placed by caller, from which
+    /// the intervals are counted
+    pub fn push(&mut self, frame: &Frame<'_>, forced: bool) -> bool {
+        let current = self.thumbnail(frame);
+        self.distance += 1;
+        let keyframe = match &self.previous {
+            None => {
+                self.score = 0.0;
+                true
+            }
+            Some(previous) => {
+                let max_value = ((1u32 << self.layout.bit_depth) - 1) as f64;
+                let difference = previous
+                    .iter()
+                    .zip(&current)
+                    .map(|(&a, &b)| a.abs_diff(b) as u64)
+                    .sum::<u64>();
+                self.score = difference as f64 / current.len() as f64 / max_value;
+                forced
+                    || self.distance >= self.options.max_interval
+                    || (self.score > self.options.threshold
+                        && self.distance >= self.options.min_interval)
+            }
+        };
+        if keyframe {
+            self.distance = 0;
+        }
+        self.previous = Some(current);
+        keyframe
+    }
+
+    /// Luma downscaled by averaging blocks
+    fn thumbnail(&self, frame: &Frame<'_>) -> Vec<u32> {
+        let (width, height) = (self.layout.width as usize, self.layout.height as usize);
+        let stride = frame.y_stride as usize;
+        let high_bit_depth = self.layout.bit_depth > 8;
+        let sample = |x: usize, y: usize| -> u32 {
+            let i = y * stride + x;
+            if high_bit_depth {
+                u16::from_le_bytes([frame.luma[2 * i], frame.luma[2 * i + 1]]) as u32
+            } else {
+                frame.luma[i] as u32
+            }
+        };
+        let (columns, rows) = (width.div_ceil(SCALE), height.div_ceil(SCALE));
+        let mut thumbnail = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x0, y0) = (column * SCALE, row * SCALE);
+                let (x1, y1) = ((x0 + SCALE).min(width), (y0 + SCALE).min(height));
+                let sum = (y0..y1)
+                    .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                    .map(|(x, y)| sample(x, y))
+                    .sum::<u32>();
+                thumbnail.push(sum / ((x1 - x0) * (y1 - y0)) as u32);
+            }
+        }
+        thumbnail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::ColorFormat;
+
+    #[test]
+    fn keyframe_decisions() {
+        let layout = PlanarLayout {
+            width: 20,
+            height: 12,
+            color_format: ColorFormat::YUV420,
+            bit_depth: 10,
+        };
+        let frame = |luma: u16| -> Vec<u8> {
+            let mut buffer = luma.to_le_bytes().repeat(20 * 12);
+            buffer.resize(layout.frame_size(), 0);
+            buffer
+        };
+        let options = SceneOptions {
+            threshold: 0.1,
+            min_interval: 2,
+            max_interval: 4,
+        };
+        let mut detector = SceneDetector::new(layout, options);
+        let keyframes = [100, 100, 100, 900, 100, 100, 100, 100, 100, 100]
+            .into_iter()
+            .map(|luma| detector.push(&layout.frame(&frame(luma)), false))
+            .collect::<Vec<_>>();
+        // Change back to 100 at frame 4 is too close to the key frame at frame 3,
+        // frame 7 is forced by maximum interval
+        assert_eq!(
+            keyframes,
+            [true, false, false, true, false, false, false, true, false, false]
+        );
+        assert!(detector.push(&layout.frame(&frame(100)), true));
+        assert_eq!(detector.score(), 0.0);
+        assert!(!detector.push(&layout.frame(&frame(900)), false));
+        assert!((detector.score() - 800.0 / 1023.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,333 @@
+//! Encoding quality statistics reported by the library.
+//!
+//! With `stat_report` enabled in [`SvtAv1EncConfiguration`], each output packet carries
+//! sum of squared errors (SSE) and SSIM of reconstructed frame per plane, see [`PacketStats`].
+//! [`QualityStats`] turns them into MSE and PSNR, keeps them per frame and averages them over
+//! sequence. It's displayed in the layout of SvtAv1EncApp stat report.
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::{stats::QualityStats, SvtAv1EncoderConfig};
+//! let mut cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
+//! cfg.config.stat_report = 1;
+//! let mut stats = QualityStats::from_config(&cfg.config);
+//! let encoder = cfg.into_encoder().unwrap();
+//! // send frames
+//! encoder.send_eos().unwrap();
+//! for packet in encoder.drain() {
+//!     stats.add_packet(&packet.unwrap());
+//! }
+//! print!("{stats}");
+//! ```
+//!
+//! [`SvtAv1EncConfiguration`]: crate::ffi::SvtAv1EncConfiguration
+use crate::{
+    ffi::{BufferHeaderType, SvtAv1EncConfiguration},
+    yuv::PlanarLayout,
+    OwnedPacket, Packet,
+};
+use std::fmt;
+
+/// Quality of reconstructed frame as reported by the library in output buffer.
+/// SSE and SSIM are zero, unless `stat_report` is enabled
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PacketStats {
+    /// Quantizer of the frame
+    pub qp: u32,
+    /// Sum of squared errors of luma plane, in samples of encoder bit depth
+    pub luma_sse: u64,
+    pub cb_sse: u64,
+    pub cr_sse: u64,
+    pub luma_ssim: f64,
+    pub cb_ssim: f64,
+    pub cr_ssim: f64,
+}
+
+impl PacketStats {
+    pub(crate) fn from_buffer(buffer: &BufferHeaderType) -> Self {
+        Self {
+            qp: buffer.qp,
+            luma_sse: buffer.luma_sse,
+            cb_sse: buffer.cb_sse,
+            cr_sse: buffer.cr_sse,
+            luma_ssim: buffer.luma_ssim,
+            cb_ssim: buffer.cb_ssim,
+            cr_ssim: buffer.cr_ssim,
+        }
+    }
+
+    /// SSE of Y, U and V planes
+    pub fn sse(&self) -> [u64; 3] {
+        [self.luma_sse, self.cb_sse, self.cr_sse]
+    }
+
+    /// SSIM of Y, U and V planes
+    pub fn ssim(&self) -> [f64; 3] {
+        [self.luma_ssim, self.cb_ssim, self.cr_ssim]
+    }
+}
+
+/// Quality of one frame, planes are in Y, U, V order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameQuality {
+    pub pts: i64,
+    pub qp: u32,
+    /// Size of the packet in bytes
+    pub bytes: usize,
+    pub sse: [u64; 3],
+    pub mse: [f64; 3],
+    /// PSNR in dB
+    pub psnr: [f64; 3],
+    pub ssim: [f64; 3],
+}
+
+/// Averages over all frames, planes are in Y, U, V order
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub frames: usize,
+    pub average_qp: f64,
+    /// Arithmetic mean of per-frame PSNR
+    pub average_psnr: [f64; 3],
+    /// PSNR of mean per-frame MSE
+    pub overall_psnr: [f64; 3],
+    pub average_ssim: [f64; 3],
+    pub bitrate_kbps: f64,
+}
+
+/// Accumulator of per-frame quality, see [module](self) documentation
+#[derive(Debug, Clone)]
+pub struct QualityStats {
+    layout: PlanarLayout,
+    frame_rate: f64,
+    frames: Vec<FrameQuality>,
+}
+
+impl QualityStats {
+    /// Statistics of frames with given size, chroma subsampling and bit depth.
+    /// Frame rate is used for bitrate only
+    pub fn new(layout: PlanarLayout, frame_rate: f64) -> Self {
+        Self {
+            layout,
+            frame_rate,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Statistics of frames encoded with config
+    pub fn from_config(config: &SvtAv1EncConfiguration) -> Self {
+        let layout = PlanarLayout {
+            width: config.source_width,
+            height: config.source_height,
+            color_format: config.encoder_color_format,
+            bit_depth: config.encoder_bit_depth,
+        };
+        let frame_rate =
+            config.frame_rate_numerator as f64 / config.frame_rate_denominator.max(1) as f64;
+        Self::new(layout, frame_rate)
+    }
+
+    /// Add frame from library output, returns its quality.
+    /// Packets without data, like the final EOS one, don't contain frame and are skipped
+    pub fn add_packet(&mut self, packet: &Packet) -> Option<&FrameQuality> {
+        self.add(packet.pts, packet.len(), &packet.stats)
+    }
+
+    /// Same as [`add_packet`] for owned packet
+    ///
+    /// [`add_packet`]: method@Self::add_packet
+    pub fn add_owned_packet(&mut self, packet: &OwnedPacket) -> Option<&FrameQuality> {
+        self.add(packet.pts, packet.len(), &packet.stats)
+    }
+
+    /// Add frame with packet of `bytes` size, returns its quality. Empty packets are skipped
+    pub fn add(&mut self, pts: i64, bytes: usize, stats: &PacketStats) -> Option<&FrameQuality> {
+        if bytes == 0 {
+            return None;
+        }
+        let samples = self.plane_samples();
+        let sse = stats.sse();
+        let mse = std::array::from_fn(|i| mse(sse[i], samples[i]));
+        let psnr = std::array::from_fn(|i| psnr(sse[i] as f64, samples[i], self.max_value()));
+        self.frames.push(FrameQuality {
+            pts,
+            qp: stats.qp,
+            bytes,
+            sse,
+            mse,
+            psnr,
+            ssim: stats.ssim(),
+        });
+        self.frames.last()
+    }
+
+    /// Quality of added frames in output order
+    pub fn frames(&self) -> &[FrameQuality] {
+        &self.frames
+    }
+
+    /// Averages over added frames, all zero without frames
+    pub fn summary(&self) -> Summary {
+        let count = self.frames.len();
+        if count == 0 {
+            return Summary::default();
+        }
+        let n = count as f64;
+        let mean =
+            |value: &dyn Fn(&FrameQuality) -> f64| self.frames.iter().map(value).sum::<f64>() / n;
+        let samples = self.plane_samples();
+        let bytes = self.frames.iter().map(|frame| frame.bytes).sum::<usize>();
+        Summary {
+            frames: count,
+            average_qp: mean(&|frame| frame.qp as f64),
+            average_psnr: std::array::from_fn(|i| mean(&|frame| frame.psnr[i])),
+            overall_psnr: std::array::from_fn(|i| {
+                let sse = mean(&|frame| frame.sse[i] as f64);
+                psnr(sse, samples[i], self.max_value())
+            }),
+            average_ssim: std::array::from_fn(|i| mean(&|frame| frame.ssim[i])),
+            bitrate_kbps: bytes as f64 * 8.0 * self.frame_rate / n / 1000.0,
+        }
+    }
+
+    fn plane_samples(&self) -> [u64; 3] {
+        let (chroma_width, chroma_height) = self.layout.chroma_size();
+        let chroma = chroma_width as u64 * chroma_height as u64;
+        [
+            self.layout.width as u64 * self.layout.height as u64,
+            chroma,
+            chroma,
+        ]
+    }
+
+    fn max_value(&self) -> f64 {
+        ((1u32 << self.layout.bit_depth) - 1) as f64
+    }
+}
+
+fn mse(sse: u64, samples: u64) -> f64 {
+    if samples == 0 {
+        0.0
+    } else {
+        sse as f64 / samples as f64
+    }
+}
+
+/// PSNR of plane, error of 0.1 is used for lossless plane like SvtAv1EncApp does.
+/// Absent (monochrome chroma) plane has zero PSNR
+pub(crate) fn psnr(sse: f64, samples: u64, max_value: f64) -> f64 {
+    if samples == 0 {
+        return 0.0;
+    }
+    let peak = max_value * max_value * samples as f64;
+    let sse = if sse == 0.0 { 0.1 } else { sse };
+    10.0 * (peak / sse).log10()
+}
+
+impl fmt::Display for FrameQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [psnr_y, psnr_u, psnr_v] = self.psnr;
+        let [mse_y, mse_u, mse_v] = self.mse;
+        let [ssim_y, ssim_u, ssim_v] = self.ssim;
+        write!(
+            f,
+            "Picture Number: {:4}\t QP: {:4}  [ PSNR-Y: {psnr_y:.2} dB,\tPSNR-U: {psnr_u:.2} dB,\tPSNR-V: {psnr_v:.2} dB,\t\
+             MSE-Y: {mse_y:.2},\tMSE-U: {mse_u:.2},\tMSE-V: {mse_v:.2},\t\
+             SSIM-Y: {ssim_y:.5},\tSSIM-U: {ssim_u:.5},\tSSIM-V: {ssim_v:.5} ]\t {:6} bytes",
+            self.pts, self.qp, self.bytes
+        )
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [average_y, average_u, average_v] = self.average_psnr;
+        let [overall_y, overall_u, overall_v] = self.overall_psnr;
+        let [ssim_y, ssim_u, ssim_v] = self.average_ssim;
+        writeln!(
+            f,
+            "\nSUMMARY ---------------------------------------------------------------------"
+        )?;
+        writeln!(
+            f,
+            "\n\t\t\t\tAverage PSNR (using per-frame PSNR)\t\t|\tOverall PSNR (using per-frame MSE)\t\t|\tAverage SSIM"
+        )?;
+        writeln!(
+            f,
+            "Total Frames\tAverage QP  \tY-PSNR   \tU-PSNR   \tV-PSNR\t\t| \tY-PSNR   \tU-PSNR   \tV-PSNR   \t|\tY-SSIM   \tU-SSIM   \tV-SSIM   \t|\tBitrate"
+        )?;
+        writeln!(
+            f,
+            "{:10}  \t   {:2.2}    \t{average_y:3.2} dB\t{average_u:3.2} dB\t{average_v:3.2} dB  \t|\t\
+             {overall_y:3.2} dB\t{overall_u:3.2} dB\t{overall_v:3.2} dB \t|\t\
+             {ssim_y:1.5} \t{ssim_u:1.5} \t{ssim_v:1.5}\t\t|\t{:.2} kbps",
+            self.frames, self.average_qp, self.bitrate_kbps
+        )
+    }
+}
+
+/// Per-frame lines followed by summary, like SvtAv1EncApp stat file
+impl fmt::Display for QualityStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for frame in &self.frames {
+            writeln!(f, "{frame}")?;
+        }
+        self.summary().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::ColorFormat;
+
+    #[test]
+    fn psnr_and_averages() {
+        let layout = PlanarLayout {
+            width: 4,
+            height: 4,
+            color_format: ColorFormat::YUV420,
+            bit_depth: 8,
+        };
+        let mut stats = QualityStats::new(layout, 30.0);
+        let frame = |luma_sse, qp| PacketStats {
+            qp,
+            luma_sse,
+            cb_sse: 0,
+            cr_sse: 4,
+            luma_ssim: 0.5,
+            cb_ssim: 1.0,
+            cr_ssim: 1.0,
+        };
+        assert!(stats.add(0, 0, &frame(0, 0)).is_none());
+        let first = *stats.add(0, 100, &frame(16, 20)).unwrap();
+        // MSE 1 over 16 samples
+        assert_eq!(first.mse, [1.0, 0.0, 1.0]);
+        assert!((first.psnr[0] - 48.1308).abs() < 1e-4);
+        assert!((first.psnr[1] - 10.0 * (255.0f64 * 255.0 * 4.0 / 0.1).log10()).abs() < 1e-9);
+        let _ = stats.add(1, 300, &frame(16 * 100, 30)).unwrap();
+
+        let summary = stats.summary();
+        assert_eq!(summary.frames, 2);
+        assert_eq!(summary.average_qp, 25.0);
+        assert!(
+            (summary.average_psnr[0] - (first.psnr[0] + first.psnr[0] - 20.0) / 2.0).abs() < 1e-9
+        );
+        // Mean MSE is 50.5
+        assert!((summary.overall_psnr[0] - 10.0 * (255.0f64 * 255.0 / 50.5).log10()).abs() < 1e-9);
+        assert_eq!(summary.average_ssim, [0.5, 1.0, 1.0]);
+        assert!((summary.bitrate_kbps - 48.0).abs() < 1e-9);
+
+        let report = stats.to_string();
+        assert!(report.starts_with("Picture Number:    0\t QP:   20  [ PSNR-Y: 48.13 dB,"));
+        assert!(report.contains("         2  \t   25.00    \t"));
+        assert!(report.trim_end().ends_with("|\t48.00 kbps"));
+
+        let bit_depth_10 = QualityStats::new(
+            PlanarLayout {
+                bit_depth: 10,
+                ..layout
+            },
+            30.0,
+        );
+        assert!(bit_depth_10.max_value() == 1023.0);
+    }
+}
@@ -0,0 +1,277 @@
+//! Async interface, encoder as [`Sink`] of frames and [`Stream`] of packets.
+//!
+//! Blocking SVT-AV1 calls run on two dedicated threads, like in [`EncoderPipeline`], so they don't
+//! stall async runtime. Frames and packets wait in queues limited by capacity, [`FrameSink`]
+//! is not ready while its queue is full and receive thread pauses while packet queue is full.
+//!
+//! Closing or dropping [`FrameSink`] sends end of stream after queued frames. Dropping
+//! [`PacketStream`] cancels encoding: queued frames are discarded, end of stream is sent
+//! and threads drain remaining packets, after which encoder is released. Drop doesn't wait
+//! for threads, so it never blocks the runtime.
+//!
+//! ```no_run
+//! # use futures::{SinkExt, StreamExt};
+//! # use svt_av1_psyex_enc::{pool::FramePool, stream::encoder_stream, SvtAv1EncoderConfig};
+//! # async fn run() {
+//! let cfg = SvtAv1EncoderConfig::new(1920, 1080, None);
+//! let pool = FramePool::from_config(&cfg.config);
+//! let (mut frames, mut packets) = encoder_stream(cfg.into_encoder().unwrap(), 4);
+//! let producer = async move {
+//!     for _ in 0..100 {
+//!         frames.send(pool.get()).await.unwrap();
+//!     }
+//!     frames.close().await.unwrap();
+//! };
+//! let consumer = async move {
+//!     while let Some(packet) = packets.next().await {
+//!         let packet = packet.unwrap();
+//!         // write packet
+//!     }
+//! };
+//! futures::join!(producer, consumer);
+//! # }
+//! ```
+//!
+//! [`EncoderPipeline`]: crate::pipeline::EncoderPipeline
+use crate::{
+    ffi::{ErrorType, SvtFlags},
+    pipeline::SharedEncoder,
+    pool::OwnedFrame,
+    OwnedPacket, Result, SvtAv1Encoder,
+};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+#[derive(Debug, Default)]
+struct State {
+    frames: VecDeque<OwnedFrame>,
+    packets: VecDeque<Result<OwnedPacket>>,
+    /// No more frames will be queued
+    input_closed: bool,
+    /// Packet with EOS flag or error was received
+    output_done: bool,
+    /// Packets are discarded instead of queued
+    stream_dropped: bool,
+    /// Error from `send_picture`, reported by the next sink call
+    send_error: Option<ErrorType>,
+    sink_waker: Option<Waker>,
+    stream_waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    /// Signals workers about new frames, free space for packets or cancellation
+    changed: Condvar,
+    capacity: usize,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+fn wake(waker: &mut Option<Waker>) {
+    if let Some(waker) = waker.take() {
+        waker.wake();
+    }
+}
+
+/// Start encoder threads and return sink for frames and stream of packets.
+/// `capacity` is the number of frames and packets, which can wait in queues
+pub fn encoder_stream(encoder: SvtAv1Encoder, capacity: usize) -> (FrameSink, PacketStream) {
+    assert!(capacity > 0);
+    let shared = Arc::new(Shared {
+        state: Mutex::default(),
+        changed: Condvar::new(),
+        capacity,
+    });
+    let encoder = Arc::new(SharedEncoder(encoder));
+
+    let send_shared = Arc::clone(&shared);
+    let send_encoder = Arc::clone(&encoder);
+    let _ = thread::spawn(move || send_worker(&send_shared, &send_encoder.0));
+    let receive_shared = Arc::clone(&shared);
+    let _ = thread::spawn(move || receive_worker(&receive_shared, &encoder.0));
+
+    (
+        FrameSink {
+            shared: Arc::clone(&shared),
+        },
+        PacketStream { shared },
+    )
+}
+
+fn send_worker(shared: &Shared, encoder: &SvtAv1Encoder) {
+    loop {
+        let frame = {
+            let mut state = shared.lock();
+            while state.frames.is_empty() && !state.input_closed {
+                state = shared.changed.wait(state).unwrap();
+            }
+            let Some(frame) = state.frames.pop_front() else {
+                break;
+            };
+            wake(&mut state.sink_waker);
+            frame
+        };
+
+        let res = encoder.send_picture(frame.as_frame(), frame.pts, frame.force_keyframe);
+        if let Err(err) = res {
+            let mut state = shared.lock();
+            state.send_error = Some(err);
+            state.input_closed = true;
+            state.frames.clear();
+            wake(&mut state.sink_waker);
+            break;
+        }
+    }
+    // Receive worker waits for EOS packet, so EOS is sent even after error
+    if let Err(err) = encoder.send_eos() {
+        let mut state = shared.lock();
+        state.send_error = Some(err);
+        wake(&mut state.sink_waker);
+    }
+}
+
+fn receive_worker(shared: &Shared, encoder: &SvtAv1Encoder) {
+    loop {
+        let (item, done) = match encoder.get_packet(1) {
+            Ok(packet) => {
+                let eos = packet.flags.contains(SvtFlags::BUFFERFLAG_EOS);
+                (Ok(packet.into()), eos)
+            }
+            Err(err) => (Err(err), true),
+        };
+
+        let mut state = shared.lock();
+        while state.packets.len() >= shared.capacity && !state.stream_dropped {
+            state = shared.changed.wait(state).unwrap();
+        }
+        if !state.stream_dropped {
+            state.packets.push_back(item);
+        }
+        state.output_done = done;
+        wake(&mut state.stream_waker);
+        if done {
+            break;
+        }
+    }
+}
+
+/// Sink of frames, created by [`encoder_stream`]. Errors of `send_picture` are reported
+/// by the next call, after that sink is closed
+#[derive(Debug)]
+pub struct FrameSink {
+    shared: Arc<Shared>,
+}
+
+impl FrameSink {
+    fn close_input(&self) {
+        let mut state = self.shared.lock();
+        state.input_closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Sink<OwnedFrame> for FrameSink {
+    type Error = ErrorType;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let mut state = self.shared.lock();
+        if let Some(err) = state.send_error.take() {
+            return Poll::Ready(Err(err));
+        }
+        if state.input_closed {
+            return Poll::Ready(Err(ErrorType::NoErrorFifoShutdown));
+        }
+        if state.frames.len() < self.shared.capacity {
+            Poll::Ready(Ok(()))
+        } else {
+            state.sink_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, frame: OwnedFrame) -> Result<()> {
+        let mut state = self.shared.lock();
+        if state.input_closed {
+            return Err(state
+                .send_error
+                .take()
+                .unwrap_or(ErrorType::NoErrorFifoShutdown));
+        }
+        state.frames.push_back(frame);
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    /// Ready when all queued frames were passed to encoder
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let mut state = self.shared.lock();
+        if let Some(err) = state.send_error.take() {
+            return Poll::Ready(Err(err));
+        }
+        if state.frames.is_empty() {
+            Poll::Ready(Ok(()))
+        } else {
+            state.sink_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Flush queued frames and send end of stream
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.close_input();
+        self.poll_flush(cx)
+    }
+}
+
+impl Drop for FrameSink {
+    fn drop(&mut self) {
+        self.close_input();
+    }
+}
+
+/// Stream of packets, created by [`encoder_stream`]. Ends after packet with EOS flag or error
+#[derive(Debug)]
+pub struct PacketStream {
+    shared: Arc<Shared>,
+}
+
+impl Stream for PacketStream {
+    type Item = Result<OwnedPacket>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.shared.lock();
+        if let Some(packet) = state.packets.pop_front() {
+            self.shared.changed.notify_all();
+            Poll::Ready(Some(packet))
+        } else if state.output_done {
+            Poll::Ready(None)
+        } else {
+            state.stream_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for PacketStream {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.stream_dropped = true;
+        state.input_closed = true;
+        state.frames.clear();
+        state.packets.clear();
+        wake(&mut state.sink_waker);
+        self.shared.changed.notify_all();
+    }
+}
@@ -0,0 +1,199 @@
+//! Version of linked SVT-AV1-PSYEX library.
+//!
+//! Bindings in [`ffi`](crate::ffi) match one library version, [`BINDINGS_VERSION`]. With `dynamic`
+//! feature the library found at runtime may differ from the one crate was built against, so
+//! [`SvtAv1EncoderConfig::try_new`] checks version with [`check_version`] before the library
+//! writes to [`SvtAv1EncConfiguration`], whose layout changes between releases, and returns
+//! [`ConfigError::Version`] on mismatch.
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::version;
+//! match version::check_version() {
+//!     Ok(version) => println!("SVT-AV1-PSYEX {version}"),
+//!     Err(err) => eprintln!("{err}"),
+//! }
+//! ```
+//!
+//! [`SvtAv1EncoderConfig::try_new`]: crate::SvtAv1EncoderConfig::try_new
+//! [`ConfigError::Version`]: crate::ConfigError::Version
+//! [`SvtAv1EncConfiguration`]: crate::ffi::SvtAv1EncConfiguration
+use crate::ffi::{svt_av1_get_version, svt_av1_print_version, svt_psy_get_version};
+use std::{
+    ffi::{c_char, CStr},
+    fmt,
+    str::FromStr,
+};
+
+/// Library version the bindings were written for, selected with API version feature
+pub const BINDINGS_VERSION: Version = {
+    let (major, minor, patch, psy) = svt_av1_psyex_sys::LIBRARY_VERSION;
+    Version {
+        major,
+        minor,
+        patch,
+        psy: Some(psy),
+    }
+};
+
+/// Semantic version of SVT-AV1-PSYEX with PSY micro-release letter, like `3.0.2-A`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// Micro-release letter, `None` for upstream SVT-AV1 tags
+    pub psy: Option<char>,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(psy) = self.psy {
+            write!(f, "-{psy}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `3.0.2`, `3.0.2-A` and `git describe` output like `v3.0.2-A-12-gabcdef-dirty`
+impl FromStr for Version {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VersionError::Invalid(s.to_owned());
+        let rest = s.strip_prefix('v').unwrap_or(s);
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (numbers, suffix) = rest.split_at(end);
+        let mut numbers = numbers.split('.').map(str::parse::<u32>);
+        let mut next = || numbers.next().and_then(|n| n.ok()).ok_or_else(invalid);
+        let (major, minor, patch) = (next()?, next()?, next()?);
+        if numbers.next().is_some() {
+            return Err(invalid());
+        }
+
+        // Letter tag is followed by end or by commit count
+        let mut suffix = suffix.chars();
+        let psy = match (suffix.next(), suffix.next(), suffix.next()) {
+            (Some('-'), Some(letter), None | Some('-')) if letter.is_ascii_alphabetic() => {
+                Some(letter)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            psy,
+        })
+    }
+}
+
+/// Error of version query or check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionError {
+    /// Library returned version string, which can't be parsed
+    Invalid(String),
+    /// Linked library differs from [`BINDINGS_VERSION`]
+    Mismatch { found: Version, expected: Version },
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(version) => write!(f, "invalid SVT-AV1-PSYEX version {version:?}"),
+            Self::Mismatch { found, expected } => write!(
+                f,
+                "SVT-AV1-PSYEX {found} is linked, but bindings are written for {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+/// Version string of linked library, `git describe` output like `v3.0.2-A-0-gabcdef`
+pub fn version_string() -> String {
+    // SAFETY: library returns pointer to static null-terminated string
+    unsafe { CStr::from_ptr(svt_av1_get_version()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Version of linked library. Micro-release letter is taken from `svt_psy_get_version`
+pub fn version() -> Result<Version, VersionError> {
+    unsafe { from_library(svt_av1_get_version(), svt_psy_get_version()) }
+}
+
+/// Parse results of `svt_av1_get_version` and `svt_psy_get_version`
+///
+/// # Safety
+/// Both pointers must point to null-terminated strings
+pub(crate) unsafe fn from_library(
+    version: *const c_char,
+    psy: *const c_char,
+) -> Result<Version, VersionError> {
+    let mut version = CStr::from_ptr(version)
+        .to_string_lossy()
+        .parse::<Version>()?;
+    version.psy = CStr::from_ptr(psy)
+        .to_str()
+        .ok()
+        .and_then(|psy| psy.chars().find(char::is_ascii_alphabetic))
+        .or(version.psy);
+    Ok(version)
+}
+
+/// Check that linked library is the version the bindings were written for
+pub fn check_version() -> Result<Version, VersionError> {
+    version().and_then(check)
+}
+
+pub(crate) fn check(found: Version) -> Result<Version, VersionError> {
+    if found == BINDINGS_VERSION {
+        Ok(found)
+    } else {
+        Err(VersionError::Mismatch {
+            found,
+            expected: BINDINGS_VERSION,
+        })
+    }
+}
+
+/// Print version header and build information to file from `SVT_LOG_FILE` environment
+/// variable or to stderr
+pub fn print_version() {
+    unsafe { svt_av1_print_version() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let version = "v3.0.2-A-12-gabcdef-dirty".parse::<Version>().unwrap();
+        assert_eq!(version, BINDINGS_VERSION);
+        assert_eq!(version.to_string(), "3.0.2-A");
+        assert_eq!("3.0.2-A".parse(), Ok(BINDINGS_VERSION));
+
+        let upstream = "v3.1.0-5-g0123456".parse::<Version>().unwrap();
+        assert_eq!(
+            upstream,
+            Version {
+                major: 3,
+                minor: 1,
+                patch: 0,
+                psy: None
+            }
+        );
+        assert!(upstream > BINDINGS_VERSION);
+        assert_eq!(
+            "3.0".parse::<Version>(),
+            Err(VersionError::Invalid("3.0".into()))
+        );
+        assert!("3.0.2.1".parse::<Version>().is_err());
+        assert!("unknown".parse::<Version>().is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! YUV4MPEG2 input.
+//!
+//! [`Y4mReader`] parses stream header into [`Y4mHeader`], which can be applied to encoder configuration,
+//! and reads frames as [`Frame`]s ready for [`send_picture`].
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::y4m::Y4mReader;
+//! let file = std::io::BufReader::new(std::fs::File::open("input.y4m").unwrap());
+//! let mut reader = Y4mReader::new(file).unwrap();
+//! let encoder = reader.header().encoder_config(None).unwrap().into_encoder().unwrap();
+//! while let Some(frame) = reader.read_frame().unwrap() {
+//!     encoder.send_picture(frame, None, false).unwrap();
+//! }
+//! encoder.send_eos().unwrap();
+//! ```
+//!
+//! [`send_picture`]: crate::SvtAv1Encoder::send_picture
+use crate::{
+    ffi::{ChromaSamplePosition, ColorFormat, ColorRange, SvtAv1EncConfiguration},
+    yuv::PlanarLayout,
+    ConfigError, Frame, SvtAv1EncoderConfig,
+};
+use std::{
+    fmt,
+    io::{self, BufRead},
+};
+
+const SIGNATURE: &[u8] = b"YUV4MPEG2";
+const FRAME: &[u8] = b"FRAME";
+/// Limit for header and frame header lines, to not read whole input on garbage
+const MAX_LINE: usize = 1024;
+
+/// Y4M reading error
+#[derive(Debug)]
+pub enum Y4mError {
+    Io(io::Error),
+    /// Stream doesn't start with `YUV4MPEG2` signature
+    InvalidSignature,
+    /// Header is missing required field or has invalid value
+    InvalidHeader(String),
+    /// Colorspace or interlacing mode, which can't be encoded
+    Unsupported(String),
+    /// Frame doesn't start with `FRAME` marker
+    InvalidFrameHeader,
+}
+
+impl fmt::Display for Y4mError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::InvalidSignature => write!(f, "not a YUV4MPEG2 stream"),
+            Self::InvalidHeader(field) => write!(f, "invalid Y4M header field {field:?}"),
+            Self::Unsupported(field) => write!(f, "unsupported Y4M header field {field:?}"),
+            Self::InvalidFrameHeader => write!(f, "invalid Y4M frame header"),
+        }
+    }
+}
+
+impl std::error::Error for Y4mError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Y4mError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Parsed Y4M stream header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Y4mHeader {
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate_numerator: u32,
+    pub frame_rate_denominator: u32,
+    pub color_format: ColorFormat,
+    pub bit_depth: u32,
+    pub chroma_sample_position: ChromaSamplePosition,
+    /// From `XCOLORRANGE` extension, studio range if absent
+    pub color_range: ColorRange,
+}
+
+impl Y4mHeader {
+    /// Parse header line, without trailing newline
+    pub fn parse(line: &[u8]) -> Result<Self, Y4mError> {
+        let mut fields = line.split(|&b| b == b' ').filter(|f| !f.is_empty());
+        if fields.next() != Some(SIGNATURE) {
+            return Err(Y4mError::InvalidSignature);
+        }
+
+        let mut width = None;
+        let mut height = None;
+        let mut frame_rate = None;
+        let mut colorspace = None;
+        let mut xyscss = None;
+        let mut color_range = ColorRange::CrStudioRange;
+        for field in fields {
+            let field = String::from_utf8_lossy(field);
+            let invalid = || Y4mError::InvalidHeader(field.to_string());
+            let mut chars = field.chars();
+            let tag = chars.next();
+            let value = chars.as_str();
+            match tag {
+                Some('W') => width = Some(value.parse::<u32>().map_err(|_| invalid())?),
+                Some('H') => height = Some(value.parse::<u32>().map_err(|_| invalid())?),
+                Some('F') => {
+                    let (num, den) = value.split_once(':').ok_or_else(invalid)?;
+                    let num = num.parse::<u32>().map_err(|_| invalid())?;
+                    let den = den.parse::<u32>().map_err(|_| invalid())?;
+                    if num == 0 || den == 0 {
+                        return Err(invalid());
+                    }
+                    frame_rate = Some((num, den));
+                }
+                Some('I') if !matches!(value, "p" | "?") => {
+                    return Err(Y4mError::Unsupported(field.to_string()));
+                }
+                Some('C') => colorspace = Some(value.to_string()),
+                Some('X') => {
+                    if let Some(value) = value.strip_prefix("YSCSS=") {
+                        xyscss = Some(value.to_ascii_lowercase());
+                    } else if let Some(value) = value.strip_prefix("COLORRANGE=") {
+                        color_range = match value {
+                            "LIMITED" => ColorRange::CrStudioRange,
+                            "FULL" => ColorRange::CrFullRange,
+                            _ => return Err(invalid()),
+                        };
+                    }
+                }
+                // Aspect ratio and unknown fields don't affect encoding
+                _ => {}
+            }
+        }
+
+        let missing = |name: &str| Y4mError::InvalidHeader(name.to_string());
+        let width = width.ok_or_else(|| missing("W"))?;
+        let height = height.ok_or_else(|| missing("H"))?;
+        let (frame_rate_numerator, frame_rate_denominator) =
+            frame_rate.ok_or_else(|| missing("F"))?;
+        // Colorspace defaults to 4:2:0 JPEG siting, XYSCSS is used by some writers without C tag
+        let colorspace = colorspace
+            .or(xyscss)
+            .unwrap_or_else(|| "420jpeg".to_string());
+        let (color_format, bit_depth, chroma_sample_position) = parse_colorspace(&colorspace)
+            .ok_or_else(|| Y4mError::Unsupported(format!("C{colorspace}")))?;
+
+        Ok(Self {
+            width,
+            height,
+            frame_rate_numerator,
+            frame_rate_denominator,
+            color_format,
+            bit_depth,
+            chroma_sample_position,
+            color_range,
+        })
+    }
+
+    /// Set source size, frame rate, bit depth, color format and color range
+    pub fn apply(&self, config: &mut SvtAv1EncConfiguration) {
+        config.source_width = self.width;
+        config.source_height = self.height;
+        config.frame_rate_numerator = self.frame_rate_numerator;
+        config.frame_rate_denominator = self.frame_rate_denominator;
+        config.encoder_bit_depth = self.bit_depth;
+        config.encoder_color_format = self.color_format;
+        config.chroma_sample_position = self.chroma_sample_position;
+        config.color_range = self.color_range;
+    }
+
+    /// Create encoder config with default settings and header parameters applied. Fails like
+    /// [`SvtAv1EncoderConfig::try_new`], e.g. for picture size encoder doesn't support
+    pub fn encoder_config(&self, preset: Option<i8>) -> Result<SvtAv1EncoderConfig, ConfigError> {
+        let mut config = SvtAv1EncoderConfig::try_new(self.width, self.height, preset)?;
+        self.apply(&mut config.config);
+        Ok(config)
+    }
+
+    /// Layout of frames returned by [`Y4mReader::read_frame`]
+    pub fn layout(&self) -> PlanarLayout {
+        PlanarLayout {
+            width: self.width,
+            height: self.height,
+            color_format: self.color_format,
+            bit_depth: self.bit_depth,
+        }
+    }
+
+    /// Size of one frame data in bytes
+    pub fn frame_size(&self) -> usize {
+        self.layout().frame_size()
+    }
+}
+
+/// Colorspace tag into color format, bit depth and chroma siting
+fn parse_colorspace(tag: &str) -> Option<(ColorFormat, u32, ChromaSamplePosition)> {
+    use ChromaSamplePosition::*;
+    let tag = tag.to_ascii_lowercase();
+    // High bit depth is `p` suffix with number, like `420p10`
+    let (format, depth) = match tag.rsplit_once('p') {
+        Some((format, depth)) if !depth.is_empty() && depth.bytes().all(|b| b.is_ascii_digit()) => {
+            (format, depth.parse().ok()?)
+        }
+        _ => (tag.as_str(), 8),
+    };
+    if !matches!(depth, 8 | 10 | 12) {
+        return None;
+    }
+    Some(match format {
+        "420" | "420jpeg" => (ColorFormat::YUV420, depth, CspUnknown),
+        "420mpeg2" => (ColorFormat::YUV420, depth, CspVertical),
+        "420paldv" => (ColorFormat::YUV420, depth, CspColocated),
+        "422" => (ColorFormat::YUV422, depth, CspUnknown),
+        "444" => (ColorFormat::YUV444, depth, CspUnknown),
+        "mono" => (ColorFormat::YUV400, depth, CspUnknown),
+        _ => return None,
+    })
+}
+
+/// Read line up to `\n`, without it. Returns `false` on end of stream before any byte
+fn read_line<R: BufRead>(reader: &mut R, line: &mut Vec<u8>) -> Result<bool, Y4mError> {
+    line.clear();
+    let read = io::Read::take(reader, MAX_LINE as u64).read_until(b'\n', line)?;
+    if read == 0 {
+        return Ok(false);
+    }
+    if line.pop() != Some(b'\n') {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(true)
+}
+
+/// Reader of Y4M stream
+#[derive(Debug)]
+pub struct Y4mReader<R: BufRead> {
+    reader: R,
+    header: Y4mHeader,
+    buffer: Vec<u8>,
+    line: Vec<u8>,
+}
+
+impl<R: BufRead> Y4mReader<R> {
+    /// Read and parse stream header
+    pub fn new(mut reader: R) -> Result<Self, Y4mError> {
+        let mut line = Vec::new();
+        if !read_line(&mut reader, &mut line)? {
+            return Err(Y4mError::InvalidSignature);
+        }
+        let header = Y4mHeader::parse(&line)?;
+        Ok(Self {
+            reader,
+            header,
+            buffer: vec![0; header.frame_size()],
+            line,
+        })
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    /// Read next frame, `None` at the end of stream. Frame borrows internal buffer,
+    /// which is reused for the next frame
+    pub fn read_frame(&mut self) -> Result<Option<Frame<'_>>, Y4mError> {
+        if !read_line(&mut self.reader, &mut self.line)? {
+            return Ok(None);
+        }
+        // Frame parameters after marker are ignored
+        if !self.line.starts_with(FRAME) {
+            return Err(Y4mError::InvalidFrameHeader);
+        }
+        self.reader.read_exact(&mut self.buffer)?;
+
+        Ok(Some(self.header.layout().frame(&self.buffer)))
+    }
+
+    /// Return underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_config;
+
+    #[test]
+    fn header() {
+        let header = Y4mHeader::parse(
+            b"YUV4MPEG2 W1920 H1080 F30000:1001 Ip A1:1 C420p10 XYSCSS=420P10 XCOLORRANGE=FULL",
+        )
+        .unwrap();
+        assert_eq!(header.width, 1920);
+        assert_eq!(header.height, 1080);
+        assert_eq!(
+            (header.frame_rate_numerator, header.frame_rate_denominator),
+            (30000, 1001)
+        );
+        assert_eq!(header.color_format, ColorFormat::YUV420);
+        assert_eq!(header.bit_depth, 10);
+        assert_eq!(header.color_range, ColorRange::CrFullRange);
+        assert_eq!(header.frame_size(), 1920 * 1080 * 3);
+
+        let mut config = test_config(64, 64);
+        header.apply(&mut config);
+        assert_eq!(config.source_width, 1920);
+        assert_eq!(config.encoder_bit_depth, 10);
+        assert_eq!(config.frame_rate_numerator, 30000);
+        assert_eq!(config.color_range, ColorRange::CrFullRange);
+
+        let header = Y4mHeader::parse(b"YUV4MPEG2 W65 H33 F25:1 XYSCSS=420PALDV").unwrap();
+        assert_eq!(header.bit_depth, 8);
+        assert_eq!(
+            header.chroma_sample_position,
+            ChromaSamplePosition::CspColocated
+        );
+        assert_eq!(header.color_range, ColorRange::CrStudioRange);
+        assert_eq!(header.frame_size(), 65 * 33 + 2 * 33 * 17);
+
+        let header = Y4mHeader::parse(b"YUV4MPEG2 W64 H64 F25:1 C444p12").unwrap();
+        assert_eq!(header.color_format, ColorFormat::YUV444);
+        assert_eq!(header.bit_depth, 12);
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert!(matches!(
+            Y4mHeader::parse(b"YUV4MPEG W64 H64 F25:1"),
+            Err(Y4mError::InvalidSignature)
+        ));
+        assert!(matches!(
+            Y4mHeader::parse(b"YUV4MPEG2 W64 F25:1"),
+            Err(Y4mError::InvalidHeader(_))
+        ));
+        assert!(matches!(
+            Y4mHeader::parse(b"YUV4MPEG2 W64 H64 F25:0"),
+            Err(Y4mError::InvalidHeader(_))
+        ));
+        assert!(matches!(
+            Y4mHeader::parse(b"YUV4MPEG2 W64 H64 F25:1 It"),
+            Err(Y4mError::Unsupported(_))
+        ));
+        assert!(matches!(
+            Y4mHeader::parse(b"YUV4MPEG2 W64 H64 F25:1 C411"),
+            Err(Y4mError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn frames() {
+        let mut data = b"YUV4MPEG2 W3 H2 F25:1 C420jpeg\n".to_vec();
+        data.extend_from_slice(b"FRAME\n");
+        data.extend_from_slice(&[1, 1, 1, 1, 1, 1, 2, 2, 3, 3]);
+        data.extend_from_slice(b"FRAME Ixyz\n");
+        data.extend_from_slice(&[4, 4, 4, 4, 4, 4, 5, 5, 6, 6]);
+
+        let mut reader = Y4mReader::new(&data[..]).unwrap();
+        assert_eq!(reader.header().frame_size(), 10);
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.luma, [1; 6]);
+        assert_eq!(frame.cb, [2, 2]);
+        assert_eq!(frame.cr, [3, 3]);
+        assert_eq!(
+            (frame.y_stride, frame.cb_stride, frame.cr_stride),
+            (3, 2, 2)
+        );
+        assert_eq!(frame.size, 10);
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.cr, [6, 6]);
+        assert!(reader.read_frame().unwrap().is_none());
+
+        // Truncated frame
+        let mut reader = Y4mReader::new(&data[..data.len() - 1]).unwrap();
+        assert!(reader.read_frame().is_ok());
+        assert!(matches!(reader.read_frame(), Err(Y4mError::Io(_))));
+    }
+}
@@ -0,0 +1,361 @@
+//! Headerless (raw) YUV input.
+//!
+//! [`YuvReader`] reads frames of known size and [`PixelFormat`] and returns them as [`Frame`]s.
+//! With seekable input it can start at any frame and stop after a range, which allows
+//! to split one file between several encoders.
+//!
+//! ```no_run
+//! # use svt_av1_psyex_enc::yuv::{PixelFormat, YuvReader};
+//! let file = std::fs::File::open("capture.yuv").unwrap();
+//! let mut reader = YuvReader::new(file, 1920, 1080, PixelFormat::P010, 60, 1);
+//! // Encode second hundred of frames only
+//! reader.set_range(100..200).unwrap();
+//! let encoder = reader.encoder_config(None).unwrap().into_encoder().unwrap();
+//! while let Some(frame) = reader.read_frame().unwrap() {
+//!     encoder.send_picture(frame, None, false).unwrap();
+//! }
+//! encoder.send_eos().unwrap();
+//! ```
+use crate::{
+    convert::semi_planar_to_planar,
+    ffi::{ColorFormat, SvtAv1EncConfiguration},
+    ConfigError, Frame, SvtAv1EncoderConfig,
+};
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+/// Planar picture with tightly packed planes, as accepted by encoder.
+/// High bit depth samples take 2 bytes, little-endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanarLayout {
+    pub width: u32,
+    pub height: u32,
+    pub color_format: ColorFormat,
+    pub bit_depth: u32,
+}
+
+impl PlanarLayout {
+    /// Bytes per sample, 2 for high bit depth
+    pub fn sample_size(&self) -> usize {
+        if self.bit_depth > 8 {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Chroma plane width and height in samples
+    pub fn chroma_size(&self) -> (u32, u32) {
+        match self.color_format {
+            ColorFormat::YUV400 => (0, 0),
+            ColorFormat::YUV420 => (self.width.div_ceil(2), self.height.div_ceil(2)),
+            ColorFormat::YUV422 => (self.width.div_ceil(2), self.height),
+            ColorFormat::YUV444 => (self.width, self.height),
+        }
+    }
+
+    /// Size of one frame in bytes
+    pub fn frame_size(&self) -> usize {
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let samples = self.width as usize * self.height as usize
+            + 2 * chroma_width as usize * chroma_height as usize;
+        samples * self.sample_size()
+    }
+
+    /// Split buffer of [`frame_size`] bytes into planes
+    ///
+    /// [`frame_size`]: method@Self::frame_size
+    pub fn frame<'a>(&self, buffer: &'a [u8]) -> Frame<'a> {
+        assert_eq!(buffer.len(), self.frame_size());
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let luma_len = self.width as usize * self.height as usize * self.sample_size();
+        let chroma_len = chroma_width as usize * chroma_height as usize * self.sample_size();
+        let (luma, chroma) = buffer.split_at(luma_len);
+        let (cb, cr) = chroma.split_at(chroma_len);
+        Frame::new(
+            luma,
+            cb,
+            cr,
+            self.width,
+            chroma_width,
+            chroma_width,
+            buffer.len() as u32,
+        )
+    }
+}
+
+/// Pixel format of raw YUV file
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 8-bit planar 4:2:0
+    I420,
+    /// 8-bit planar 4:2:2
+    I422,
+    /// 8-bit planar 4:4:4
+    I444,
+    /// 10-bit 4:2:0 with interleaved chroma, samples in high bits of 16-bit little-endian words
+    P010,
+    /// 10-bit planar 4:2:0, samples in low bits of 16-bit little-endian words
+    Yuv420p10le,
+}
+
+impl PixelFormat {
+    pub fn color_format(self) -> ColorFormat {
+        match self {
+            Self::I422 => ColorFormat::YUV422,
+            Self::I444 => ColorFormat::YUV444,
+            Self::I420 | Self::P010 | Self::Yuv420p10le => ColorFormat::YUV420,
+        }
+    }
+
+    pub fn bit_depth(self) -> u32 {
+        match self {
+            Self::I420 | Self::I422 | Self::I444 => 8,
+            Self::P010 | Self::Yuv420p10le => 10,
+        }
+    }
+}
+
+/// Reader of headerless YUV files
+#[derive(Debug)]
+pub struct YuvReader<R> {
+    reader: R,
+    format: PixelFormat,
+    layout: PlanarLayout,
+    frame_rate_numerator: u32,
+    frame_rate_denominator: u32,
+    /// Frame as read from file, when it needs conversion
+    raw: Vec<u8>,
+    buffer: Vec<u8>,
+    /// Index of the next frame
+    position: u64,
+    end: Option<u64>,
+}
+
+impl<R: Read> YuvReader<R> {
+    /// Create reader of frames with given size, format and frame rate
+    pub fn new(
+        reader: R,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        frame_rate_numerator: u32,
+        frame_rate_denominator: u32,
+    ) -> Self {
+        let layout = PlanarLayout {
+            width,
+            height,
+            color_format: format.color_format(),
+            bit_depth: format.bit_depth(),
+        };
+        let raw = match format {
+            PixelFormat::P010 => vec![0; layout.frame_size()],
+            _ => Vec::new(),
+        };
+        Self {
+            reader,
+            format,
+            layout,
+            frame_rate_numerator,
+            frame_rate_denominator,
+            raw,
+            buffer: vec![0; layout.frame_size()],
+            position: 0,
+            end: None,
+        }
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Layout of frames returned by [`read_frame`]
+    ///
+    /// [`read_frame`]: method@Self::read_frame
+    pub fn layout(&self) -> &PlanarLayout {
+        &self.layout
+    }
+
+    /// Index of the frame, which will be read next
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Set source size, frame rate, bit depth and color format
+    pub fn apply(&self, config: &mut SvtAv1EncConfiguration) {
+        config.source_width = self.layout.width;
+        config.source_height = self.layout.height;
+        config.frame_rate_numerator = self.frame_rate_numerator;
+        config.frame_rate_denominator = self.frame_rate_denominator;
+        config.encoder_bit_depth = self.layout.bit_depth;
+        config.encoder_color_format = self.layout.color_format;
+    }
+
+    /// Create encoder config with default settings and input parameters applied. Fails like
+    /// [`SvtAv1EncoderConfig::try_new`], e.g. for picture size encoder doesn't support
+    pub fn encoder_config(&self, preset: Option<i8>) -> Result<SvtAv1EncoderConfig, ConfigError> {
+        let mut config =
+            SvtAv1EncoderConfig::try_new(self.layout.width, self.layout.height, preset)?;
+        self.apply(&mut config.config);
+        Ok(config)
+    }
+
+    /// Read next frame, `None` at the end of file or range. Frame borrows internal buffer,
+    /// which is reused for the next frame
+    pub fn read_frame(&mut self) -> io::Result<Option<Frame<'_>>> {
+        if self.end.is_some_and(|end| self.position >= end) {
+            return Ok(None);
+        }
+        let target = match self.format {
+            PixelFormat::P010 => &mut self.raw,
+            _ => &mut self.buffer,
+        };
+        if !read_full(&mut self.reader, target)? {
+            return Ok(None);
+        }
+        if self.format == PixelFormat::P010 {
+            // Chroma rows have twice as many samples as chroma plane width
+            let (chroma_width, _) = self.layout.chroma_size();
+            semi_planar_to_planar(
+                &self.layout,
+                &self.raw,
+                self.layout.width as usize * 2,
+                chroma_width as usize * 4,
+                &mut self.buffer,
+            );
+        }
+        self.position += 1;
+        Ok(Some(self.layout.frame(&self.buffer)))
+    }
+
+    /// Return underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> YuvReader<R> {
+    /// Number of whole frames in input
+    pub fn frame_count(&mut self) -> io::Result<u64> {
+        let current = self.reader.stream_position()?;
+        let len = self.reader.seek(SeekFrom::End(0))?;
+        let _ = self.reader.seek(SeekFrom::Start(current))?;
+        Ok(len / self.layout.frame_size() as u64)
+    }
+
+    /// Move to frame with index `frame`
+    pub fn seek_frame(&mut self, frame: u64) -> io::Result<()> {
+        let _ = self
+            .reader
+            .seek(SeekFrom::Start(frame * self.layout.frame_size() as u64))?;
+        self.position = frame;
+        Ok(())
+    }
+
+    /// Read only frames in range. Moves to the start of range
+    pub fn set_range(&mut self, frames: Range<u64>) -> io::Result<()> {
+        self.seek_frame(frames.start)?;
+        self.end = Some(frames.end);
+        Ok(())
+    }
+
+    /// Read frames until the end of file again after [`set_range`], position isn't changed
+    ///
+    /// [`set_range`]: method@Self::set_range
+    pub fn clear_range(&mut self) {
+        self.end = None;
+    }
+}
+
+/// Fill buffer, `false` if input ended before first byte. Partial frame is an error
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn layout() {
+        let layout = PlanarLayout {
+            width: 5,
+            height: 3,
+            color_format: ColorFormat::YUV420,
+            bit_depth: 10,
+        };
+        assert_eq!(layout.chroma_size(), (3, 2));
+        assert_eq!(layout.frame_size(), (15 + 2 * 6) * 2);
+        let buffer = vec![0; layout.frame_size()];
+        let frame = layout.frame(&buffer);
+        assert_eq!(
+            (frame.luma.len(), frame.cb.len(), frame.cr.len()),
+            (30, 12, 12)
+        );
+        assert_eq!((frame.y_stride, frame.cb_stride), (5, 3));
+
+        let layout = PlanarLayout {
+            color_format: ColorFormat::YUV422,
+            bit_depth: 8,
+            ..layout
+        };
+        assert_eq!(layout.frame_size(), 15 + 2 * 9);
+    }
+
+    #[test]
+    fn seek_and_range() {
+        // 2x2 I420 frames of 6 bytes, filled with frame index
+        let data: Vec<u8> = (0..5).flat_map(|i| [i; 6]).collect();
+        let mut reader = YuvReader::new(Cursor::new(data), 2, 2, PixelFormat::I420, 25, 1);
+        assert_eq!(reader.frame_count().unwrap(), 5);
+        assert_eq!(reader.read_frame().unwrap().unwrap().luma, [0; 4]);
+
+        reader.set_range(2..4).unwrap();
+        assert_eq!(reader.position(), 2);
+        assert_eq!(reader.read_frame().unwrap().unwrap().cb, [2]);
+        assert_eq!(reader.read_frame().unwrap().unwrap().cr, [3]);
+        assert!(reader.read_frame().unwrap().is_none());
+
+        reader.seek_frame(4).unwrap();
+        assert!(reader.read_frame().unwrap().is_none());
+        reader.clear_range();
+        assert!(reader.read_frame().unwrap().is_some());
+        assert!(reader.read_frame().unwrap().is_none());
+
+        // Truncated last frame
+        let mut reader = YuvReader::new(&[0u8; 10][..], 2, 2, PixelFormat::I420, 25, 1);
+        assert!(reader.read_frame().unwrap().is_some());
+        assert_eq!(
+            reader.read_frame().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn p010() {
+        // 2x2 frame, luma 1..=4, cb 5, cr 6
+        let mut data = Vec::new();
+        for sample in [1u16, 2, 3, 4, 5, 6] {
+            data.extend_from_slice(&(sample << 6).to_le_bytes());
+        }
+        let mut reader = YuvReader::new(&data[..], 2, 2, PixelFormat::P010, 25, 1);
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.luma, [1, 0, 2, 0, 3, 0, 4, 0]);
+        assert_eq!(frame.cb, [5, 0]);
+        assert_eq!(frame.cr, [6, 0]);
+        assert_eq!(frame.y_stride, 2);
+    }
+}
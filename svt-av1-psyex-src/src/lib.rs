@@ -0,0 +1,181 @@
+//! Build SVT-AV1-PSYEX from source as static library. Intended for use in build scripts.
+//!
+//! ```no_run
+//! let artifacts = svt_av1_psyex_src::Build::new().build();
+//! artifacts.print_cargo_metadata();
+//! ```
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Version of bundled source
+pub const VERSION: &str = "3.0.2-A";
+
+/// Upstream repository, bundled source is its `v{VERSION}` tag
+pub const REPOSITORY: &str = "https://github.com/BlueSwordM/svt-av1-psyex";
+
+/// Directory with bundled source tree
+pub fn source_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("SVT-AV1-PSYEX")
+}
+
+/// Build configuration
+#[derive(Debug, Clone, Default)]
+pub struct Build {
+    source_dir: Option<PathBuf>,
+    out_dir: Option<PathBuf>,
+    avx512: Option<bool>,
+    native: Option<bool>,
+    c_only: Option<bool>,
+}
+
+impl Build {
+    /// Build with source from `SVT_AV1_SRC` env variable or bundled one,
+    /// CPU options are taken from env variables, see [`apply_env`]
+    ///
+    /// [`apply_env`]: method@Self::apply_env
+    pub fn new() -> Self {
+        Self::default().apply_env()
+    }
+
+    /// Read `SVT_AV1_SRC`, `SVT_AV1_AVX512`, `SVT_AV1_NATIVE` and `SVT_AV1_C_ONLY` env variables.
+    /// Options are `1` or `0`, unset ones use CMake defaults
+    pub fn apply_env(mut self) -> Self {
+        if let Some(dir) = env_var("SVT_AV1_SRC") {
+            self.source_dir = Some(dir.into());
+        }
+        let flag = |name| env_var(name).map(|value| value != "0");
+        self.avx512 = flag("SVT_AV1_AVX512").or(self.avx512);
+        self.native = flag("SVT_AV1_NATIVE").or(self.native);
+        self.c_only = flag("SVT_AV1_C_ONLY").or(self.c_only);
+        self
+    }
+
+    /// Build from source tree in `dir` instead of bundled one
+    pub fn source_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.source_dir = Some(dir.into());
+        self
+    }
+
+    /// Install directory, `OUT_DIR` by default
+    pub fn out_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.out_dir = Some(dir.into());
+        self
+    }
+
+    /// Build AVX-512 kernels (`ENABLE_AVX512`)
+    pub fn avx512(&mut self, enable: bool) -> &mut Self {
+        self.avx512 = Some(enable);
+        self
+    }
+
+    /// Optimize for CPU of the build machine (`NATIVE`)
+    pub fn native(&mut self, enable: bool) -> &mut Self {
+        self.native = Some(enable);
+        self
+    }
+
+    /// Build without assembly (`COMPILE_C_ONLY`), NASM isn't needed then
+    pub fn c_only(&mut self, enable: bool) -> &mut Self {
+        self.c_only = Some(enable);
+        self
+    }
+
+    /// Configure, build and install library. Panics with explanation on failure, like build scripts do
+    pub fn build(&self) -> Artifacts {
+        let source_dir = self.source_dir.clone().unwrap_or_else(source_dir);
+        let cmake_lists = source_dir.join("CMakeLists.txt");
+        let cmake_lists = fs::read_to_string(&cmake_lists).unwrap_or_else(|_| {
+            panic!(
+                "SVT-AV1-PSYEX source not found in {0}, check out {VERSION} source tree there with \
+                 `git clone --depth 1 --branch v{VERSION} {REPOSITORY} {0}` or set SVT_AV1_SRC env variable",
+                source_dir.display()
+            )
+        });
+        let expected = VERSION.split('-').next().unwrap();
+        match project_version(&cmake_lists) {
+            Some(version) if version == expected => {}
+            version => panic!(
+                "SVT-AV1-PSYEX source in {} is version {}, expected {VERSION}",
+                source_dir.display(),
+                version.unwrap_or("unknown")
+            ),
+        }
+        println!("cargo:rerun-if-changed={}", source_dir.display());
+
+        let mut cfg = cmake::Config::new(&source_dir);
+        if let Some(out_dir) = &self.out_dir {
+            let _ = cfg.out_dir(out_dir);
+        }
+        let _ = cfg
+            .profile("Release")
+            .define("BUILD_SHARED_LIBS", "OFF")
+            .define("BUILD_APPS", "OFF")
+            .define("BUILD_TESTING", "OFF")
+            .define("CMAKE_INSTALL_LIBDIR", "lib");
+        let options = [
+            ("ENABLE_AVX512", self.avx512),
+            ("NATIVE", self.native),
+            ("COMPILE_C_ONLY", self.c_only),
+        ];
+        for (name, value) in options {
+            if let Some(value) = value {
+                let _ = cfg.define(name, if value { "ON" } else { "OFF" });
+            }
+        }
+        let install_dir = cfg.build();
+
+        Artifacts {
+            lib_dir: install_dir.join("lib"),
+            include_dir: install_dir.join("include"),
+        }
+    }
+}
+
+/// Installed library
+#[derive(Debug, Clone)]
+pub struct Artifacts {
+    pub lib_dir: PathBuf,
+    pub include_dir: PathBuf,
+}
+
+impl Artifacts {
+    /// Print instructions to link static library and its system dependencies
+    pub fn print_cargo_metadata(&self) {
+        println!("cargo:rustc-link-search=native={}", self.lib_dir.display());
+        println!("cargo:rustc-link-lib=static=SvtAv1Enc");
+        if env::var("CARGO_CFG_UNIX").is_ok() {
+            println!("cargo:rustc-link-lib=pthread");
+            println!("cargo:rustc-link-lib=m");
+        }
+        println!("cargo:include={}", self.include_dir.display());
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    println!("cargo:rerun-if-env-changed={name}");
+    env::var(name).ok()
+}
+
+/// Version from `project(... VERSION x.y.z ...)` call
+fn project_version(cmake_lists: &str) -> Option<&str> {
+    let start = cmake_lists.find("project(")?;
+    let project = &cmake_lists[start..];
+    let project = &project[..project.find(')')?];
+    let mut words = project.split_whitespace();
+    let _ = words.find(|&word| word == "VERSION")?;
+    words.next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version() {
+        let cmake_lists = "cmake_minimum_required(VERSION 3.16)\nproject(svt-av1\n    VERSION 3.0.2\n    LANGUAGES C CXX)\n";
+        assert_eq!(project_version(cmake_lists), Some("3.0.2"));
+        assert_eq!(project_version("project(svt-av1 C)"), None);
+    }
+}
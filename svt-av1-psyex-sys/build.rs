@@ -0,0 +1,378 @@
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Structure from layout file
+struct Layout {
+    rust: String,
+    c: Option<String>,
+    size: usize,
+    align: usize,
+    /// Name, offset and size
+    fields: Vec<(String, usize, usize)>,
+}
+
+/// Supported API versions: feature, layout file and library version for pkg-config
+const VERSIONS: &[(&str, &str, &str)] = &[("V3_0", "layout/v3_0.txt", "3.0.2")];
+
+fn main() {
+    let selected = VERSIONS
+        .iter()
+        .filter(|(feature, ..)| env::var(format!("CARGO_FEATURE_{feature}")).is_ok())
+        .collect::<Vec<_>>();
+    let &[&(_, layout_file, library_version)] = &selected[..] else {
+        panic!("Please choose exactly one SVT-AV1-PSYEX API version feature: `v3_0`")
+    };
+    println!("cargo:rerun-if-changed={layout_file}");
+    let mut layouts = parse_layouts(&fs::read_to_string(layout_file).unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    if env::var("DOCS_RS").is_ok() {
+        write_rust(&layouts, &out_dir);
+        return;
+    }
+
+    let static_feature = env::var("CARGO_FEATURE_STATIC").is_ok();
+    let dynamic_feature = env::var("CARGO_FEATURE_DYNAMIC").is_ok();
+    if static_feature && dynamic_feature {
+        panic!("Please choose only one of the next features: `static` or `dynamic`")
+    }
+    let mut include_dirs = Vec::new();
+    if static_feature || dynamic_feature {
+        include_dirs = link(static_feature, library_version);
+    }
+    println!("cargo:rerun-if-env-changed=SVT_AV1_INCLUDE");
+    if let Ok(dir) = env::var("SVT_AV1_INCLUDE") {
+        include_dirs.push(dir.into());
+    }
+    // Expected layout is for 64-bit targets
+    if env::var("CARGO_CFG_TARGET_POINTER_WIDTH").as_deref() == Ok("64") {
+        check_c_layout(&mut layouts, layout_file, &include_dirs, &out_dir);
+    }
+    write_rust(&layouts, &out_dir);
+}
+
+/// Write layout assertions and length of configuration padding, which follows the layout so
+/// fields added in its place are caught by layout check
+fn write_rust(layouts: &[Layout], out_dir: &Path) {
+    fs::write(out_dir.join("layout.rs"), rust_assertions(layouts)).unwrap();
+    let &(_, _, padding) = layouts
+        .iter()
+        .find(|layout| layout.rust == "SvtAv1EncConfiguration")
+        .and_then(|layout| layout.fields.iter().find(|(field, ..)| field == "padding"))
+        .expect("SvtAv1EncConfiguration::padding missing in layout file");
+    fs::write(
+        out_dir.join("padding.rs"),
+        format!("pub(crate) const SVT_AV1_ENC_CONFIGURATION_PADDING: usize = {padding};\n"),
+    )
+    .unwrap();
+}
+
+/// Print link instructions, returns include directories of found library
+fn link(static_feature: bool, library_version: &str) -> Vec<PathBuf> {
+    if let Some(include_dir) = build_vendored() {
+        return vec![include_dir];
+    }
+    if static_feature {
+        println!("cargo:rustc-link-lib=static=SvtAv1Enc");
+    } else {
+        println!("cargo:rustc-link-lib=dylib=SvtAv1Enc");
+    }
+    println!("cargo:rerun-if-env-changed=SVT_AV1_LIB");
+    match env::var("SVT_AV1_LIB") {
+        // User specify path, lookup for libraries files there
+        Ok(path) => {
+            println!("cargo:rustc-link-search=native={}", path);
+            // Headers of installation prefix (`lib` next to `include`)
+            // or of build tree (`Bin/Release` in source directory)
+            let path = Path::new(&path);
+            let mut include_dirs = Vec::new();
+            if let Some(prefix) = path.parent() {
+                include_dirs.push(prefix.join("include"));
+                if let Some(source_dir) = prefix.parent() {
+                    include_dirs.push(source_dir.join("Source/API"));
+                }
+            }
+            include_dirs
+        }
+        // No user specified path, use pkg_config to find lib in system
+        Err(_) => {
+            let mut cfg = pkg_config::Config::new();
+            cfg.exactly_version(library_version);
+            if static_feature {
+                cfg.statik(true);
+            }
+            let res = cfg.probe("SvtAv1Enc").map_err(|_| "Couldn't find SvtAv1Enc library, install it with your distro package manager or build it yourself and provide path to it with SVT_AV1_LIB env variable").unwrap();
+            for p in res.link_files {
+                println!("cargo:rustc-link-search=native={}", p.display());
+            }
+            res.include_paths
+        }
+    }
+}
+
+/// Build bundled source or one from SVT_AV1_SRC, it's linked statically
+#[cfg(feature = "vendored")]
+fn build_vendored() -> Option<PathBuf> {
+    let artifacts = svt_av1_psyex_src::Build::new().build();
+    artifacts.print_cargo_metadata();
+    Some(artifacts.include_dir)
+}
+
+#[cfg(not(feature = "vendored"))]
+fn build_vendored() -> Option<PathBuf> {
+    None
+}
+
+fn parse_layouts(text: &str) -> Vec<Layout> {
+    let mut layouts = Vec::<Layout>::new();
+    for line in text.lines() {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let number = |word: &str| word.parse::<usize>().unwrap();
+        match words[..] {
+            [] => {}
+            [comment, ..] if comment.starts_with('#') => {}
+            ["struct", rust, c, size, align] => layouts.push(Layout {
+                rust: rust.into(),
+                c: (c != "-").then(|| c.into()),
+                size: number(size),
+                align: number(align),
+                fields: Vec::new(),
+            }),
+            [field, offset, size] => {
+                let layout = layouts.last_mut().expect("field before struct");
+                layout
+                    .fields
+                    .push((field.into(), number(offset), number(size)));
+            }
+            _ => panic!("invalid layout line: {line}"),
+        }
+    }
+    layouts
+}
+
+fn rust_assertions(layouts: &[Layout]) -> String {
+    let mut out = String::from(
+        "use super::*;
+use std::{mem::{align_of, offset_of, size_of, MaybeUninit}, ptr::addr_of};
+
+const fn size_of_pointee<T>(_: *const T) -> usize {
+    size_of::<T>()
+}
+",
+    );
+    for Layout {
+        rust,
+        size,
+        align,
+        fields,
+        ..
+    } in layouts
+    {
+        let _ = writeln!(
+            out,
+            "const _: () = assert!(size_of::<{rust}>() == {size}, \"size of {rust}\");"
+        );
+        let _ = writeln!(
+            out,
+            "const _: () = assert!(align_of::<{rust}>() == {align}, \"alignment of {rust}\");"
+        );
+        for (field, offset, size) in fields {
+            let _ = writeln!(
+                out,
+                "const _: () = {{
+    assert!(offset_of!({rust}, {field}) == {offset}, \"offset of {rust}::{field}\");
+    let value = MaybeUninit::<{rust}>::uninit();
+    let field = unsafe {{ addr_of!((*value.as_ptr()).{field}) }};
+    assert!(size_of_pointee(field) == {size}, \"size of {rust}::{field}\");
+}};"
+            );
+        }
+    }
+    out
+}
+
+/// Check layouts against C headers found in include directories. When build machine can run
+/// target programs, layouts of structures with C name are read from compiled C program, and
+/// build fails if they differ from layout file, which Rust structures are asserted to match.
+/// When cross compiling, values of layout file are checked in C with static assertions.
+fn check_c_layout(
+    layouts: &mut [Layout],
+    layout_file: &str,
+    include_dirs: &[PathBuf],
+    out_dir: &Path,
+) {
+    let Some(header) = include_dirs.iter().find_map(|dir| {
+        [dir.join("EbSvtAv1Enc.h"), dir.join("svt-av1/EbSvtAv1Enc.h")]
+            .into_iter()
+            .find(|header| header.is_file())
+    }) else {
+        println!(
+            "cargo:warning=EbSvtAv1Enc.h not found, bindings are checked only against {layout_file}. \
+             Set SVT_AV1_INCLUDE to directory with SVT-AV1-PSYEX headers to check them against C structures"
+        );
+        return;
+    };
+    println!("cargo:rerun-if-changed={}", header.display());
+    if env::var("HOST") != env::var("TARGET") {
+        println!(
+            "cargo:warning=Cross compiling, C structures are checked against {layout_file} \
+             with static assertions instead of reading layout from headers"
+        );
+        assert_c_layout(layouts, &header, out_dir);
+        return;
+    }
+
+    let c_layouts = read_c_layout(layouts, &header, out_dir);
+    let mut differences = Vec::new();
+    for layout in layouts.iter_mut().filter(|layout| layout.c.is_some()) {
+        let c_layout = c_layouts
+            .iter()
+            .find(|c_layout| c_layout.rust == layout.rust)
+            .expect("structure missing in layout read from headers");
+        let expected = (layout.size, layout.align);
+        let found = (c_layout.size, c_layout.align);
+        if expected != found {
+            differences.push(format!(
+                "{}: size and alignment {found:?} in headers, {expected:?} expected",
+                layout.rust
+            ));
+        }
+        for ((field, offset, size), (_, c_offset, c_size)) in
+            layout.fields.iter().zip(&c_layout.fields)
+        {
+            if (offset, size) != (c_offset, c_size) {
+                differences.push(format!(
+                    "{}::{field}: offset and size {:?} in headers, {:?} expected",
+                    layout.rust,
+                    (c_offset, c_size),
+                    (offset, size)
+                ));
+            }
+        }
+        layout.size = c_layout.size;
+        layout.align = c_layout.align;
+        layout.fields.clone_from(&c_layout.fields);
+    }
+    if !differences.is_empty() {
+        // Mismatch is undefined behavior at every call taking these structures
+        let file = out_dir.join("layout.txt");
+        fs::write(&file, format_layouts(layouts)).unwrap();
+        panic!(
+            "{layout_file} doesn't match C headers {}, bindings must be updated:\n{}\nLayout read from headers is written to {}",
+            header.display(),
+            differences.join("\n"),
+            file.display()
+        );
+    }
+}
+
+/// Compile and run C program printing layouts of structures with C name, in layout file format.
+/// Names of structures and fields are taken from layout file, values only from headers
+fn read_c_layout(layouts: &[Layout], header: &Path, out_dir: &Path) -> Vec<Layout> {
+    let mut out = format!(
+        "#include <stddef.h>\n#include <stdio.h>\n#include \"{}\"\n\nint main(void) {{\n",
+        header.display().to_string().replace('\\', "/")
+    );
+    for layout in layouts {
+        let Some(c) = &layout.c else {
+            continue;
+        };
+        let rust = &layout.rust;
+        let _ = writeln!(
+            out,
+            "    printf(\"struct {rust} {c} %zu %zu\\n\", sizeof({c}), _Alignof({c}));"
+        );
+        for (field, ..) in &layout.fields {
+            let _ = writeln!(
+                out,
+                "    printf(\"    {field} %zu %zu\\n\", offsetof({c}, {field}), sizeof((({c} *)0)->{field}));"
+            );
+        }
+    }
+    out.push_str("    return 0;\n}\n");
+    let file = out_dir.join("layout_read.c");
+    fs::write(&file, out).unwrap();
+
+    let program = out_dir.join(format!("layout_read{}", env::consts::EXE_SUFFIX));
+    let compiler = cc::Build::new()
+        .include(header.parent().unwrap())
+        .cargo_metadata(false)
+        .get_compiler();
+    let mut command = compiler.to_command();
+    command.arg(&file);
+    if compiler.is_like_msvc() {
+        command.arg(format!("/Fe{}", program.display()));
+    } else {
+        command.arg("-o").arg(&program);
+    }
+    let status = command.status().expect("failed to run C compiler");
+    assert!(
+        status.success(),
+        "failed to compile {}, structures or fields of layout file are missing in headers",
+        file.display()
+    );
+    let output = Command::new(&program)
+        .output()
+        .expect("failed to run layout reading program");
+    assert!(output.status.success(), "layout reading program failed");
+    parse_layouts(&String::from_utf8(output.stdout).unwrap())
+}
+
+fn format_layouts(layouts: &[Layout]) -> String {
+    let mut out = String::new();
+    for layout in layouts {
+        let c = layout.c.as_deref().unwrap_or("-");
+        let _ = writeln!(
+            out,
+            "struct {} {c} {} {}",
+            layout.rust, layout.size, layout.align
+        );
+        for (field, offset, size) in &layout.fields {
+            let _ = writeln!(out, "    {field} {offset} {size}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Compile C file with static assertions for values of layout file
+fn assert_c_layout(layouts: &[Layout], header: &Path, out_dir: &Path) {
+    let mut out = format!(
+        "#include <stddef.h>\n#include \"{}\"\n",
+        header.display().to_string().replace('\\', "/")
+    );
+    for layout in layouts {
+        let Some(c) = &layout.c else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "_Static_assert(sizeof({c}) == {}, \"size of {c}\");",
+            layout.size
+        );
+        for (field, offset, size) in &layout.fields {
+            if field == "padding" {
+                continue;
+            }
+            let _ = writeln!(
+                out,
+                "_Static_assert(offsetof({c}, {field}) == {offset}, \"offset of {c}.{field}\");"
+            );
+            let _ = writeln!(
+                out,
+                "_Static_assert(sizeof((({c} *)0)->{field}) == {size}, \"size of {c}.{field}\");"
+            );
+        }
+    }
+    let file = out_dir.join("layout_check.c");
+    fs::write(&file, out).unwrap();
+    let _ = cc::Build::new()
+        .file(&file)
+        .include(header.parent().unwrap())
+        .cargo_metadata(false)
+        .compile_intermediates();
+}
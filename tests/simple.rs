@@ -13,10 +13,62 @@ fn get_y4m_input() -> Vec<u8> {
     data
 }
 
+/// Size and planar 4:2:0 frames of test input
+fn get_y4m_frames() -> (u32, u32, Vec<Vec<u8>>) {
+    let data = get_y4m_input();
+    let mut dec = ::y4m::decode(&*data).unwrap();
+    let (width, height) = (dec.get_width() as u32, dec.get_height() as u32);
+    let mut frames = Vec::new();
+    while let Ok(frame) = dec.read_frame() {
+        let mut planar = frame.get_y_plane().to_vec();
+        planar.extend_from_slice(frame.get_u_plane());
+        planar.extend_from_slice(frame.get_v_plane());
+        frames.push(planar);
+    }
+    (width, height, frames)
+}
+
+/// Encode planar frames of config layout with pts given by frame number, returns all packets
+fn encode_frames(
+    config: SvtAv1EncoderConfig,
+    frames: &[Vec<u8>],
+    pts: impl Fn(i64) -> i64,
+) -> (SvtAv1Encoder, Vec<OwnedPacket>) {
+    let layout = yuv::PlanarLayout {
+        width: config.config.source_width,
+        height: config.config.source_height,
+        color_format: config.config.encoder_color_format,
+        bit_depth: config.config.encoder_bit_depth,
+    };
+    let svt_encoder = config.into_encoder().expect("Encoder");
+    for (number, frame) in frames.iter().enumerate() {
+        svt_encoder
+            .send_picture(layout.frame(frame), Some(pts(number as i64)), false)
+            .expect("Send frame");
+    }
+    svt_encoder.send_eos().expect("Send eos");
+    let packets = svt_encoder
+        .drain()
+        .map(|packet| OwnedPacket::from(packet.expect("Packet")))
+        .collect();
+    (svt_encoder, packets)
+}
+
+/// Pts of shown key frames
+fn shown_keyframes(packets: &[OwnedPacket]) -> Vec<i64> {
+    packets
+        .iter()
+        .filter(|packet| {
+            packet.is_keyframe() && !packet.flags.contains(ffi::SvtFlags::BUFFERFLAG_IS_ALT_REF)
+        })
+        .map(|packet| packet.pts)
+        .collect()
+}
+
 #[test]
 fn encode_decode_from_y4m() {
     let data = get_y4m_input();
-    let mut dec = y4m::decode(&*data).unwrap();
+    let mut dec = ::y4m::decode(&*data).unwrap();
     let width = dec.get_width();
     let height = dec.get_height();
     let framerate = dec.get_framerate();
@@ -78,3 +130,272 @@ fn encode_decode_from_y4m() {
 
     assert_eq!(encoded_frames, decoded_frames);
 }
+
+#[cfg(feature = "y4m")]
+#[test]
+fn y4m_reader() {
+    let data = get_y4m_input();
+    let mut expected = ::y4m::decode(&*data).unwrap();
+    let mut reader = svt_av1_psyex_enc::y4m::Y4mReader::new(&*data).unwrap();
+    let header = *reader.header();
+    assert_eq!(header.width, expected.get_width() as u32);
+    assert_eq!(header.height, expected.get_height() as u32);
+    assert_eq!(
+        header.frame_rate_numerator,
+        expected.get_framerate().num as u32
+    );
+
+    let mut svt_config = header.encoder_config(Some(12)).expect("Config");
+    svt_config.config.force_key_frames = true;
+    let svt_encoder = svt_config.into_encoder().expect("Encoder");
+    let mut frames = 0;
+    while let Some(frame) = reader.read_frame().unwrap() {
+        let expected = expected.read_frame().unwrap();
+        assert_eq!(
+            header.frame_size(),
+            expected.get_y_plane().len()
+                + expected.get_u_plane().len()
+                + expected.get_v_plane().len()
+        );
+        svt_encoder
+            .send_picture(frame, Some(frames), false)
+            .expect("Send frame");
+        frames += 1;
+    }
+    assert!(expected.read_frame().is_err());
+    svt_encoder.send_eos().expect("Send eos");
+
+    let packets = svt_encoder
+        .drain()
+        .map(|packet| packet.expect("Packet"))
+        .filter(|packet| !packet.is_empty())
+        .count();
+    assert!(packets > 0);
+}
+
+#[test]
+fn pipeline() {
+    use svt_av1_psyex_enc::{pipeline::EncoderPipeline, pool::FramePool};
+
+    let data = get_y4m_input();
+    let dec = ::y4m::decode(&*data).unwrap();
+    let mut svt_config =
+        SvtAv1EncoderConfig::new(dec.get_width() as u32, dec.get_height() as u32, Some(12));
+    svt_config.config.encoder_bit_depth = 8;
+    let pool = FramePool::from_config(&svt_config.config);
+    let mut pipeline = EncoderPipeline::new(svt_config.into_encoder().expect("Encoder"), 2);
+
+    // Producer runs concurrently with packets being received below
+    let frames = pipeline.frame_sender().unwrap();
+    pipeline.send_eos();
+    let producer = std::thread::spawn(move || {
+        let mut dec = ::y4m::decode(&*data).unwrap();
+        let mut sent = 0;
+        while let Ok(frame) = dec.read_frame() {
+            let mut planar = frame.get_y_plane().to_vec();
+            planar.extend_from_slice(frame.get_u_plane());
+            planar.extend_from_slice(frame.get_v_plane());
+            let mut owned = pool.get();
+            owned.copy_from_planar(&planar);
+            owned.pts = Some(sent);
+            frames.send(owned).expect("Send frame");
+            sent += 1;
+        }
+        sent
+    });
+
+    let mut pts = Vec::new();
+    for packet in pipeline.iter() {
+        let packet = packet.expect("Packet");
+        if !packet.is_empty() && !packet.flags.contains(ffi::SvtFlags::BUFFERFLAG_IS_ALT_REF) {
+            pts.push(packet.pts);
+        }
+    }
+    let sent = producer.join().unwrap();
+    pts.sort_unstable();
+    assert_eq!(pts, (0..sent).collect::<Vec<_>>());
+}
+
+#[test]
+fn chunked() {
+    use std::{io::Cursor, sync::Mutex};
+    use svt_av1_psyex_enc::{
+        chunked::ChunkedEncoder,
+        yuv::{PixelFormat, YuvReader},
+    };
+
+    let (width, height, frames) = get_y4m_frames();
+    let raw = frames.concat();
+    let source = Mutex::new(YuvReader::new(
+        Cursor::new(raw),
+        width,
+        height,
+        PixelFormat::I420,
+        30,
+        1,
+    ));
+    let frame_count = source.lock().unwrap().frame_count().unwrap();
+    let middle = frame_count / 2;
+
+    let encoder = ChunkedEncoder::new(
+        || source.lock().unwrap().encoder_config(Some(12)).unwrap(),
+        2,
+    );
+    let mut pts = Vec::new();
+    let mut keyframes = Vec::new();
+    let header = encoder
+        .encode(&source, &[0..middle, middle..frame_count], |packet| {
+            if packet.is_keyframe() {
+                keyframes.push(packet.pts);
+            }
+            if !packet.flags.contains(ffi::SvtFlags::BUFFERFLAG_IS_ALT_REF) {
+                pts.push(packet.pts);
+            }
+            Ok(())
+        })
+        .expect("Chunked encode");
+    assert!(!header.is_empty());
+    assert_eq!(keyframes, [0, middle as i64]);
+    pts.sort_unstable();
+    assert_eq!(pts, (0..frame_count as i64).collect::<Vec<_>>());
+}
+
+#[test]
+fn scene_detection_keyframes() {
+    use svt_av1_psyex_enc::scene::SceneOptions;
+
+    // Frame 6 cuts to inverted luma
+    let (width, height, input) = get_y4m_frames();
+    let luma_len = (width * height) as usize;
+    let mut frames = vec![input[0].clone(); 12];
+    for frame in &mut frames[6..] {
+        frame[..luma_len]
+            .iter_mut()
+            .for_each(|sample| *sample = !*sample);
+    }
+    let mut svt_config = SvtAv1EncoderConfig::new(width, height, Some(12));
+    svt_config.config.encoder_bit_depth = 8;
+    svt_config.set_scene_detection(Some(SceneOptions {
+        min_interval: 2,
+        ..Default::default()
+    }));
+    let (svt_encoder, packets) = encode_frames(svt_config, &frames, |number| number);
+    assert_eq!(shown_keyframes(&packets), [0, 6]);
+    assert!(svt_encoder.take_missed_keyframes().is_empty());
+}
+
+#[test]
+fn keyframe_schedule() {
+    use svt_av1_psyex_enc::keyframes::KeyframeSchedule;
+
+    let (width, height, input) = get_y4m_frames();
+    let frames = input.iter().cycle().take(12).cloned().collect::<Vec<_>>();
+    let mut svt_config = SvtAv1EncoderConfig::new(width, height, Some(12));
+    svt_config.config.encoder_bit_depth = 8;
+    svt_config.set_keyframe_schedule(Some(KeyframeSchedule::Frames(vec![7, 3])));
+    let (svt_encoder, packets) = encode_frames(svt_config, &frames, |number| number * 10);
+    assert_eq!(shown_keyframes(&packets), [0, 30, 70]);
+    assert!(svt_encoder.take_missed_keyframes().is_empty());
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_stream() {
+    use futures::{executor::block_on, SinkExt, StreamExt};
+    use svt_av1_psyex_enc::{pool::FramePool, stream::encoder_stream};
+
+    let mut svt_config = SvtAv1EncoderConfig::new(64, 64, Some(12));
+    svt_config.config.encoder_bit_depth = 8;
+    let pool = FramePool::from_config(&svt_config.config);
+    let (mut frames, mut packets) = encoder_stream(svt_config.into_encoder().expect("Encoder"), 2);
+
+    let producer = async move {
+        for pts in 0..10 {
+            let mut frame = pool.get();
+            frame.pts = Some(pts);
+            frames.send(frame).await.expect("Send frame");
+        }
+        frames.close().await.expect("Close");
+    };
+    let consumer = async move {
+        let mut eos = false;
+        while let Some(packet) = packets.next().await {
+            eos = packet
+                .expect("Packet")
+                .flags
+                .contains(ffi::SvtFlags::BUFFERFLAG_EOS);
+        }
+        eos
+    };
+    let ((), eos) = block_on(futures::future::join(producer, consumer));
+    assert!(eos);
+
+    // Dropping stream in the middle of encoding sends EOS and releases encoder in background
+    let svt_config = SvtAv1EncoderConfig::new(64, 64, Some(12));
+    let pool = FramePool::from_config(&svt_config.config);
+    let (mut frames, packets) = encoder_stream(svt_config.into_encoder().expect("Encoder"), 2);
+    block_on(frames.send(pool.get())).expect("Send frame");
+    drop(packets);
+    assert!(block_on(frames.send(pool.get())).is_err());
+}
+
+#[test]
+fn packet_iterators() {
+    let mut svt_config = SvtAv1EncoderConfig::new(64, 64, Some(12));
+    svt_config.config.encoder_bit_depth = 8;
+    let svt_encoder = svt_config.into_encoder().expect("Encoder");
+    let luma = vec![128; 64 * 64];
+    let chroma = vec![128; 32 * 32];
+    let mut packets = 0;
+    for pts in 0..10 {
+        let frame = Frame::new(&luma, &chroma, &chroma, 64, 32, 32, 64 * 64 * 3 / 2);
+        svt_encoder
+            .send_picture(frame, Some(pts), false)
+            .expect("Send frame");
+        // Non-blocking, returns only packets which are already done
+        for packet in svt_encoder.packets() {
+            packet.expect("Packet");
+            packets += 1;
+        }
+    }
+    svt_encoder.send_eos().expect("Send eos");
+    let mut eos = false;
+    for packet in svt_encoder.drain() {
+        let packet = packet.expect("Packet");
+        eos = packet.flags.contains(ffi::SvtFlags::BUFFERFLAG_EOS);
+        packets += 1;
+    }
+    assert!(eos);
+    assert!(packets >= 10);
+    assert!(svt_encoder.drain().next().is_none());
+    assert!(svt_encoder.packets().next().is_none());
+}
+
+// Run under AddressSanitizer (see README) to catch reads of released packets
+#[test]
+fn packets_outlive_encoder() {
+    let svt_encoder = SvtAv1EncoderConfig::new(64, 64, Some(12))
+        .into_encoder()
+        .expect("Encoder");
+    let luma = vec![128; 64 * 64];
+    let chroma = vec![128; 32 * 32];
+    for pts in 0..10 {
+        let frame = Frame::new(&luma, &chroma, &chroma, 64, 32, 32, 64 * 64 * 3 / 2);
+        svt_encoder
+            .send_picture(frame, Some(pts), false)
+            .expect("Send frame");
+    }
+    svt_encoder.send_eos().expect("Send eos");
+    // Keep first packets and drop encoder before it's drained
+    let held = svt_encoder
+        .drain()
+        .take(2)
+        .map(|packet| packet.expect("Packet"))
+        .collect::<Vec<_>>();
+    drop(svt_encoder);
+
+    let sizes = std::thread::spawn(move || held.iter().map(|packet| packet.len()).sum::<usize>())
+        .join()
+        .unwrap();
+    assert!(sizes > 0);
+}